@@ -1,10 +1,13 @@
 use std::path::Path;
+use std::io::Read;
 use crate::bus::types::*;
 use crate::mos;
 use crate::ppu;
 use crate::controller;
 use crate::cartridge;
-use std::io::Result;
+use crate::cartridge::types::Reset;
+use crate::mixer;
+use crate::error::Result;
 
 impl Bus {
     pub fn set_controller_a(&mut self, state : u8){
@@ -43,8 +46,22 @@ impl Bus {
         if dma_hold {
             self.tick_dma();
         } else {
+            self.poll_irq();
             mos::tick(self);
         }
+        self.tick_cart();
+    }
+    fn poll_irq(&mut self){
+        let instruction_boundary = self.cpu.cycles == 0;
+        if instruction_boundary && self.cart.irq() {
+            mos::irq(self);
+        }
+    }
+    fn tick_cart(&mut self){
+        self.cart.cpu_tick();
+        // The 2A03 channels are not emulated yet, so cartridge expansion audio is the only source.
+        let level = self.cart.audio_output();
+        self.mixer.push(level);
     }
     fn tick_ppu(&mut self){
         ppu::tick(self);
@@ -69,31 +86,103 @@ impl Bus {
         self.data.display[address]
     }
 
-    pub fn reset(&mut self){
+    // Cold start. Every chip, RAM included, goes back to its initial state.
+    pub fn power_on(&mut self){
         self.context = Context{dma_page: 0, dma_byte: 0, dma_cycle: 0, dma_hold: false, clock: 0};
         mos::reset(self);
         self.ppu.reset();
-        self.cart.reset();
+        self.cart.reset(Reset::PowerOn);
         self.data = Data { cpu_ram: [0; 0x800], nt_ram: [0; 0x800], pal_ram: [0; 0x20], display: [0x0; 256 * 240] };
         self.controller_a.reset();
         self.controller_b.reset();
+        self.mixer.reset();
+    }
+
+    // Same as power_on, as `reset` always has been.
+    pub fn reset(&mut self){
+        self.power_on();
+    }
+
+    // The console's reset button. RAM survives, and so does whatever state the cartridge keeps
+    // across resets.
+    pub fn soft_reset(&mut self){
+        self.context = Context{dma_page: 0, dma_byte: 0, dma_cycle: 0, dma_hold: false, clock: 0};
+        mos::reset(self);
+        self.ppu.reset();
+        self.cart.reset(Reset::Soft);
+        self.controller_a.reset();
+        self.controller_b.reset();
+        self.mixer.reset();
     }
 
     pub fn copy_to_screen(&self, screen : &mut [u8; 256 * 240]){
         screen.copy_from_slice(&self.data.display);
     }
+
+    // Moves the samples produced since the last call into `buffer`. Only the last second
+    // (mixer::MAX_SAMPLES) is kept, so call this at least once per emulated second.
+    pub fn drain_audio(&mut self, buffer : &mut Vec<f32>){
+        self.mixer.drain(buffer);
+    }
 }
 
-pub fn load<T : AsRef<Path>>(filepath : T) -> Result<Bus> {
+fn build(cart : cartridge::Cartridge) -> Bus {
     let context = Context{dma_page: 0, dma_byte: 0, dma_cycle: 0, dma_hold: false, clock: 0};
     let cpu = mos::new();
     let ppu = ppu::new();
-    let cart = cartridge::load(filepath)?;
     let data = Data { cpu_ram: [0; 0x800], nt_ram: [0; 0x800], pal_ram: [0; 0x20], display: [0x0; 256 * 240] };
     let controller_a = controller::new();
     let controller_b = controller::new();
+    let mixer = mixer::new();
+
+    let mut bus = Bus { context, cpu, ppu, cart, data, controller_a, controller_b, mixer };
+    bus.power_on();
+    bus
+}
+
+pub fn load<T : AsRef<Path>>(filepath : T) -> Result<Bus> {
+    let cart = cartridge::load(filepath)?;
+    Ok(build(cart))
+}
+
+// Same as load, for a ROM or a .zip or .gz archive that is already in memory.
+pub fn load_from_bytes(data : &[u8]) -> Result<Bus> {
+    let cart = cartridge::load_from_bytes(data)?;
+    Ok(build(cart))
+}
+
+// Same as load_from_bytes, reading the ROM from any reader.
+pub fn load_from_reader<R : Read>(reader : R) -> Result<Bus> {
+    let cart = cartridge::load_from_reader(reader)?;
+    Ok(build(cart))
+}
+
+// Same as load, with the given IPS, UPS or BPS patches instead of the ones next to the ROM.
+pub fn load_with_patches<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, patches : &[U]) -> Result<Bus> {
+    let cart = cartridge::load_with_patches(filepath, patches)?;
+    Ok(build(cart))
+}
+
+// Same as load, with the board taken from a generic discrete board description.
+pub fn load_with_board<T : AsRef<Path>>(filepath : T, description : &str) -> Result<Bus> {
+    let cart = cartridge::load_with_board(filepath, description)?;
+    Ok(build(cart))
+}
+
+// Same as load_with_board, with the given patches instead of the ones next to the ROM.
+pub fn load_with_board_and_patches<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, patches : &[U], description : &str) -> Result<Bus> {
+    let cart = cartridge::load_with_board_and_patches(filepath, patches, description)?;
+    Ok(build(cart))
+}
+
+// Famicom Disk System image, run with the given BIOS.
+pub fn load_fds<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, bios_path : U) -> Result<Bus> {
+    let cart = cartridge::load_fds(filepath, bios_path)?;
+    Ok(build(cart))
+}
 
-    let mut bus = Bus { context, cpu, ppu, cart, data, controller_a, controller_b };
-    bus.reset();
-    Ok(bus)
+// NSF or NSFe tune, played by a synthetic cartridge.
+pub fn load_nsf<T : AsRef<Path>>(filepath : T) -> Result<Bus> {
+    let cart = cartridge::load_nsf(filepath)?;
+    Ok(build(cart))
 }
@@ -59,28 +59,19 @@ impl Bus {
 
     // PPU Read
     fn ppu_read_pt(&mut self, address : u16) -> u8 {
-        self.cart.ppu_read(address)
+        match self.cart.chr_ciram(address) {
+            Some(page) => self.data.nt_ram[page * 0x400 + (address & 0x03FF) as usize],
+            None => self.cart.ppu_read(address)
+        }
     }
     fn ppu_read_nt(&mut self, address : u16) -> u8 {
-        let mirroring = self.cart.header.h_mirroring;
-        let nametable_choice = (address & 0x1FFF) >> 10;
-        let base_address = if mirroring == cartridge::Mirroring::Horizontal {
-            match nametable_choice {
-                0 => 0x000,
-                1 => 0x400,
-                2 => 0x000,
-                _ => 0x400
-            }
-        } else {
-            match nametable_choice {
-                0 => 0x000,
-                1 => 0x000,
-                2 => 0x400,
-                _ => 0x400
+        match self.cart.nametable(address) {
+            cartridge::Nametable::Ciram(page) => {
+                let mapped_address = page * 0x400 + (address & 0x03FF) as usize;
+                self.data.nt_ram[mapped_address]
             }
-        };
-        let mapped_address = base_address + (address & 0x03FF);
-        self.data.nt_ram[mapped_address as usize]
+            cartridge::Nametable::Cartridge => self.cart.nt_read(address)
+        }
     }
     fn ppu_read_pal(&mut self, address : u16) -> u8 {
         let mapped_address = match address & 0x1F {
@@ -95,28 +86,19 @@ impl Bus {
 
     // PPU Write
     fn ppu_write_pt(&mut self, address : u16, byte : u8) {
-        self.cart.ppu_write(address, byte);
+        match self.cart.chr_ciram(address) {
+            Some(page) => { self.data.nt_ram[page * 0x400 + (address & 0x03FF) as usize] = byte; }
+            None => self.cart.ppu_write(address, byte)
+        }
     }
     fn ppu_write_nt(&mut self, address : u16, byte : u8) {
-        let mirroring = self.cart.header.h_mirroring;
-        let nametable_choice = (address & 0x1FFF) >> 10;
-        let base_address = if mirroring == cartridge::Mirroring::Horizontal {
-            match nametable_choice {
-                0 => 0x000,
-                1 => 0x400,
-                2 => 0x000,
-                _ => 0x400
+        match self.cart.nametable(address) {
+            cartridge::Nametable::Ciram(page) => {
+                let mapped_address = page * 0x400 + (address & 0x03FF) as usize;
+                self.data.nt_ram[mapped_address] = byte;
             }
-        } else {
-            match nametable_choice {
-                0 => 0x000,
-                1 => 0x000,
-                2 => 0x400,
-                _ => 0x400
-            }
-        };
-        let mapped_address = base_address + (address & 0x03FF);
-        self.data.nt_ram[mapped_address as usize] = byte;
+            cartridge::Nametable::Cartridge => self.cart.nt_write(address, byte)
+        }
     }
     fn ppu_write_pal(&mut self, address : u16, byte : u8) {
         let mapped_address = match address & 0x1F {
@@ -173,6 +155,9 @@ impl ppu::Bus for Bus {
     fn trigger_nmi(&mut self) {
         mos::nmi(self);
     }
+    fn notify_scanline(&mut self) {
+        self.cart.scanline();
+    }
     fn fetch_ppu(&mut self) -> &mut ppu::PPU{
         &mut self.ppu
     }
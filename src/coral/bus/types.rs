@@ -2,6 +2,7 @@ use crate::controller;
 use crate::cartridge;
 use crate::mos;
 use crate::ppu;
+use crate::mixer;
 
 #[derive(Copy, Clone, Debug)] 
 pub struct Data {
@@ -28,6 +29,7 @@ pub struct Bus {
     pub cart : cartridge::Cartridge,
     pub data : Data,
     pub controller_a : controller::Controller,
-    pub controller_b : controller::Controller
+    pub controller_b : controller::Controller,
+    pub mixer : mixer::Mixer
 }
 
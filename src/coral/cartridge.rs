@@ -1,6 +1,12 @@
 pub mod types;
 pub mod loader;
 pub mod mapper;
+pub mod database;
+pub mod disk;
+pub mod patch;
+pub mod nsf;
+pub mod unif;
+pub mod archive;
 
 
 pub use types::*;
@@ -0,0 +1,341 @@
+use crate::coral::cartridge::database::crc32;
+use crate::coral::error::{CoralError, Result};
+
+// ROMs packed in .zip or .gz archives. Both use DEFLATE (RFC 1951): a series of blocks that are
+// either stored, or Huffman coded literals and back references with fixed or dynamic codes. Zip
+// archives must hold a single ROM, optionally next to other files such as a readme.
+
+const GZIP_MAGIC : &[u8] = &[0x1F, 0x8B];
+const ZIP_MAGIC : &[u8] = b"PK\x03\x04";
+const ZIP_END_MAGIC : &[u8] = b"PK\x05\x06";
+const ZIP_ENTRY_MAGIC : &[u8] = b"PK\x01\x02";
+// Larger than any ROM, so that a small archive can't unpack to an unbounded amount of memory.
+pub const MAX_OUTPUT_SIZE : usize = 32 * 1024 * 1024;
+const ROM_EXTENSIONS : &[&str] = &[".nes", ".unf", ".unif", ".fds", ".qd", ".nsf", ".nsfe"];
+
+// Base lengths and extra bits of length codes 257-285, then the same for distance codes 0-29.
+const LENGTH_BASE : [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA : [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE : [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA : [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// Order in which the code length code lengths of a dynamic block are stored.
+const CODE_LENGTH_ORDER : [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+fn invalid(message : &str) -> CoralError {
+    CoralError::InvalidData(format!("Failed to unpack: {}", message))
+}
+
+fn too_large() -> CoralError {
+    invalid(&format!("Unpacked data is larger than {} bytes.", MAX_OUTPUT_SIZE))
+}
+
+// Reads the stream least significant bit first, as DEFLATE packs it.
+struct Bits<'a> {
+    data : &'a [u8],
+    position : usize,
+    bit : u8
+}
+
+impl Bits<'_> {
+    fn read(&mut self, count : u8) -> Result<u32> {
+        let mut value = 0;
+        for index in 0..count {
+            let byte = *self.data.get(self.position).ok_or_else(|| invalid("Compressed data is truncated."))?;
+            value |= (((byte >> self.bit) & 0x01) as u32) << index;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.position += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+    }
+}
+
+// Canonical Huffman code, stored as the number of codes of each length and the symbols sorted by
+// code.
+struct Huffman {
+    counts : [u16; 16],
+    symbols : Vec<u16>
+}
+
+impl Huffman {
+    fn new(lengths : &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = vec![];
+        for length in 1..16 {
+            for (symbol, symbol_length) in lengths.iter().enumerate() {
+                if *symbol_length as usize == length {
+                    symbols.push(symbol as u16);
+                }
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits : &mut Bits) -> Result<u16> {
+        // Codes are packed most significant bit first, so build them up one bit at a time.
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= bits.read(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("Invalid Huffman code."))
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(bits : &mut Bits) -> Result<(Huffman, Huffman)> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = bits.read(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![];
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_length_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or_else(|| invalid("Repeated code length without a previous one."))?, 3 + bits.read(2)? as usize),
+            17 => (0, 3 + bits.read(3)? as usize),
+            _ => (0, 11 + bits.read(7)? as usize)
+        };
+        lengths.resize(lengths.len() + repeat, length);
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("Code lengths overrun the table."));
+    }
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn inflate_block(bits : &mut Bits, output : &mut Vec<u8>, literals : &Huffman, distances : &Huffman) -> Result<()> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        if symbol < 256 {
+            if output.len() >= MAX_OUTPUT_SIZE {
+                return Err(too_large());
+            }
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let code = symbol - 257;
+        if code >= LENGTH_BASE.len() {
+            return Err(invalid("Invalid length code."));
+        }
+        let length = LENGTH_BASE[code] as usize + bits.read(LENGTH_EXTRA[code])? as usize;
+        let code = distances.decode(bits)? as usize;
+        if code >= DISTANCE_BASE.len() {
+            return Err(invalid("Invalid distance code."));
+        }
+        let distance = DISTANCE_BASE[code] as usize + bits.read(DISTANCE_EXTRA[code])? as usize;
+        if distance > output.len() {
+            return Err(invalid("Back reference before the start of the data."));
+        }
+        if output.len() + length > MAX_OUTPUT_SIZE {
+            return Err(too_large());
+        }
+        // Byte by byte, since the copy can overlap what it writes.
+        let start = output.len() - distance;
+        for index in 0..length {
+            output.push(output[start + index]);
+        }
+    }
+}
+
+// Decompresses raw DEFLATE data. Returns the data and the number of bytes it was packed in.
+pub fn inflate(data : &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut bits = Bits { data, position: 0, bit: 0 };
+    let mut output = vec![];
+    loop {
+        let last = bits.read(1)? > 0;
+        match bits.read(2)? {
+            0 => {
+                bits.align();
+                let header = data.get(bits.position..bits.position + 4).ok_or_else(|| invalid("Compressed data is truncated."))?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(invalid("Stored block length does not match its complement."));
+                }
+                let start = bits.position + 4;
+                let stored = data.get(start..start + length as usize).ok_or_else(|| invalid("Compressed data is truncated."))?;
+                if output.len() + stored.len() > MAX_OUTPUT_SIZE {
+                    return Err(too_large());
+                }
+                output.extend_from_slice(stored);
+                bits.position = start + length as usize;
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut bits, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("Invalid block type."))
+        }
+        if last {
+            bits.align();
+            return Ok((output, bits.position));
+        }
+    }
+}
+
+fn u16_at(data : &[u8], offset : usize) -> Result<usize> {
+    let bytes = data.get(offset..offset + 2).ok_or_else(|| invalid("Archive is truncated."))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn u32_at(data : &[u8], offset : usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(|| invalid("Archive is truncated."))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn check_crc(data : &[u8], expected : u32) -> Result<()> {
+    if crc32(data) != expected {
+        return Err(invalid(&format!("CRC32 {:08X} does not match the {:08X} in the archive.", crc32(data), expected)));
+    }
+    Ok(())
+}
+
+// Gzip member (RFC 1952): a 10-byte header with optional fields, DEFLATE data, then the CRC32
+// and size of the original data.
+pub fn gunzip(data : &[u8]) -> Result<Vec<u8>> {
+    if !data.starts_with(GZIP_MAGIC) || data.get(2) != Some(&8) {
+        return Err(invalid("Not a gzip file compressed with DEFLATE."));
+    }
+    let flags = data[3];
+    let mut position = 10;
+    // Extra field, file name, comment and header CRC.
+    if flags & 0x04 > 0 {
+        position += 2 + u16_at(data, position)?;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag > 0 {
+            let end = data.get(position..).and_then(|rest| rest.iter().position(|byte| *byte == 0)).ok_or_else(|| invalid("Archive is truncated."))?;
+            position += end + 1;
+        }
+    }
+    if flags & 0x02 > 0 {
+        position += 2;
+    }
+    let (output, length) = inflate(data.get(position..).ok_or_else(|| invalid("Archive is truncated."))?)?;
+    let trailer = position + length;
+    check_crc(&output, u32_at(data, trailer)?)?;
+    if u32_at(data, trailer + 4)? != output.len() as u32 {
+        return Err(invalid("Size does not match the one in the archive."));
+    }
+    Ok(output)
+}
+
+struct ZipEntry {
+    name : String,
+    method : usize,
+    crc : u32,
+    compressed_size : usize,
+    size : usize,
+    header_offset : usize
+}
+
+fn zip_entries(data : &[u8]) -> Result<Vec<ZipEntry>> {
+    // The end of central directory record is last, unless the archive has a comment.
+    let end = (0..data.len().saturating_sub(21)).rev()
+        .find(|offset| data[*offset..].starts_with(ZIP_END_MAGIC))
+        .ok_or_else(|| invalid("Zip archive has no central directory."))?;
+    let count = u16_at(data, end + 10)?;
+    let mut position = u32_at(data, end + 16)? as usize;
+    let mut entries = vec![];
+    for _ in 0..count {
+        if !data.get(position..).is_some_and(|entry| entry.starts_with(ZIP_ENTRY_MAGIC)) {
+            return Err(invalid("Zip central directory is corrupt."));
+        }
+        let name_length = u16_at(data, position + 28)?;
+        let name = data.get(position + 46..position + 46 + name_length).ok_or_else(|| invalid("Archive is truncated."))?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).to_string(),
+            method: u16_at(data, position + 10)?,
+            crc: u32_at(data, position + 16)?,
+            compressed_size: u32_at(data, position + 20)? as usize,
+            size: u32_at(data, position + 24)? as usize,
+            header_offset: u32_at(data, position + 42)? as usize,
+        });
+        position += 46 + name_length + u16_at(data, position + 30)? + u16_at(data, position + 32)?;
+    }
+    Ok(entries)
+}
+
+fn is_rom(name : &str) -> bool {
+    let name = name.to_lowercase();
+    ROM_EXTENSIONS.iter().any(|extension| name.ends_with(extension))
+}
+
+// Zip archive with a single file, or a single file with a ROM extension among others.
+pub fn unzip(data : &[u8]) -> Result<Vec<u8>> {
+    let files : Vec<ZipEntry> = zip_entries(data)?.into_iter().filter(|entry| !entry.name.ends_with('/')).collect();
+    let roms : Vec<&ZipEntry> = files.iter().filter(|entry| is_rom(&entry.name)).collect();
+    let entry = match (files.len(), roms.len()) {
+        (1, _) => &files[0],
+        (_, 1) => roms[0],
+        (0, _) => return Err(invalid("Zip archive is empty.")),
+        _ => return Err(invalid("Zip archive holds more than one ROM."))
+    };
+
+    let header = entry.header_offset;
+    if !data.get(header..).is_some_and(|local| local.starts_with(ZIP_MAGIC)) {
+        return Err(invalid(&format!("Zip entry {} is corrupt.", entry.name)));
+    }
+    let start = header + 30 + u16_at(data, header + 26)? + u16_at(data, header + 28)?;
+    let compressed = data.get(start..start + entry.compressed_size).ok_or_else(|| invalid("Archive is truncated."))?;
+    let output = match entry.method {
+        0 => compressed.to_vec(),
+        8 => inflate(compressed)?.0,
+        method => return Err(invalid(&format!("Zip entry {} uses unsupported compression method {}.", entry.name, method)))
+    };
+    check_crc(&output, entry.crc)?;
+    if output.len() != entry.size {
+        return Err(invalid("Size does not match the one in the archive."));
+    }
+    Ok(output)
+}
+
+// Contents of a .zip or .gz archive, told apart by their magic. Anything else is returned as is.
+pub fn extract(data : Vec<u8>) -> Result<Vec<u8>> {
+    if data.starts_with(ZIP_MAGIC) {
+        unzip(&data)
+    } else if data.starts_with(GZIP_MAGIC) {
+        gunzip(&data)
+    } else {
+        Ok(data)
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::{OnceLock, RwLock};
+
+use crate::coral::cartridge::types::*;
+use crate::coral::error::{CoralError, Result};
+
+// Game database used to correct bad iNES headers. Games are identified by the CRC32 of their
+// PRG and CHR ROM, so the header itself plays no part in the lookup. The compiled-in entries live
+// in database.txt, one game per line:
+//
+//     3337EC46 | name=Super Mario Bros. | mapper=0 | mirroring=horizontal
+//
+// Known fields are name, board, mapper, submapper, mirroring (horizontal, vertical or
+// four_screen, named as in `Mirroring`), battery (true or false), prg_ram, prg_nvram, chr_ram
+// and chr_nvram (in bytes), region (ntsc, pal, multi or dendy) and device (NES 2.0 default
+// expansion device). Fields that are left out are taken from the header.
+
+pub const DATABASE : &str = include_str!("database.txt");
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub crc : u32,
+    pub name : String,
+    pub board : Option<String>,
+    pub mapper : Option<u16>,
+    pub submapper : Option<u8>,
+    pub mirroring : Option<Mirroring>,
+    pub four_screen : Option<bool>,
+    pub battery : Option<bool>,
+    // PRG RAM, PRG NVRAM, CHR RAM and CHR NVRAM in bytes. Given together or not at all.
+    pub ram_sizes : Option<[usize; 4]>,
+    pub tv_system : Option<TVSystem>,
+    pub expansion_device : Option<u8>,
+}
+
+// A header field that disagreed with the database, with both values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Correction {
+    pub field : &'static str,
+    pub header : String,
+    pub database : String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub crc : u32,
+    pub name : String,
+    pub board : Option<String>,
+    pub corrections : Vec<Correction>,
+}
+
+pub fn crc32(data : &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn invalid(line : usize, message : &str) -> CoralError {
+    let error_message = format!("Game database, line {}: {}", line, message);
+    CoralError::InvalidData(error_message)
+}
+
+fn parse_number<T : TryFrom<u64>>(value : &str, line : usize) -> Result<T> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse::<u64>().ok()
+    };
+    number.and_then(|number| T::try_from(number).ok()).ok_or_else(|| invalid(line, &format!("Invalid number {}", value)))
+}
+
+fn parse_line(text : &str, line : usize) -> Result<Entry> {
+    let mut fields = text.split('|').map(|field| field.trim());
+    let crc = fields.next().unwrap_or_default();
+    let crc = u32::from_str_radix(crc, 16).map_err(|_| invalid(line, &format!("Invalid CRC32 {}", crc)))?;
+    let mut entry = Entry { crc, ..Entry::default() };
+    let mut ram_sizes = [None; 4];
+    for field in fields {
+        let (key, value) = field.split_once('=').ok_or_else(|| invalid(line, "Expected key=value"))?;
+        let value = value.trim();
+        match key.trim() {
+            "name" => { entry.name = value.to_string(); }
+            "board" => { entry.board = Some(value.to_string()); }
+            "mapper" => { entry.mapper = Some(parse_number(value, line)?); }
+            "submapper" => { entry.submapper = Some(parse_number(value, line)?); }
+            "mirroring" => {
+                let (mirroring, four_screen) = match value {
+                    "horizontal" => (Mirroring::Horizontal, false),
+                    "vertical" => (Mirroring::Vertical, false),
+                    "four_screen" => (Mirroring::Horizontal, true),
+                    _ => return Err(invalid(line, &format!("Unknown mirroring {}", value)))
+                };
+                // Four-screen boards ignore the mirroring bit, so only the layout is corrected.
+                if !four_screen {
+                    entry.mirroring = Some(mirroring);
+                }
+                entry.four_screen = Some(four_screen);
+            }
+            "battery" => {
+                entry.battery = match value {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => return Err(invalid(line, "battery must be true or false"))
+                };
+            }
+            "prg_ram" => { ram_sizes[0] = Some(parse_number(value, line)?); }
+            "prg_nvram" => { ram_sizes[1] = Some(parse_number(value, line)?); }
+            "chr_ram" => { ram_sizes[2] = Some(parse_number(value, line)?); }
+            "chr_nvram" => { ram_sizes[3] = Some(parse_number(value, line)?); }
+            "region" => {
+                entry.tv_system = match value {
+                    "ntsc" => Some(TVSystem::NTSC),
+                    "pal" => Some(TVSystem::PAL),
+                    "multi" => Some(TVSystem::MultiRegion),
+                    "dendy" => Some(TVSystem::Dendy),
+                    _ => return Err(invalid(line, &format!("Unknown region {}", value)))
+                };
+            }
+            "device" => { entry.expansion_device = Some(parse_number(value, line)?); }
+            key => { return Err(invalid(line, &format!("Unknown field {}", key))); }
+        }
+    }
+    if ram_sizes.iter().any(|size| size.is_some()) {
+        entry.ram_sizes = Some(ram_sizes.map(|size| size.unwrap_or(0)));
+    }
+    Ok(entry)
+}
+
+pub fn parse(text : &str) -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        entries.push(parse_line(line, index + 1)?);
+    }
+    Ok(entries)
+}
+
+// The compiled-in entries plus the registered ones. A compiled-in database that fails to parse is
+// reported by every lookup instead of panicking.
+fn database() -> Result<&'static RwLock<Vec<Entry>>> {
+    static ENTRIES : OnceLock<std::result::Result<RwLock<Vec<Entry>>, String>> = OnceLock::new();
+    let entries = ENTRIES.get_or_init(|| parse(DATABASE).map(RwLock::new).map_err(|error| error.to_string()));
+    entries.as_ref().map_err(|message| CoralError::InvalidData(message.clone()))
+}
+
+// Adds an entry, replacing any entry with the same CRC32.
+pub fn register(entry : Entry) -> Result<()> {
+    let mut entries = database()?.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    entries.retain(|existing| existing.crc != entry.crc);
+    entries.push(entry);
+    Ok(())
+}
+
+pub fn lookup(crc : u32) -> Result<Option<Entry>> {
+    let entries = database()?.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(entries.iter().find(|entry| entry.crc == crc).cloned())
+}
+
+// CRC32 of the PRG and CHR ROM as stored in the file, without padding.
+pub fn rom_crc(cart : &Cartridge) -> u32 {
+    let prg = &cart.prg_data[..cart.header.h_prg_rom_bytes.min(cart.prg_data.len())];
+    let chr = &cart.chr_data[..cart.header.h_chr_rom_bytes.min(cart.chr_data.len())];
+    crc32(&[prg, chr].concat())
+}
+
+fn correct<T : PartialEq + std::fmt::Debug>(corrections : &mut Vec<Correction>, field : &'static str, value : &mut T, database : Option<T>) {
+    if let Some(database) = database {
+        if *value != database {
+            corrections.push(Correction { field, header: format!("{:?}", value), database: format!("{:?}", database) });
+            *value = database;
+        }
+    }
+}
+
+// Looks the cartridge up and fixes its header where it disagrees with the database.
+pub fn apply(cart : &mut Cartridge) -> Result<Option<Report>> {
+    let crc = rom_crc(cart);
+    let Some(entry) = lookup(crc)? else {
+        return Ok(None);
+    };
+    let header = &mut cart.header;
+    let mut corrections = vec![];
+
+    correct(&mut corrections, "mapper", &mut header.h_mapper, entry.mapper);
+    correct(&mut corrections, "submapper", &mut header.h_submapper, entry.submapper);
+    correct(&mut corrections, "mirroring", &mut header.h_mirroring, entry.mirroring);
+    correct(&mut corrections, "four-screen", &mut header.h_alt_layout, entry.four_screen);
+    correct(&mut corrections, "battery", &mut header.h_battery, entry.battery);
+    correct(&mut corrections, "region", &mut header.h_tv_system, entry.tv_system);
+    correct(&mut corrections, "expansion device", &mut header.h_expansion_device, entry.expansion_device);
+    if let Some(ram_sizes) = entry.ram_sizes {
+        let mut sizes = [header.h_prg_ram_size, header.h_prg_nvram_size, header.h_chr_ram_size, header.h_chr_nvram_size];
+        correct(&mut corrections, "RAM sizes", &mut sizes, Some(ram_sizes));
+        [header.h_prg_ram_size, header.h_prg_nvram_size, header.h_chr_ram_size, header.h_chr_nvram_size] = sizes;
+        header.h_ram_sizes_known = true;
+    }
+
+    Ok(Some(Report { crc, name: entry.name, board: entry.board, corrections }))
+}
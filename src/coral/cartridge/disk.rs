@@ -0,0 +1,157 @@
+use crate::coral::error::{CoralError, Result};
+
+// Famicom Disk System images. Both formats store each side as the sequence of its blocks:
+//
+//     1  Disk info, 56 bytes, starting with "*NINTENDO-HVC*"
+//     2  File amount, 2 bytes
+//     3  File header, 16 bytes, with the size of the file at bytes 13-14
+//     4  File data, 1 byte plus the size from the preceding header
+//
+// .fds sides are 65500 bytes, optionally behind a 16-byte "FDS\x1A" header. .qd sides are 65536
+// bytes and keep the two CRC bytes after every block. The drive works on a raw version of each
+// side that also has the gaps between blocks, each closed by a $80 mark, and the CRCs.
+
+pub const SIDE_SIZE : usize = 65500;
+const QD_SIDE_SIZE : usize = 0x10000;
+const HEADER_SIZE : usize = 16;
+const HEADER_MAGIC : &[u8] = b"FDS\x1A";
+const DISK_MAGIC : &[u8] = b"\x01*NINTENDO-HVC*";
+// Gaps in bytes. The gap before the first block is 28300 bits, the others 976.
+const LEADING_GAP : usize = 28300 / 8;
+const BLOCK_GAP : usize = 976 / 8;
+// Real disks hold a little over 65500 bytes of blocks. The extra room covers the gaps and CRCs.
+const RAW_SIDE_SIZE : usize = 0x14000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskImage {
+    // Each side in the .fds layout.
+    pub sides : Vec<Vec<u8>>
+}
+
+fn invalid(message : String) -> CoralError {
+    CoralError::InvalidData(message)
+}
+
+fn block_length(block_type : u8, file_size : usize) -> Option<usize> {
+    match block_type {
+        1 => Some(56),
+        2 => Some(2),
+        3 => Some(16),
+        4 => Some(1 + file_size),
+        _ => None
+    }
+}
+
+// Blocks of a side, stopping at the first byte that does not start a block.
+fn blocks(side : &[u8], has_crc : bool) -> Vec<&[u8]> {
+    let mut blocks = vec![];
+    let mut position = 0;
+    let mut file_size = 0;
+    while let Some(length) = side.get(position).and_then(|block_type| block_length(*block_type, file_size)) {
+        let block = match side.get(position..position + length) {
+            Some(block) => block,
+            None => break
+        };
+        if block[0] == 3 {
+            file_size = block[13] as usize | ((block[14] as usize) << 8);
+        }
+        blocks.push(block);
+        position += length + if has_crc { 2 } else { 0 };
+    }
+    blocks
+}
+
+fn normalise(side : &[u8], has_crc : bool) -> Vec<u8> {
+    let mut normalised = blocks(side, has_crc).concat();
+    normalised.resize(SIDE_SIZE, 0);
+    normalised
+}
+
+// Whether the data looks like a disk image, with an fwNES header or starting with a raw side.
+pub fn is_disk_image(data : &[u8]) -> bool {
+    data.starts_with(HEADER_MAGIC) || data.starts_with(DISK_MAGIC)
+}
+
+pub fn parse(data : &[u8]) -> Result<DiskImage> {
+    let (data, side_size, side_count, has_crc) = if data.starts_with(HEADER_MAGIC) {
+        let side_count = data.get(4).copied().unwrap_or(0) as usize;
+        (&data[HEADER_SIZE.min(data.len())..], SIDE_SIZE, side_count, false)
+    } else if !data.is_empty() && data.len().is_multiple_of(SIDE_SIZE) {
+        (data, SIDE_SIZE, data.len() / SIDE_SIZE, false)
+    } else if !data.is_empty() && data.len().is_multiple_of(QD_SIDE_SIZE) {
+        (data, QD_SIDE_SIZE, data.len() / QD_SIDE_SIZE, true)
+    } else {
+        return Err(invalid(format!("Failed to parse: {} bytes is not a whole number of disk sides.", data.len())));
+    };
+    if side_count == 0 || data.len() < side_count * side_size {
+        return Err(invalid(format!("Failed to parse: Expected {} disk sides of {} bytes.", side_count, side_size)));
+    }
+    let mut sides = vec![];
+    for (index, side) in data.chunks_exact(side_size).take(side_count).enumerate() {
+        if !side.starts_with(DISK_MAGIC) {
+            return Err(invalid(format!("Failed to parse: Side {} does not start with a disk info block.", index + 1)));
+        }
+        sides.push(normalise(side, has_crc));
+    }
+    Ok(DiskImage { sides })
+}
+
+// One step of the drive's CRC-16, which is CCITT with the bits reversed.
+pub fn update_crc(crc : u16, byte : u8) -> u16 {
+    let mut crc = crc;
+    for bit in 0..8 {
+        let carry = crc & 0x01 > 0;
+        crc >>= 1;
+        if carry {
+            crc ^= 0x8408;
+        }
+        if (byte >> bit) & 0x01 > 0 {
+            crc ^= 0x8000;
+        }
+    }
+    crc
+}
+
+pub fn to_raw(side : &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEADING_GAP];
+    for block in blocks(side, false) {
+        raw.push(0x80);
+        raw.extend_from_slice(block);
+        let crc = [0x80].iter().chain(block).chain(&[0, 0]).fold(0, |crc, byte| update_crc(crc, *byte));
+        raw.extend_from_slice(&crc.to_le_bytes());
+        raw.resize(raw.len() + BLOCK_GAP, 0);
+    }
+    raw.resize(raw.len().max(RAW_SIDE_SIZE), 0);
+    raw
+}
+
+// Inverse of to_raw, for sides that have since been written to by the drive.
+pub fn from_raw(raw : &[u8]) -> Vec<u8> {
+    let mut side = vec![];
+    let mut position = 0;
+    let mut file_size = 0;
+    loop {
+        while raw.get(position) == Some(&0) {
+            position += 1;
+        }
+        if raw.get(position) != Some(&0x80) {
+            break;
+        }
+        position += 1;
+        let length = match raw.get(position).and_then(|block_type| block_length(*block_type, file_size)) {
+            Some(length) => length,
+            None => break
+        };
+        let block = match raw.get(position..position + length) {
+            Some(block) => block,
+            None => break
+        };
+        if block[0] == 3 {
+            file_size = block[13] as usize | ((block[14] as usize) << 8);
+        }
+        side.extend_from_slice(block);
+        position += length + 2;
+    }
+    side.resize(SIDE_SIZE, 0);
+    side
+}
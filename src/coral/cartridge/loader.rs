@@ -1,31 +1,54 @@
-use std::io::{self, Read};
-use std::path::Path;
-use std::fs::File;
-use std::io::Error;
-use std::io::ErrorKind;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::fs;
 
 use crate::coral::utils;
 use crate::coral::cartridge::types::*;
 use crate::coral::cartridge::mapper;
+use crate::coral::cartridge::database;
+use crate::coral::cartridge::disk;
+use crate::coral::cartridge::nsf;
+use crate::coral::cartridge::unif;
+use crate::coral::cartridge::patch;
+use crate::coral::cartridge::archive;
+use crate::coral::error::{CoralError, Result};
 
 fn new_cartridge() -> Cartridge {
     Cartridge {  header: Header 
                 { h_prg_size: 0, 
                   h_chr_size: 0, 
+                  h_prg_rom_bytes: 0,
+                  h_chr_rom_bytes: 0,
                   h_chr_ram: false, 
                   h_mirroring: Mirroring::Horizontal, 
                   h_battery: false, 
                   h_trainer: false, 
                   h_alt_layout: false, 
                   h_mapper: 0, 
+                  h_submapper: 0,
                   h_console: ConsoleType::Undefined, 
                   h_nes2: false, 
+                  h_ram_sizes_known: false,
                   h_prg_ram_size: 0,
-                  h_tv_system: TVSystem::NTSC
+                  h_prg_nvram_size: 0,
+                  h_chr_ram_size: 0,
+                  h_chr_nvram_size: 0,
+                  h_tv_system: TVSystem::NTSC,
+                  h_vs_ppu: VsPpu::Unknown(0),
+                  h_vs_hardware: VsHardware::Unknown(0),
+                  h_extended_console: 0,
+                  h_misc_roms: 0,
+                  h_expansion_device: 0
                 }, 
                 trainer: [0;512],
+                trainer_ram: [0;512],
                 prg_data: vec![], 
                 chr_data: vec![],
+                misc_data: vec![],
+                playchoice: None,
+                database: None,
+                nsf: None,
+                board: None,
                 mapper: mapper::generic_mapper()
             }
 }
@@ -40,21 +63,73 @@ fn get_console_type(byte : u8) -> ConsoleType {
     }
 }
 
-fn process_header(buffer : &mut [u8; 12], cart : &mut Cartridge) -> io::Result<()> {
+fn get_tv_system(byte : u8) -> TVSystem {
+    match byte & 0x03 {
+        0 => TVSystem::NTSC,
+        1 => TVSystem::PAL,
+        2 => TVSystem::MultiRegion,
+        _ => TVSystem::Dendy
+    }
+}
+
+fn get_vs_ppu(nibble : u8) -> VsPpu {
+    match nibble {
+        0 | 1 | 6 | 7 => VsPpu::Rp2c03,
+        2 => VsPpu::Rp2c04_0001,
+        3 => VsPpu::Rp2c04_0002,
+        4 => VsPpu::Rp2c04_0003,
+        5 => VsPpu::Rp2c04_0004,
+        8 => VsPpu::Rc2c05_01,
+        9 => VsPpu::Rc2c05_02,
+        10 => VsPpu::Rc2c05_03,
+        11 => VsPpu::Rc2c05_04,
+        _ => VsPpu::Unknown(nibble)
+    }
+}
+
+fn get_vs_hardware(nibble : u8) -> VsHardware {
+    match nibble {
+        0 => VsHardware::Unisystem,
+        1 => VsHardware::RbiBaseball,
+        2 => VsHardware::TkoBoxing,
+        3 => VsHardware::SuperXevious,
+        4 => VsHardware::IceClimberJapan,
+        5 => VsHardware::DualSystem,
+        6 => VsHardware::RaidOnBungelingBay,
+        _ => VsHardware::Unknown(nibble)
+    }
+}
+
+// NES 2.0 ROM size from the LSB byte and the MSB nibble. An MSB of $F switches to
+// 2^E * (MM * 2 + 1) bytes, with the LSB byte laid out as EEEEEEMM.
+fn rom_size(lsb : u8, msb : u8, unit : usize) -> Result<usize> {
+    if msb != 0x0F {
+        return Ok((((msb as usize) << 8) | lsb as usize) * unit);
+    }
+    let exponent = (lsb >> 2) as u32;
+    let multiplier = ((lsb & 0x03) as usize) * 2 + 1;
+    if exponent > 30 {
+        return Err(CoralError::InvalidHeader("Failed to parse: ROM size in the NES 2.0 header is too large.".to_string()));
+    }
+    Ok((1usize << exponent) * multiplier)
+}
+
+// NES 2.0 RAM sizes are stored as shift counts: 64 << n bytes, or nothing for 0.
+fn ram_size(shift : u8) -> usize {
+    if shift == 0 { 0 } else { 64 << shift }
+}
+
+fn process_header(buffer : &mut [u8; 12], cart : &mut Cartridge) -> Result<()> {
     let prg_size = buffer[0];
     let chr_size = buffer[1];
 
-    cart.header.h_prg_size = prg_size;
-    cart.header.h_chr_size = chr_size;
-    cart.header.h_chr_ram = if chr_size == 0 { true } else { false }; 
-
     // Flag 6
     let flag6 = buffer[2];
     let mirroring = if utils::b0(flag6) {Mirroring::Horizontal} else {Mirroring::Vertical};
     let battery = utils::b1(flag6);
     let trainer = utils::b2(flag6);
     let alt_layout = utils::b3(flag6);
-    let mapper_lsn = flag6 >> 4;
+    let mapper_lsn = (flag6 >> 4) as u16;
 
     cart.header.h_mirroring = mirroring;
     cart.header.h_battery = battery;
@@ -65,41 +140,116 @@ fn process_header(buffer : &mut [u8; 12], cart : &mut Cartridge) -> io::Result<(
     // Flag 7
     let flag7 = buffer[3];
     let console_type = get_console_type(flag7 & 0x3);
-    let nes2 = utils::b3(flag7);
-    let mapper_msn = flag7 & 0xF0;
+    let nes2 = flag7 & 0x0C == 0x08;
+    let mapper_msn = (flag7 & 0xF0) as u16;
 
     cart.header.h_console = console_type;
     cart.header.h_nes2 = nes2;
-    cart.header.h_mapper += mapper_msn;
+    cart.header.h_ram_sizes_known = nes2;
+
+    if nes2 {
+        process_nes2_header(buffer, cart)?;
+        cart.header.h_mapper |= mapper_msn;
+    } else {
+        // Old dumping tools left text such as "DiskDude!" in bytes 7-15, which garbles the upper
+        // mapper nibble. Only trust it if the tail of the header is clean.
+        if buffer[8..].iter().all(|byte| *byte == 0) {
+            cart.header.h_mapper |= mapper_msn;
+        }
+        cart.header.h_prg_rom_bytes = prg_size as usize * 0x4000;
+        cart.header.h_chr_rom_bytes = chr_size as usize * 0x2000;
+
+        // Flag 8: PRG RAM in 8 KB units, where 0 means 8 KB.
+        cart.header.h_prg_ram_size = (buffer[4].max(1) as usize) * 0x2000;
+        if chr_size == 0 {
+            cart.header.h_chr_ram_size = 0x2000;
+        }
+
+        // Flag 9
+        let flag9 = buffer[5];
+        let tv_system = if utils::b0(flag9) {TVSystem::PAL} else {TVSystem::NTSC};
+        cart.header.h_tv_system = tv_system;
+
+        // Flag 10
+        // Not part of the official speficiation. Few emulators honor this. We are no better.
+    }
+
+    // Every board maps PRG ROM at $8000, so none can be built without it.
+    if cart.header.h_prg_rom_bytes == 0 {
+        return Err(CoralError::InvalidHeader("Failed to parse: The header gives no PRG ROM.".to_string()));
+    }
+    let too_large = |_| CoralError::InvalidHeader("Failed to parse: ROM size in the header does not fit in 65535 banks.".to_string());
+    cart.header.h_prg_size = u16::try_from(cart.header.h_prg_rom_bytes.div_ceil(0x4000)).map_err(too_large)?;
+    cart.header.h_chr_size = u16::try_from(cart.header.h_chr_rom_bytes.div_ceil(0x2000)).map_err(too_large)?;
+    cart.header.h_chr_ram = cart.header.h_chr_size == 0;
+    Ok(())
+}
+
+fn process_nes2_header(buffer : &[u8; 12], cart : &mut Cartridge) -> Result<()> {
+    // Byte 8: mapper bits 8-11 and submapper.
+    cart.header.h_mapper |= ((buffer[4] & 0x0F) as u16) << 8;
+    cart.header.h_submapper = buffer[4] >> 4;
 
-    // Flag 8
-    let prg_ram_size = buffer[4];
-    cart.header.h_prg_ram_size = prg_ram_size;
+    // Byte 9: upper nibbles of the ROM sizes.
+    cart.header.h_prg_rom_bytes = rom_size(buffer[0], buffer[5] & 0x0F, 0x4000)?;
+    cart.header.h_chr_rom_bytes = rom_size(buffer[1], buffer[5] >> 4, 0x2000)?;
 
-    // Flag 9
-    let flag9 = buffer[5];
-    let tv_system = if utils::b0(flag9) {TVSystem::NTSC} else {TVSystem::PAL};
+    // Bytes 10 and 11: volatile and battery-backed RAM.
+    cart.header.h_prg_ram_size = ram_size(buffer[6] & 0x0F);
+    cart.header.h_prg_nvram_size = ram_size(buffer[6] >> 4);
+    cart.header.h_chr_ram_size = ram_size(buffer[7] & 0x0F);
+    cart.header.h_chr_nvram_size = ram_size(buffer[7] >> 4);
+
+    // Byte 12: CPU/PPU timing.
+    cart.header.h_tv_system = get_tv_system(buffer[8]);
+
+    // Byte 13: Vs. System type, or the extended console type.
+    match cart.header.h_console {
+        ConsoleType::NVS => {
+            cart.header.h_vs_ppu = get_vs_ppu(buffer[9] & 0x0F);
+            cart.header.h_vs_hardware = get_vs_hardware(buffer[9] >> 4);
+        }
+        ConsoleType::Extended => { cart.header.h_extended_console = buffer[9] & 0x0F; }
+        _ => {}
+    }
 
-    cart.header.h_tv_system = tv_system;
+    // Bytes 14 and 15: miscellaneous ROMs and default expansion device.
+    cart.header.h_misc_roms = buffer[10] & 0x03;
+    cart.header.h_expansion_device = buffer[11] & 0x3F;
+    Ok(())
+}
 
+// Reports how much of `what` is missing if the file ends before `size` bytes. Sizes come from the
+// header, so this is checked before allocating anything for them.
+fn check_section(file : &[u8], size : usize, what : &str) -> Result<()> {
+    if file.len() < size {
+        return Err(CoralError::SizeMismatch { what: what.to_string(), expected: size, found: file.len() });
+    }
+    Ok(())
+}
 
-    // Flag 10
-    // Not part of the official speficiation. Few emulators honor this. We are no better.
-    
-    // Remaining 5 bits: Padding. Set to 0
+// Fills the buffer from the file, or reports how much of `what` is missing.
+fn read_section(file : &mut &[u8], buffer : &mut [u8], what : &str) -> Result<()> {
+    check_section(file, buffer.len(), what)?;
+    file.read_exact(buffer)?;
     Ok(())
 }
 
-fn load_header(file : &mut File, cart : &mut Cartridge) -> io::Result<()> {
+fn load_header(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> {
     let mut magic_numbers : [u8; 4] = [0; 4];
-    file.read_exact(&mut magic_numbers)?;
+    file.read_exact(&mut magic_numbers).map_err(|_| {
+        CoralError::InvalidHeader("Failed to parse: File is too short to be a valid .NES file.".to_string())
+    })?;
 
+    if magic_numbers == [0x46, 0x44, 0x53, 0x1A] {
+        return Err(CoralError::InvalidHeader("Failed to parse: File is a Famicom Disk System image, which needs to be loaded with the FDS BIOS.".to_string()));
+    }
     if magic_numbers != [0x4E, 0x45, 0x53, 0x1A] {
-        return Err(Error::new(ErrorKind::InvalidData, "Failed to parse: Missing magic numbers. File is not a valid .NES file."));
+        return Err(CoralError::InvalidHeader("Failed to parse: Missing magic numbers. File is not a valid .NES file.".to_string()));
     }
 
     let mut header_buffer : [u8; 12] = [0; 12];
-    file.read_exact(&mut header_buffer)?;
+    read_section(file, &mut header_buffer, "the header")?;
 
     process_header(&mut header_buffer, cart)?;
 
@@ -107,52 +257,241 @@ fn load_header(file : &mut File, cart : &mut Cartridge) -> io::Result<()> {
 }
 
 
-// TODO: Implement this
-fn load_trainer(file : &mut File, cart : &mut Cartridge) -> io::Result<()> { 
+fn load_trainer(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> { 
     let has_trainer = cart.header.h_trainer;
 
     if has_trainer {
-        file.read_exact(&mut cart.trainer)?;
+        read_section(file, &mut cart.trainer, "the trainer")?;
+        cart.trainer_ram = cart.trainer;
     }
     Ok(())
 } 
 
-fn load_prg(file : &mut File, cart : &mut Cartridge) -> io::Result<()> {
-    let buffer_size = 0x4000 * (cart.header.h_prg_size as usize);
+// ROMs that are not a whole number of banks are padded with zeros.
+fn load_prg(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> {
+    let buffer_size = cart.header.h_prg_rom_bytes;
+    check_section(file, buffer_size, "the PRG ROM")?;
     cart.prg_data.resize(buffer_size, 0);
-    file.read_exact(&mut cart.prg_data)?;
+    read_section(file, &mut cart.prg_data, "the PRG ROM")?;
+    cart.prg_data.resize(0x4000 * cart.header.h_prg_size as usize, 0);
     Ok(())
 }
 
-fn load_chr(file : &mut File, cart : &mut Cartridge) -> io::Result<()> {
+fn load_chr(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> {
+    check_section(file, cart.header.h_chr_rom_bytes, "the CHR ROM")?;
     let chr_size = if cart.header.h_chr_ram { 1 } else { cart.header.h_chr_size } as usize;
     let chr_data_size = 0x2000 * chr_size;
     cart.chr_data.resize(chr_data_size, 0);
 
-    let buffer_size = 0x2000 * cart.header.h_chr_size as usize;
-    file.read_exact(&mut cart.chr_data[..buffer_size])?;
+    let buffer_size = cart.header.h_chr_rom_bytes;
+    read_section(file, &mut cart.chr_data[..buffer_size], "the CHR ROM")?;
 
     Ok(())
 }
 
-// TODO: Implement this
-fn load_playchoice(_file : &mut File, _cart : &mut Cartridge) -> io::Result<()> {
+// Whatever follows the CHR ROM, e.g. the PCM samples of some Vs. System games or the
+// microcontroller ROMs of a few boards.
+fn load_misc(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> {
+    if cart.header.h_misc_roms > 0 {
+        file.read_to_end(&mut cart.misc_data)?;
+    }
     Ok(())
 }
 
-fn setup_mapper(cart : &mut Cartridge) -> io::Result<()> {
+// N bytes, or None if the file ends first.
+fn read_optional<const N : usize>(file : &mut &[u8]) -> Option<[u8; N]> {
+    let mut buffer = [0; N];
+    file.read_exact(&mut buffer).ok().map(|_| buffer)
+}
+
+// PlayChoice-10 dumps follow the CHR ROM with the 8 KB INST-ROM, then the 16 bytes of PROM data
+// and the 16 bytes of PROM CounterOut.
+fn load_playchoice(file : &mut &[u8], cart : &mut Cartridge) -> Result<()> {
+    if cart.header.h_console != ConsoleType::Playchoice10 || file.is_empty() {
+        return Ok(());
+    }
+    let mut inst_rom = vec![0; 0x2000];
+    read_section(file, &mut inst_rom, "the PlayChoice-10 INST-ROM")?;
+    let prom_data = read_optional::<16>(file);
+    let prom_counter_out = prom_data.and_then(|_| read_optional::<16>(file));
+    cart.playchoice = Some(PlayChoice { inst_rom, prom_data, prom_counter_out });
+    Ok(())
+}
+
+fn setup_mapper(cart : &mut Cartridge) -> Result<()> {
     mapper::choose_mapper(cart)?;
     Ok(())
 }
 
-pub fn load<T : AsRef<Path>>(filepath : T) -> io::Result<Cartridge> {
+// Builds the header of a UNIF image from its chunks, with the mapper looked up by board name.
+fn load_unif(data : &[u8]) -> Result<Cartridge> {
+    let image = unif::parse(data)?;
+    let (mapper, submapper) = unif::board_mapper(&image.board).ok_or_else(|| CoralError::UnsupportedBoard(image.board.clone()))?;
+
+    let mut cart = new_cartridge();
+    cart.header.h_mapper = mapper;
+    cart.header.h_submapper = submapper;
+    cart.header.h_mirroring = image.mirroring.unwrap_or(Mirroring::Vertical);
+    cart.header.h_alt_layout = image.four_screen;
+    cart.header.h_battery = image.battery;
+    cart.header.h_tv_system = image.tv_system;
+    cart.header.h_prg_rom_bytes = image.prg.len();
+    cart.header.h_chr_rom_bytes = image.chr.len();
+    cart.header.h_prg_size = image.prg.len().div_ceil(0x4000) as u16;
+    cart.header.h_chr_size = image.chr.len().div_ceil(0x2000) as u16;
+    cart.header.h_chr_ram = image.chr.is_empty();
+    // UNIF does not give RAM sizes, so assume the usual 8 KB.
+    cart.header.h_prg_ram_size = 0x2000;
+    if image.chr.is_empty() {
+        cart.header.h_chr_ram_size = 0x2000;
+    }
+
+    cart.prg_data = image.prg;
+    cart.prg_data.resize(0x4000 * cart.header.h_prg_size as usize, 0);
+    cart.chr_data = image.chr;
+    cart.chr_data.resize(0x2000 * (cart.header.h_chr_size.max(1) as usize), 0);
+    cart.board = Some(image.board);
+    Ok(cart)
+}
+
+// Reads a file, unpacking it if it is a .zip or .gz archive.
+fn read_file<T : AsRef<Path>>(filepath : T) -> Result<Vec<u8>> {
+    archive::extract(fs::read(filepath)?)
+}
+
+// Reads the ROM and applies the patches to it in order, in memory.
+fn read_patched<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, patches : &[U]) -> Result<Vec<u8>> {
+    let mut data = read_file(filepath)?;
+    for patch_path in patches {
+        let patch_path = patch_path.as_ref();
+        data = patch::apply(&data, &fs::read(patch_path)?).map_err(|error| {
+            CoralError::InvalidData(format!("Failed to apply {}: {}", patch_path.display(), error))
+        })?;
+    }
+    Ok(data)
+}
+
+// Parses an iNES, NES 2.0 or UNIF image, without setting up the mapper.
+fn parse_rom(data : &[u8]) -> Result<Cartridge> {
+    if data.starts_with(unif::MAGIC) {
+        return load_unif(data);
+    }
+    let mut file = data;
+
     let mut cart = new_cartridge();
-    let mut file = File::open(filepath)?;
     load_header(&mut file, &mut cart)?;
     load_trainer(&mut file, &mut cart)?;
     load_prg(&mut file, &mut cart)?;
     load_chr(&mut file, &mut cart)?;
     load_playchoice(&mut file, &mut cart)?;
+    load_misc(&mut file, &mut cart)?;
+    Ok(cart)
+}
+
+// Patch with the same name as the ROM, e.g. game.bps, game.ups or game.ips for game.nes, which is
+// applied automatically. Patches are rarely made to stack, so only one is used: BPS first, then
+// UPS, both of which check that they apply to the ROM, and IPS last.
+pub fn find_patch<T : AsRef<Path>>(filepath : T) -> Option<PathBuf> {
+    ["bps", "ups", "ips"].iter()
+        .map(|extension| filepath.as_ref().with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+pub fn load<T : AsRef<Path>>(filepath : T) -> Result<Cartridge> {
+    let patches : Vec<PathBuf> = find_patch(&filepath).into_iter().collect();
+    load_with_patches(filepath, &patches)
+}
+
+// Same as load, with the given IPS, UPS or BPS patches applied in order instead of the ones found
+// next to the ROM.
+pub fn load_with_patches<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, patches : &[U]) -> Result<Cartridge> {
+    load_from_data(&read_patched(filepath, patches)?)
+}
+
+pub fn detect_format(data : &[u8]) -> Format {
+    if nsf::is_nsf(data) {
+        Format::Music
+    } else if disk::is_disk_image(data) {
+        Format::Disk
+    } else {
+        Format::Cartridge
+    }
+}
+
+// Format of a ROM file, looking inside .zip and .gz archives.
+pub fn file_format<T : AsRef<Path>>(filepath : T) -> Result<Format> {
+    Ok(detect_format(&read_file(filepath)?))
+}
+
+// Disk images are rejected here, since they can't run without the BIOS given to load_fds.
+fn load_from_data(data : &[u8]) -> Result<Cartridge> {
+    match detect_format(data) {
+        Format::Music => return nsf_cartridge(data),
+        Format::Disk => return Err(CoralError::InvalidInput("Famicom Disk System images need the BIOS. Please load them with load_fds.".to_string())),
+        Format::Cartridge => {}
+    }
+    let mut cart = parse_rom(data)?;
+    cart.database = database::apply(&mut cart)?;
     setup_mapper(&mut cart)?;
     Ok(cart)
 }
+
+// Same as load, for a ROM, tune or a .zip or .gz archive that is already in memory.
+pub fn load_from_bytes(data : &[u8]) -> Result<Cartridge> {
+    load_from_data(&archive::extract(data.to_vec())?)
+}
+
+// Same as load_from_bytes, reading the ROM to the end first.
+pub fn load_from_reader<R : Read>(mut reader : R) -> Result<Cartridge> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    load_from_bytes(&data)
+}
+
+// Loads a cartridge whose board is given by a generic discrete board description rather than
+// the mapper number in the header.
+pub fn load_with_board<T : AsRef<Path>>(filepath : T, description : &str) -> Result<Cartridge> {
+    let patches : Vec<PathBuf> = find_patch(&filepath).into_iter().collect();
+    load_with_board_and_patches(filepath, &patches, description)
+}
+
+// Same as load_with_board, with the given patches instead of the ones found next to the ROM.
+pub fn load_with_board_and_patches<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, patches : &[U], description : &str) -> Result<Cartridge> {
+    let mut cart = parse_rom(&read_patched(filepath, patches)?)?;
+    mapper::choose_generic(&mut cart, description)?;
+    Ok(cart)
+}
+
+// Loads a Famicom Disk System image (.fds or .qd) into the RAM adapter, with the first side in
+// the drive. The BIOS is the 8 KB disksys.rom.
+pub fn load_fds<T : AsRef<Path>, U : AsRef<Path>>(filepath : T, bios_path : U) -> Result<Cartridge> {
+    let image = disk::parse(&read_file(filepath)?)?;
+    let bios = fs::read(bios_path)?;
+    if bios.len() != 0x2000 {
+        return Err(CoralError::SizeMismatch { what: "the FDS BIOS".to_string(), expected: 0x2000, found: bios.len() });
+    }
+    let mut cart = new_cartridge();
+    // Mapper 20 is reserved for the disk system. The battery stands for the writable disk.
+    cart.header.h_mapper = 20;
+    cart.header.h_battery = true;
+    cart.header.h_chr_ram = true;
+    cart.header.h_prg_ram_size = 0x8000;
+    cart.header.h_chr_ram_size = 0x2000;
+    mapper::choose_fds(&mut cart, image, bios);
+    Ok(cart)
+}
+
+// Loads an NSF or NSFe file into a player cartridge, starting at the file's first track.
+pub fn load_nsf<T : AsRef<Path>>(filepath : T) -> Result<Cartridge> {
+    nsf_cartridge(&read_file(filepath)?)
+}
+
+fn nsf_cartridge(data : &[u8]) -> Result<Cartridge> {
+    let nsf = nsf::parse(data)?;
+    let mut cart = new_cartridge();
+    cart.header.h_tv_system = nsf.info.region;
+    cart.header.h_prg_ram_size = 0x2000;
+    cart.nsf = Some(nsf.info.clone());
+    mapper::choose_nsf(&mut cart, nsf);
+    Ok(cart)
+}
@@ -1,26 +1,133 @@
 pub mod types;
+pub mod description;
+pub mod registry;
 mod nomapper;
 mod mapper0;
 mod mapper2;
+mod mapper4;
+mod mapper16;
+mod mapper18;
+mod mapper19;
+mod mapper24;
+mod mapper28;
+mod mapper30;
+mod mapper32;
+mod mapper33;
+mod mapper64;
+mod mapper65;
+mod mapper68;
+mod mapper69;
+mod mapper78;
+mod mapper80;
+mod mapper85;
+mod mapper90;
+mod mapper105;
+mod mapper111;
+mod mapper206;
+mod multicart;
+mod fds;
+mod nsf;
+mod generic;
+mod vrcirq;
+mod eeprom;
+mod barcode;
+mod flash;
+mod mmc1;
+mod audio;
 
-
-use std::io;
-use std::io::Error;
-use std::io::ErrorKind;
+pub use generic::GenericDiscreteMapper;
+pub use registry::{register, supported_mappers, Factory, MapperInfo};
 
 use crate::coral::cartridge as Cartridge;
+use crate::coral::cartridge::disk;
+use crate::coral::error::{CoralError, Result};
+
+fn builtin(mapper : u16, submapper : Option<u8>, name : &str, factory : registry::Factory) -> registry::Entry {
+    registry::Entry { info: registry::MapperInfo { mapper, submapper, name: name.to_string() }, factory }
+}
 
-pub fn choose_mapper(cartridge : &mut Cartridge::Cartridge) -> io::Result<()>{
-    match cartridge.header.h_mapper {
-        0 => { mapper0::choose(cartridge); Ok(()) }
-        2 => { mapper2::choose(cartridge); Ok(()) }
-        _ => {
-            let error_message = format!("Mapper {} is not yet supported. My bad :(", cartridge.header.h_mapper);
-            Err(Error::new(ErrorKind::Other, error_message))
-        }
+// Mappers implemented by Coral. Boards registered at runtime are added on top of these.
+fn builtin_mappers() -> Vec<registry::Entry> {
+    vec![
+        builtin(0, None, "NROM", |cartridge| { mapper0::choose(cartridge); Ok(()) }),
+        builtin(2, None, "UxROM", |cartridge| { mapper2::choose(cartridge); Ok(()) }),
+        builtin(4, None, "MMC3", |cartridge| { mapper4::choose(cartridge, mapper4::Board::Mmc3); Ok(()) }),
+        builtin(4, Some(1), "MMC6", |cartridge| { mapper4::choose(cartridge, mapper4::Board::Mmc6); Ok(()) }),
+        builtin(15, None, "K-1029 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M15); Ok(()) }),
+        builtin(16, None, "Bandai FCG / LZ93D50", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Unspecified); Ok(()) }),
+        builtin(16, Some(4), "Bandai FCG", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Fcg); Ok(()) }),
+        builtin(16, Some(5), "Bandai LZ93D50 with 24C02", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Lz93d50); Ok(()) }),
+        builtin(18, None, "Jaleco SS88006", |cartridge| { mapper18::choose(cartridge); Ok(()) }),
+        builtin(19, None, "Namco 163", |cartridge| { mapper19::choose(cartridge); Ok(()) }),
+        builtin(24, None, "Konami VRC6a", |cartridge| { mapper24::choose(cartridge); Ok(()) }),
+        builtin(26, None, "Konami VRC6b", |cartridge| { mapper24::choose(cartridge); Ok(()) }),
+        builtin(28, None, "Action 53", |cartridge| { mapper28::choose(cartridge); Ok(()) }),
+        builtin(30, None, "UNROM 512", |cartridge| { mapper30::choose(cartridge); Ok(()) }),
+        builtin(32, None, "Irem G-101", |cartridge| { mapper32::choose(cartridge); Ok(()) }),
+        builtin(33, None, "Taito TC0190", |cartridge| { mapper33::choose(cartridge, mapper33::Board::Tc0190); Ok(()) }),
+        builtin(48, None, "Taito TC0690", |cartridge| { mapper33::choose(cartridge, mapper33::Board::Tc0690); Ok(()) }),
+        builtin(58, None, "GK-192 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M58); Ok(()) }),
+        builtin(60, None, "Reset-based 4-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M60); Ok(()) }),
+        builtin(61, None, "20-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M61); Ok(()) }),
+        builtin(64, None, "Tengen RAMBO-1", |cartridge| { mapper64::choose(cartridge); Ok(()) }),
+        builtin(65, None, "Irem H3001", |cartridge| { mapper65::choose(cartridge); Ok(()) }),
+        builtin(68, None, "Sunsoft-4", |cartridge| { mapper68::choose(cartridge); Ok(()) }),
+        builtin(69, None, "Sunsoft FME-7", |cartridge| { mapper69::choose(cartridge); Ok(()) }),
+        builtin(76, None, "Namco 3446", |cartridge| { mapper206::choose(cartridge, mapper206::Board::Namco3446); Ok(()) }),
+        builtin(78, None, "Irem 74HC161/32 / Jaleco JF-16", |cartridge| { mapper78::choose(cartridge); Ok(()) }),
+        builtin(80, None, "Taito X1-005", |cartridge| { mapper80::choose(cartridge); Ok(()) }),
+        builtin(85, None, "Konami VRC7", |cartridge| { mapper85::choose(cartridge); Ok(()) }),
+        builtin(88, None, "Namco 3433", |cartridge| { mapper206::choose(cartridge, mapper206::Board::Namco3433); Ok(()) }),
+        builtin(90, None, "J.Y. Company", |cartridge| { mapper90::choose(cartridge, mapper90::Board::M90); Ok(()) }),
+        builtin(95, None, "Namco 3425", |cartridge| { mapper206::choose(cartridge, mapper206::Board::Namco3425); Ok(()) }),
+        builtin(105, None, "NES-EVENT", |cartridge| { mapper105::choose(cartridge); Ok(()) }),
+        builtin(111, None, "GTROM", |cartridge| { mapper111::choose(cartridge); Ok(()) }),
+        builtin(118, None, "TxSROM", |cartridge| { mapper4::choose(cartridge, mapper4::Board::TxSrom); Ok(()) }),
+        builtin(119, None, "TQROM", |cartridge| { mapper4::choose(cartridge, mapper4::Board::TqRom); Ok(()) }),
+        builtin(153, None, "Bandai LZ93D50 with SRAM", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Lz93d50Sram); Ok(()) }),
+        builtin(154, None, "Namco 3453", |cartridge| { mapper206::choose(cartridge, mapper206::Board::Namco3453); Ok(()) }),
+        builtin(157, None, "Bandai Datach", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Datach); Ok(()) }),
+        builtin(159, None, "Bandai LZ93D50 with 24C01", |cartridge| { mapper16::choose(cartridge, mapper16::Board::Lz93d50X24c01); Ok(()) }),
+        builtin(200, None, "1200-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M200); Ok(()) }),
+        builtin(201, None, "21-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M201); Ok(()) }),
+        builtin(202, None, "150-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M202); Ok(()) }),
+        builtin(203, None, "35-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M203); Ok(()) }),
+        builtin(204, None, "64-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M204); Ok(()) }),
+        builtin(206, None, "Namco 108", |cartridge| { mapper206::choose(cartridge, mapper206::Board::Namco108); Ok(()) }),
+        builtin(209, None, "J.Y. Company with ROM nametables", |cartridge| { mapper90::choose(cartridge, mapper90::Board::M209); Ok(()) }),
+        builtin(211, None, "J.Y. Company with forced ROM nametables", |cartridge| { mapper90::choose(cartridge, mapper90::Board::M211); Ok(()) }),
+        builtin(225, None, "64-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M225); Ok(()) }),
+        builtin(227, None, "1200-in-1 multicart", |cartridge| { multicart::choose(cartridge, multicart::Board::M227); Ok(()) }),
+    ]
+}
+
+pub fn choose_mapper(cartridge : &mut Cartridge::Cartridge) -> Result<()>{
+    let (mapper, submapper) = (cartridge.header.h_mapper, cartridge.header.h_submapper);
+    match registry::find(mapper, submapper) {
+        Some(factory) => factory(cartridge),
+        None if supported_mappers().iter().any(|info| info.mapper == mapper) => Err(CoralError::UnsupportedSubmapper(mapper, submapper)),
+        None => Err(CoralError::UnsupportedMapper(mapper))
     }
 }
 
+// Replaces the mapper with a generic discrete board built from a text description. See
+// description.rs for the format.
+pub fn choose_generic(cartridge : &mut Cartridge::Cartridge, description : &str) -> Result<()>{
+    let description = description::BoardDescription::parse(description)?;
+    generic::choose(cartridge, description);
+    Ok(())
+}
+
+// Replaces the mapper with the Famicom Disk System RAM adapter, with the disk in the drive.
+pub fn choose_fds(cartridge : &mut Cartridge::Cartridge, image : disk::DiskImage, bios : Vec<u8>){
+    fds::choose(cartridge, image, bios);
+}
+
+// Replaces the mapper with a player for an NSF tune.
+pub fn choose_nsf(cartridge : &mut Cartridge::Cartridge, nsf : Cartridge::nsf::NsfFile){
+    nsf::choose(cartridge, nsf);
+}
+
 pub fn generic_mapper() -> types::Mapper {
     types::Mapper{0 : Box::new(nomapper::new())}
 }
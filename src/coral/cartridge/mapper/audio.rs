@@ -0,0 +1,8 @@
+// Expansion sound chips found on cartridges. Each chip is owned by its mapper, which clocks it
+// from MapperT::cpu_tick and reports its level through MapperT::audio_output.
+
+pub mod vrc6;
+pub mod vrc7;
+pub mod n163;
+pub mod sunsoft5b;
+pub mod fds;
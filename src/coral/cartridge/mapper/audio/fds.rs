@@ -0,0 +1,223 @@
+// Famicom Disk System sound: one channel playing a 64-step, 6-bit wavetable, with a volume
+// envelope and a modulation unit that bends the pitch using a 64-step table of deltas.
+
+// Linear level of one step of the 6-bit output, chosen so that full volume is about 2.4 times
+// a 2A03 pulse at full volume.
+const STEP_LEVEL : f32 = 0.00567;
+// Master volume as a fraction of 1152: 2/2, 2/3, 2/4 and 2/5.
+const MASTER_VOLUME : [u32; 4] = [36, 24, 17, 14];
+// Change of the modulation counter for each table entry. None resets the counter.
+const MODULATION_STEPS : [Option<i8>; 8] = [Some(0), Some(1), Some(2), Some(4), None, Some(-4), Some(-2), Some(-1)];
+
+#[derive(Copy, Clone, Debug)]
+struct Envelope {
+    speed : u8,
+    gain : u8,
+    increase : bool,
+    disabled : bool,
+    timer : u32
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct FdsAudio {
+    wave_table : [u8; 64],
+    wave_write : bool,
+    wave_halted : bool,
+    wave_position : u8,
+    wave_accumulator : u32,
+    frequency : u16,
+    volume : Envelope,
+    // Volume gain latched at the start of each waveform cycle.
+    output_gain : u8,
+    envelopes_halted : bool,
+    master_volume : u8,
+    envelope_speed : u8,
+    modulation : Envelope,
+    modulation_table : [u8; 64],
+    modulation_position : u8,
+    modulation_accumulator : u32,
+    modulation_frequency : u16,
+    modulation_halted : bool,
+    // 7-bit signed.
+    modulation_counter : i8,
+    last_output : u8
+}
+
+impl Envelope {
+    fn write(&mut self, byte : u8){
+        self.speed = byte & 0x3F;
+        self.increase = byte & 0x40 > 0;
+        self.disabled = byte & 0x80 > 0;
+        if self.disabled {
+            self.gain = self.speed;
+        }
+    }
+    fn reset_timer(&mut self, envelope_speed : u8){
+        self.timer = 8 * (self.speed as u32 + 1) * envelope_speed as u32;
+    }
+    fn tick(&mut self, envelope_speed : u8){
+        if self.disabled {
+            return;
+        }
+        if self.timer > 0 {
+            self.timer -= 1;
+        }
+        if self.timer == 0 {
+            self.reset_timer(envelope_speed);
+            if self.increase && self.gain < 32 {
+                self.gain += 1;
+            } else if !self.increase && self.gain > 0 {
+                self.gain -= 1;
+            }
+        }
+    }
+}
+
+fn wrap_counter(value : i32) -> i8 {
+    (((value & 0x7F) << 1) as i8) >> 1
+}
+
+impl FdsAudio {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    pub fn read(&self, address : u16) -> u8 {
+        match address {
+            0x4040..=0x407F => self.wave_table[(address & 0x3F) as usize],
+            0x4090 => self.volume.gain,
+            0x4092 => self.modulation.gain,
+            _ => 0
+        }
+    }
+    pub fn write(&mut self, address : u16, byte : u8){
+        match address {
+            0x4040..=0x407F if self.wave_write => { self.wave_table[(address & 0x3F) as usize] = byte & 0x3F; }
+            0x4080 => { self.volume.write(byte); }
+            0x4082 => { self.frequency = (self.frequency & 0x0F00) | byte as u16; }
+            0x4083 => {
+                self.frequency = (self.frequency & 0x00FF) | (((byte & 0x0F) as u16) << 8);
+                self.wave_halted = byte & 0x80 > 0;
+                self.envelopes_halted = byte & 0x40 > 0;
+                if self.wave_halted {
+                    self.wave_position = 0;
+                    self.wave_accumulator = 0;
+                }
+                if self.envelopes_halted {
+                    self.volume.reset_timer(self.envelope_speed);
+                    self.modulation.reset_timer(self.envelope_speed);
+                }
+            }
+            0x4084 => { self.modulation.write(byte); }
+            0x4085 => { self.modulation_counter = wrap_counter(byte as i32); }
+            0x4086 => { self.modulation_frequency = (self.modulation_frequency & 0x0F00) | byte as u16; }
+            0x4087 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x00FF) | (((byte & 0x0F) as u16) << 8);
+                self.modulation_halted = byte & 0x80 > 0;
+                if self.modulation_halted {
+                    self.modulation_accumulator = 0;
+                }
+            }
+            // The table can only be written while the modulation unit is halted. Each write fills
+            // two entries.
+            0x4088 if self.modulation_halted => {
+                let position = self.modulation_position as usize;
+                self.modulation_table[position] = byte & 0x07;
+                self.modulation_table[(position + 1) & 0x3F] = byte & 0x07;
+                self.modulation_position = ((position + 2) & 0x3F) as u8;
+            }
+            0x4089 => {
+                self.master_volume = byte & 0x03;
+                self.wave_write = byte & 0x80 > 0;
+            }
+            0x408A => { self.envelope_speed = byte; }
+            _ => {}
+        }
+    }
+    // Wave frequency after modulation, following the hardware's rounding.
+    fn pitch(&self) -> u32 {
+        let counter = self.modulation_counter as i32;
+        let mut temp = counter * self.modulation.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+        let frequency = self.frequency as i32;
+        temp *= frequency;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        (frequency + temp).max(0) as u32
+    }
+    fn tick_modulation(&mut self){
+        if self.modulation_halted || self.modulation_frequency == 0 {
+            return;
+        }
+        self.modulation_accumulator += self.modulation_frequency as u32;
+        if self.modulation_accumulator > 0xFFFF {
+            self.modulation_accumulator &= 0xFFFF;
+            let entry = self.modulation_table[self.modulation_position as usize];
+            self.modulation_counter = match MODULATION_STEPS[entry as usize] {
+                Some(step) => wrap_counter(self.modulation_counter as i32 + step as i32),
+                None => 0
+            };
+            self.modulation_position = (self.modulation_position + 1) & 0x3F;
+        }
+    }
+    pub fn tick(&mut self){
+        if !self.wave_halted && !self.envelopes_halted && self.envelope_speed > 0 {
+            self.volume.tick(self.envelope_speed);
+            self.modulation.tick(self.envelope_speed);
+        }
+        self.tick_modulation();
+        // The output holds its last value while the wavetable is being written.
+        if self.wave_halted || self.wave_write {
+            return;
+        }
+        self.wave_accumulator += self.pitch();
+        if self.wave_accumulator > 0xFFFF {
+            self.wave_accumulator &= 0xFFFF;
+            self.wave_position = (self.wave_position + 1) & 0x3F;
+            if self.wave_position == 0 {
+                self.output_gain = self.volume.gain.min(32);
+            }
+        }
+        self.last_output = self.wave_table[self.wave_position as usize];
+    }
+    pub fn output(&self) -> f32 {
+        let level = self.last_output as u32 * self.output_gain as u32 * MASTER_VOLUME[self.master_volume as usize];
+        level as f32 / 1152.0 * STEP_LEVEL
+    }
+}
+
+pub fn new() -> FdsAudio {
+    let envelope = Envelope { speed: 0, gain: 0, increase: false, disabled: true, timer: 0 };
+    FdsAudio {
+        wave_table: [0; 64],
+        wave_write: false,
+        wave_halted: true,
+        wave_position: 0,
+        wave_accumulator: 0,
+        frequency: 0,
+        volume: envelope,
+        output_gain: 0,
+        envelopes_halted: false,
+        master_volume: 0,
+        envelope_speed: 0xE8,
+        modulation: envelope,
+        modulation_table: [0; 64],
+        modulation_position: 0,
+        modulation_accumulator: 0,
+        modulation_frequency: 0,
+        modulation_halted: true,
+        modulation_counter: 0,
+        last_output: 0
+    }
+}
@@ -0,0 +1,95 @@
+// Namco 163 sound: up to eight wavetable channels that share the chip's 128 bytes of internal RAM.
+//
+// Channel registers live at the top of the RAM ($40-$7F, eight bytes per channel) and waveforms
+// are 4-bit samples packed two per byte anywhere in it. The chip updates a single channel every
+// 15 CPU cycles, cycling through the enabled ones, so more channels means a lower sample rate.
+
+const CLOCKS_PER_UPDATE : u32 = 15;
+// Linear level of a single step, chosen so that a full-volume channel matches a 2A03 pulse.
+const STEP_LEVEL : f32 = 0.00125;
+
+#[derive(Copy, Clone, Debug)] 
+pub struct N163Audio {
+    pub ram : [u8; 0x80],
+    address : u8,
+    auto_increment : bool,
+    clock : u32,
+    current_channel : usize,
+    outputs : [i32; 8]
+}
+
+impl N163Audio {
+    pub fn reset(&mut self){
+        self.address = 0;
+        self.auto_increment = false;
+        self.clock = 0;
+        self.current_channel = 7;
+        self.outputs = [0; 8];
+    }
+    pub fn write_address(&mut self, byte : u8){
+        self.address = byte & 0x7F;
+        self.auto_increment = byte & 0x80 > 0;
+    }
+    pub fn read_data(&mut self) -> u8 {
+        let byte = self.ram[self.address as usize];
+        self.increment();
+        byte
+    }
+    pub fn write_data(&mut self, byte : u8){
+        self.ram[self.address as usize] = byte;
+        self.increment();
+    }
+    fn increment(&mut self){
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+    fn enabled_channels(&self) -> usize {
+        (((self.ram[0x7F] >> 4) & 0x07) + 1) as usize
+    }
+    fn sample(&self, index : u32) -> i32 {
+        let byte = self.ram[((index & 0xFF) >> 1) as usize];
+        let nibble = if index & 0x01 > 0 { byte >> 4 } else { byte & 0x0F };
+        nibble as i32
+    }
+    fn update_channel(&mut self, channel : usize){
+        let base = 0x40 + channel * 8;
+        let register = |offset : usize| -> u32 { self.ram[base + offset] as u32 };
+        let frequency = register(0) | (register(2) << 8) | ((register(4) & 0x03) << 16);
+        let phase = register(1) | (register(3) << 8) | (register(5) << 16);
+        let length = 256 - (register(4) & 0xFC);
+        let offset = register(6);
+        let volume = (register(7) & 0x0F) as i32;
+
+        let phase = (phase + frequency) % (length << 16);
+        let sample = self.sample(offset + (phase >> 16));
+        self.outputs[channel] = (sample - 8) * volume;
+
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+    }
+    pub fn tick(&mut self){
+        self.clock += 1;
+        if self.clock < CLOCKS_PER_UPDATE {
+            return;
+        }
+        self.clock = 0;
+
+        let first_channel = 8 - self.enabled_channels();
+        if self.current_channel < first_channel {
+            self.current_channel = 7;
+        }
+        self.update_channel(self.current_channel);
+        self.current_channel = if self.current_channel == first_channel { 7 } else { self.current_channel - 1 };
+    }
+    pub fn output(&self) -> f32 {
+        let enabled_channels = self.enabled_channels();
+        let sum : i32 = self.outputs[(8 - enabled_channels)..].iter().sum();
+        sum as f32 * STEP_LEVEL / enabled_channels as f32
+    }
+}
+
+pub fn new() -> N163Audio {
+    N163Audio { ram: [0; 0x80], address: 0, auto_increment: false, clock: 0, current_channel: 7, outputs: [0; 8] }
+}
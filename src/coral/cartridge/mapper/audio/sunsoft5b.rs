@@ -0,0 +1,179 @@
+// Sunsoft 5B sound: a licensed YM2149 (AY-3-8910 family) with three square channels, one noise
+// generator and one envelope generator shared by all channels.
+//
+// Tones and noise are clocked at CPU/16. The envelope has 32 steps, twice the AY-3-8910's
+// resolution, and volumes are logarithmic with 1.5 dB per envelope step (3 dB per volume step).
+
+const CLOCK_DIVIDER : u32 = 16;
+// Linear level of one channel at full volume, on the 2A03 scale.
+const CHANNEL_LEVEL : f32 = 0.15;
+
+#[derive(Copy, Clone, Debug)] 
+struct Tone {
+    period : u16,
+    counter : u16,
+    high : bool
+}
+
+#[derive(Copy, Clone, Debug)] 
+pub struct Sunsoft5bAudio {
+    registers : [u8; 0x10],
+    address : u8,
+    divider : u32,
+    tones : [Tone; 3],
+    noise_counter : u8,
+    noise_shift : u32,
+    noise_toggle : bool,
+    envelope_counter : u16,
+    envelope_step : u8,
+    envelope_attack : bool,
+    envelope_holding : bool
+}
+
+fn level(volume : u8) -> f32 {
+    if volume == 0 {
+        0.0
+    } else {
+        10.0_f32.powf(-((31 - volume) as f32 * 1.5) / 20.0)
+    }
+}
+
+// Maps a 4-bit channel volume onto the 5-bit envelope scale.
+fn fixed_volume(volume : u8) -> u8 {
+    if volume == 0 { 0 } else { volume * 2 + 1 }
+}
+
+impl Tone {
+    fn tick(&mut self){
+        self.counter += 1;
+        if self.counter >= self.period.max(1) {
+            self.counter = 0;
+            self.high = !self.high;
+        }
+    }
+}
+
+impl Sunsoft5bAudio {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    pub fn write_address(&mut self, byte : u8){
+        self.address = byte & 0x0F;
+    }
+    pub fn write_data(&mut self, byte : u8){
+        let register = self.address as usize;
+        self.registers[register] = byte;
+        match register {
+            0x00..=0x05 => {
+                let channel = register >> 1;
+                let low = self.registers[channel * 2] as u16;
+                let high = (self.registers[channel * 2 + 1] & 0x0F) as u16;
+                self.tones[channel].period = (high << 8) | low;
+            }
+            0x0D => {
+                self.envelope_step = 0;
+                self.envelope_counter = 0;
+                self.envelope_holding = false;
+                self.envelope_attack = byte & 0x04 > 0;
+            }
+            _ => {}
+        }
+    }
+    fn envelope_period(&self) -> u16 {
+        ((self.registers[0x0C] as u16) << 8) | self.registers[0x0B] as u16
+    }
+    fn envelope_level(&self) -> u8 {
+        if self.envelope_attack { self.envelope_step } else { 31 - self.envelope_step }
+    }
+    fn tick_noise(&mut self){
+        self.noise_toggle = !self.noise_toggle;
+        if self.noise_toggle {
+            return;
+        }
+        self.noise_counter += 1;
+        if self.noise_counter >= (self.registers[0x06] & 0x1F).max(1) {
+            self.noise_counter = 0;
+            let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+            self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+        }
+    }
+    // Each envelope step takes half the time of an AY-3-8910 step, since there are twice as many.
+    fn tick_envelope(&mut self){
+        self.envelope_counter += 2;
+        if self.envelope_counter < self.envelope_period().max(1) {
+            return;
+        }
+        self.envelope_counter = 0;
+        if self.envelope_holding {
+            return;
+        }
+        self.envelope_step += 1;
+        if self.envelope_step > 31 {
+            let shape = self.registers[0x0D];
+            let continue_flag = shape & 0x08 > 0;
+            let alternate = shape & 0x02 > 0;
+            let hold = shape & 0x01 > 0;
+            if !continue_flag {
+                self.envelope_holding = true;
+                self.envelope_attack = false;
+                self.envelope_step = 31;
+            } else if hold {
+                self.envelope_holding = true;
+                if alternate {
+                    self.envelope_attack = !self.envelope_attack;
+                }
+                self.envelope_step = 31;
+            } else {
+                if alternate {
+                    self.envelope_attack = !self.envelope_attack;
+                }
+                self.envelope_step = 0;
+            }
+        }
+    }
+    pub fn tick(&mut self){
+        self.divider += 1;
+        if self.divider < CLOCK_DIVIDER {
+            return;
+        }
+        self.divider = 0;
+        for tone in self.tones.iter_mut() {
+            tone.tick();
+        }
+        self.tick_noise();
+        self.tick_envelope();
+    }
+    pub fn output(&self) -> f32 {
+        let mixer = self.registers[0x07];
+        let noise = self.noise_shift & 0x01 > 0;
+        let mut output = 0.0;
+        for (channel, tone) in self.tones.iter().enumerate() {
+            let tone_enabled = mixer & (0x01 << channel) == 0;
+            let noise_enabled = mixer & (0x08 << channel) == 0;
+            let high = (tone.high || !tone_enabled) && (noise || !noise_enabled);
+            if high {
+                let amplitude = self.registers[0x08 + channel];
+                let volume = if amplitude & 0x10 > 0 { self.envelope_level() } else { fixed_volume(amplitude & 0x0F) };
+                output += level(volume);
+            }
+        }
+        output * CHANNEL_LEVEL
+    }
+}
+
+pub fn new() -> Sunsoft5bAudio {
+    let tone = Tone { period: 0, counter: 0, high: false };
+    Sunsoft5bAudio {
+        registers: [0; 0x10],
+        address: 0,
+        divider: 0,
+        tones: [tone; 3],
+        noise_counter: 0,
+        noise_shift: 0x01,
+        noise_toggle: false,
+        envelope_counter: 0,
+        envelope_step: 0,
+        envelope_attack: false,
+        envelope_holding: false
+    }
+}
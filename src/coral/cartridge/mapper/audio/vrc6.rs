@@ -0,0 +1,144 @@
+// Konami VRC6 sound: two pulse channels with 8 duty settings and a sawtooth channel.
+
+// Linear level of a single step, chosen so that a VRC6 pulse at full volume matches a 2A03 pulse.
+const STEP_LEVEL : f32 = 0.00993;
+
+#[derive(Copy, Clone, Debug)] 
+struct Pulse {
+    volume : u8,
+    duty : u8,
+    ignore_duty : bool,
+    period : u16,
+    enabled : bool,
+    timer : u16,
+    step : u8
+}
+
+#[derive(Copy, Clone, Debug)] 
+struct Sawtooth {
+    rate : u8,
+    period : u16,
+    enabled : bool,
+    timer : u16,
+    step : u8,
+    accumulator : u8
+}
+
+#[derive(Copy, Clone, Debug)] 
+pub struct Vrc6Audio {
+    pulse1 : Pulse,
+    pulse2 : Pulse,
+    sawtooth : Sawtooth,
+    halt : bool,
+    shift : u8
+}
+
+impl Pulse {
+    fn write(&mut self, register : u16, byte : u8){
+        match register {
+            0 => {
+                self.volume = byte & 0x0F;
+                self.duty = (byte >> 4) & 0x07;
+                self.ignore_duty = byte & 0x80 > 0;
+            }
+            1 => { self.period = (self.period & 0x0F00) | byte as u16; }
+            _ => {
+                self.period = (self.period & 0x00FF) | (((byte & 0x0F) as u16) << 8);
+                self.enabled = byte & 0x80 > 0;
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+    fn tick(&mut self, shift : u8){
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = self.step.wrapping_sub(1) & 0x0F;
+        } else {
+            self.timer -= 1;
+        }
+    }
+    fn output(&self) -> u8 {
+        if self.enabled && (self.ignore_duty || self.step <= self.duty) { self.volume } else { 0 }
+    }
+}
+
+impl Sawtooth {
+    fn write(&mut self, register : u16, byte : u8){
+        match register {
+            0 => { self.rate = byte & 0x3F; }
+            1 => { self.period = (self.period & 0x0F00) | byte as u16; }
+            _ => {
+                self.period = (self.period & 0x00FF) | (((byte & 0x0F) as u16) << 8);
+                self.enabled = byte & 0x80 > 0;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+    fn tick(&mut self, shift : u8){
+        if !self.enabled {
+            return;
+        }
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step += 1;
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if self.step & 0x01 == 0 {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+    fn output(&self) -> u8 {
+        if self.enabled { self.accumulator >> 3 } else { 0 }
+    }
+}
+
+impl Vrc6Audio {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    // Expects the register address after the board has normalised its address lines,
+    // i.e. one of $9000-$9003, $A000-$A002 or $B000-$B002.
+    pub fn write(&mut self, address : u16, byte : u8){
+        let register = address & 0x0003;
+        match address & 0xF000 {
+            0x9000 if register == 3 => {
+                self.halt = byte & 0x01 > 0;
+                self.shift = if byte & 0x04 > 0 { 8 } else if byte & 0x02 > 0 { 4 } else { 0 };
+            }
+            0x9000 => { self.pulse1.write(register, byte); }
+            0xA000 => { self.pulse2.write(register, byte); }
+            0xB000 => { self.sawtooth.write(register, byte); }
+            _ => {}
+        }
+    }
+    pub fn tick(&mut self){
+        if self.halt {
+            return;
+        }
+        self.pulse1.tick(self.shift);
+        self.pulse2.tick(self.shift);
+        self.sawtooth.tick(self.shift);
+    }
+    pub fn output(&self) -> f32 {
+        let level = self.pulse1.output() + self.pulse2.output() + self.sawtooth.output();
+        level as f32 * STEP_LEVEL
+    }
+}
+
+pub fn new() -> Vrc6Audio {
+    let pulse = Pulse { volume: 0, duty: 0, ignore_duty: false, period: 0, enabled: false, timer: 0, step: 15 };
+    let sawtooth = Sawtooth { rate: 0, period: 0, enabled: false, timer: 0, step: 0, accumulator: 0 };
+    Vrc6Audio { pulse1: pulse, pulse2: pulse, sawtooth, halt: false, shift: 0 }
+}
@@ -0,0 +1,365 @@
+use std::sync::OnceLock;
+
+// Konami VRC7 sound: a cut-down YM2413 (OPLL) with six two-operator FM channels, fifteen
+// built-in instruments and one user-defined instrument. Rhythm mode is not present.
+//
+// The synthesizer runs at the OPLL sample rate (one sample every 36 CPU cycles) and keeps
+// envelopes in 0.375 dB steps, like the real chip. Phases are tracked in fractions of a cycle.
+
+const CLOCKS_PER_SAMPLE : u32 = 36;
+const SAMPLE_RATE : f32 = 49716.0;
+const SINE_SIZE : usize = 1024;
+// Envelope resolution and range, in 0.375 dB steps.
+const ENVELOPE_STEP_DB : f32 = 0.375;
+const ENVELOPE_MAX : f32 = 128.0;
+// Linear level of a single channel at full volume.
+const CHANNEL_LEVEL : f32 = 0.1;
+
+// Built-in instruments 1-15, as dumped from the VRC7 die.
+const PATCHES : [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27], // Buzzy bell
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12], // Guitar
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12], // Wurly
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27], // Flute
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28], // Clarinet
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4], // Synth
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07], // Trumpet
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17], // Organ
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01], // Bells
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02], // Vibes
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12], // Vibraphone
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16], // Tutti
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02], // Fretless
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6], // Synth bass
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06], // Sweep
+];
+
+// Frequency multipliers selected by MULT. Values 11 and 13 round down, as on the YM2413.
+const MULTIPLIER : [f32; 16] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0];
+
+// Key scaling attenuation in dB at block 7, indexed by the top four bits of the F-number.
+const KSL_DB : [f32; 16] = [0.0, 9.0, 12.0, 13.875, 15.0, 16.125, 16.875, 17.625, 18.0, 18.75, 19.125, 19.5, 19.875, 20.25, 20.625, 21.0];
+
+// Time in seconds for a full decay at rate 4. Each additional rate step divides it by 2^(1/4).
+const DECAY_TIME : f32 = 19.64;
+// Time in seconds for a full attack at rate 4.
+const ATTACK_TIME : f32 = 2.826;
+
+// LFO frequencies and depths.
+const TREMOLO_RATE : f32 = 3.7;
+const TREMOLO_DEPTH : f32 = 4.8 / ENVELOPE_STEP_DB;
+const VIBRATO_RATE : f32 = 6.4;
+const VIBRATO_DEPTH : f32 = 0.007;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Idle
+}
+
+// Parameters of one operator, decoded from an instrument.
+#[derive(Copy, Clone, Debug)]
+struct OperatorPatch {
+    tremolo : bool,
+    vibrato : bool,
+    sustained : bool,
+    key_scale_rate : bool,
+    multiplier : f32,
+    key_scale_level : u8,
+    total_level : u8,
+    rectified : bool,
+    attack_rate : u8,
+    decay_rate : u8,
+    sustain_level : u8,
+    release_rate : u8
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Operator {
+    phase : f32,
+    envelope : f32,
+    state : EnvelopeState,
+    output : f32,
+    previous_output : f32
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Channel {
+    fnum : u16,
+    block : u8,
+    key_on : bool,
+    sustain : bool,
+    instrument : u8,
+    volume : u8,
+    modulator : Operator,
+    carrier : Operator
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Vrc7Audio {
+    custom_patch : [u8; 8],
+    channels : [Channel; 6],
+    address : u8,
+    clock : u32,
+    tremolo_phase : f32,
+    vibrato_phase : f32,
+    silenced : bool,
+    output : f32
+}
+
+fn sine_table() -> &'static [f32; SINE_SIZE] {
+    static TABLE : OnceLock<[f32; SINE_SIZE]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; SINE_SIZE];
+        for (i, value) in table.iter_mut().enumerate() {
+            *value = (2.0 * std::f32::consts::PI * (i as f32 + 0.5) / SINE_SIZE as f32).sin();
+        }
+        table
+    })
+}
+
+fn attenuation_table() -> &'static [f32; 256] {
+    static TABLE : OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            *value = 10.0_f32.powf(-(i as f32 * ENVELOPE_STEP_DB) / 20.0);
+        }
+        table
+    })
+}
+
+// Samples a (possibly half-wave rectified) sine at `phase`, attenuated by `attenuation` steps.
+fn wave(phase : f32, attenuation : f32, rectified : bool) -> f32 {
+    let index = ((phase - phase.floor()) * SINE_SIZE as f32) as usize % SINE_SIZE;
+    let sine = sine_table()[index];
+    if rectified && sine < 0.0 {
+        return 0.0;
+    }
+    let step = attenuation.clamp(0.0, 255.0) as usize;
+    sine * attenuation_table()[step]
+}
+
+fn decode_patch(patch : &[u8; 8], carrier : bool) -> OperatorPatch {
+    let i = if carrier { 1 } else { 0 };
+    let flags = patch[i];
+    let total_level = if carrier { 0 } else { patch[2] & 0x3F };
+    let rectified = if carrier { patch[3] & 0x10 > 0 } else { patch[3] & 0x08 > 0 };
+    OperatorPatch {
+        tremolo: flags & 0x80 > 0,
+        vibrato: flags & 0x40 > 0,
+        sustained: flags & 0x20 > 0,
+        key_scale_rate: flags & 0x10 > 0,
+        multiplier: MULTIPLIER[(flags & 0x0F) as usize],
+        key_scale_level: patch[2 + i] >> 6,
+        total_level,
+        rectified,
+        attack_rate: patch[4 + i] >> 4,
+        decay_rate: patch[4 + i] & 0x0F,
+        sustain_level: patch[6 + i] >> 4,
+        release_rate: patch[6 + i] & 0x0F
+    }
+}
+
+impl Operator {
+    fn key_on(&mut self){
+        self.phase = 0.0;
+        self.state = EnvelopeState::Attack;
+    }
+    fn key_off(&mut self){
+        if self.state != EnvelopeState::Idle {
+            self.state = EnvelopeState::Release;
+        }
+    }
+    fn update_envelope(&mut self, patch : &OperatorPatch, channel : &Channel){
+        let key_code = ((channel.block << 1) | (channel.fnum >> 8) as u8) as u32;
+        let rate_offset = if patch.key_scale_rate { key_code } else { key_code >> 2 };
+        let effective_rate = |rate : u8| -> u32 {
+            if rate == 0 { 0 } else { (rate as u32 * 4 + rate_offset).min(63) }
+        };
+        let decay_step = |rate : u32| -> f32 {
+            if rate == 0 { 0.0 } else { ENVELOPE_MAX / (DECAY_TIME * SAMPLE_RATE) * 2.0_f32.powf((rate as f32 - 4.0) / 4.0) }
+        };
+
+        match self.state {
+            EnvelopeState::Attack => {
+                let rate = effective_rate(patch.attack_rate);
+                if rate >= 60 {
+                    self.envelope = 0.0;
+                } else if rate > 0 {
+                    let time = ATTACK_TIME / 2.0_f32.powf((rate as f32 - 4.0) / 4.0);
+                    let factor = (ENVELOPE_MAX.ln()) / (time * SAMPLE_RATE);
+                    self.envelope -= (self.envelope + 1.0) * factor;
+                }
+                if self.envelope <= 0.0 {
+                    self.envelope = 0.0;
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.envelope += decay_step(effective_rate(patch.decay_rate));
+                let sustain_level = patch.sustain_level as f32 * 8.0;
+                if self.envelope >= sustain_level {
+                    self.envelope = sustain_level;
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain => {
+                // Percussive tones keep decaying at the release rate while the key is held.
+                if !patch.sustained {
+                    self.envelope += decay_step(effective_rate(patch.release_rate));
+                }
+            }
+            EnvelopeState::Release => {
+                let rate = if channel.sustain { 5 } else if patch.sustained { patch.release_rate } else { 7 };
+                self.envelope += decay_step(effective_rate(rate));
+            }
+            EnvelopeState::Idle => {}
+        }
+        if self.envelope >= ENVELOPE_MAX {
+            self.envelope = ENVELOPE_MAX;
+            if self.state != EnvelopeState::Attack {
+                self.state = EnvelopeState::Idle;
+            }
+        }
+    }
+    fn advance(&mut self, patch : &OperatorPatch, channel : &Channel, vibrato : f32){
+        let frequency = (channel.fnum as f32) * 2.0_f32.powi(channel.block as i32) * SAMPLE_RATE / 524288.0;
+        let modulation = if patch.vibrato { 1.0 + vibrato } else { 1.0 };
+        self.phase += frequency * patch.multiplier * modulation / SAMPLE_RATE;
+        self.phase -= self.phase.floor();
+    }
+    fn attenuation(&self, patch : &OperatorPatch, channel : &Channel, tremolo : f32) -> f32 {
+        let key_scale = match patch.key_scale_level {
+            0 => 0.0,
+            level => {
+                let base = KSL_DB[(channel.fnum >> 5) as usize] - 6.0 * (7 - channel.block) as f32;
+                base.max(0.0) * (1 << (level - 1)) as f32 / 4.0 / ENVELOPE_STEP_DB
+            }
+        };
+        let tremolo = if patch.tremolo { tremolo } else { 0.0 };
+        self.envelope + (patch.total_level as f32 * 2.0) + key_scale + tremolo
+    }
+}
+
+impl Channel {
+    fn patch(&self, custom_patch : &[u8; 8]) -> [u8; 8] {
+        if self.instrument == 0 { *custom_patch } else { PATCHES[self.instrument as usize - 1] }
+    }
+    fn write_key(&mut self, byte : u8){
+        let key_on = byte & 0x10 > 0;
+        if key_on && !self.key_on {
+            self.modulator.key_on();
+            self.carrier.key_on();
+        } else if !key_on && self.key_on {
+            self.modulator.key_off();
+            self.carrier.key_off();
+        }
+        self.key_on = key_on;
+        self.sustain = byte & 0x20 > 0;
+        self.block = (byte >> 1) & 0x07;
+        self.fnum = (self.fnum & 0x00FF) | (((byte & 0x01) as u16) << 8);
+    }
+    fn generate(&mut self, custom_patch : &[u8; 8], tremolo : f32, vibrato : f32) -> f32 {
+        let patch = self.patch(custom_patch);
+        let modulator_patch = decode_patch(&patch, false);
+        let carrier_patch = decode_patch(&patch, true);
+        let feedback = patch[3] & 0x07;
+        let snapshot = *self;
+
+        self.modulator.update_envelope(&modulator_patch, &snapshot);
+        self.carrier.update_envelope(&carrier_patch, &snapshot);
+        self.modulator.advance(&modulator_patch, &snapshot, vibrato);
+        self.carrier.advance(&carrier_patch, &snapshot, vibrato);
+
+        let feedback_phase = if feedback == 0 {
+            0.0
+        } else {
+            (self.modulator.output + self.modulator.previous_output) * 2.0_f32.powi(feedback as i32 - 7)
+        };
+        let modulator_attenuation = self.modulator.attenuation(&modulator_patch, &snapshot, tremolo);
+        let modulator_output = wave(self.modulator.phase + feedback_phase, modulator_attenuation, modulator_patch.rectified);
+        self.modulator.previous_output = self.modulator.output;
+        self.modulator.output = modulator_output;
+
+        // A full-scale modulator shifts the carrier phase by four cycles (8 pi).
+        let carrier_phase = self.carrier.phase + modulator_output * 4.0;
+        let carrier_attenuation = self.carrier.attenuation(&carrier_patch, &snapshot, tremolo) + self.volume as f32 * 8.0;
+        let carrier_output = wave(carrier_phase, carrier_attenuation, carrier_patch.rectified);
+        self.carrier.previous_output = self.carrier.output;
+        self.carrier.output = carrier_output;
+        carrier_output
+    }
+}
+
+impl Vrc7Audio {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    pub fn silence(&mut self, silenced : bool){
+        if silenced {
+            self.reset();
+        }
+        self.silenced = silenced;
+    }
+    pub fn write_address(&mut self, byte : u8){
+        self.address = byte;
+    }
+    pub fn write_data(&mut self, byte : u8){
+        let address = self.address;
+        let channel = (address & 0x0F) as usize;
+        match address {
+            0x00..=0x07 => { self.custom_patch[address as usize] = byte; }
+            0x10..=0x15 => { self.channels[channel].fnum = (self.channels[channel].fnum & 0x0100) | byte as u16; }
+            0x20..=0x25 => { self.channels[channel].write_key(byte); }
+            0x30..=0x35 => {
+                self.channels[channel].instrument = byte >> 4;
+                self.channels[channel].volume = byte & 0x0F;
+            }
+            _ => {}
+        }
+    }
+    pub fn tick(&mut self){
+        if self.silenced {
+            return;
+        }
+        self.clock += 1;
+        if self.clock < CLOCKS_PER_SAMPLE {
+            return;
+        }
+        self.clock = 0;
+
+        self.tremolo_phase = (self.tremolo_phase + TREMOLO_RATE / SAMPLE_RATE).fract();
+        self.vibrato_phase = (self.vibrato_phase + VIBRATO_RATE / SAMPLE_RATE).fract();
+        let tremolo = TREMOLO_DEPTH * (1.0 - (2.0 * self.tremolo_phase - 1.0).abs());
+        let vibrato = VIBRATO_DEPTH * (2.0 * std::f32::consts::PI * self.vibrato_phase).sin();
+
+        let custom_patch = self.custom_patch;
+        let mut output = 0.0;
+        for channel in self.channels.iter_mut() {
+            output += channel.generate(&custom_patch, tremolo, vibrato);
+        }
+        self.output = output * CHANNEL_LEVEL;
+    }
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+}
+
+pub fn new() -> Vrc7Audio {
+    let operator = Operator { phase: 0.0, envelope: ENVELOPE_MAX, state: EnvelopeState::Idle, output: 0.0, previous_output: 0.0 };
+    let channel = Channel { fnum: 0, block: 0, key_on: false, sustain: false, instrument: 0, volume: 0, modulator: operator, carrier: operator };
+    Vrc7Audio {
+        custom_patch: [0; 8],
+        channels: [channel; 6],
+        address: 0,
+        clock: 0,
+        tremolo_phase: 0.0,
+        vibrato_phase: 0.0,
+        silenced: false,
+        output: 0.0
+    }
+}
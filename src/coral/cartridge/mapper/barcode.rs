@@ -0,0 +1,86 @@
+// Barcode reader of the Bandai Datach Joint ROM System. A scanned EAN-13 or EAN-8 code is
+// played back as a serial stream of bars and spaces, one module every CYCLES_PER_MODULE CPU cycles.
+
+const CYCLES_PER_MODULE : usize = 1000;
+const QUIET_ZONE : usize = 32;
+
+// L-codes of the digits 0-9, MSB first. R-codes are their complement, G-codes the reversed R-codes.
+const L_CODES : [u8; 10] = [0x0D, 0x19, 0x13, 0x3D, 0x23, 0x31, 0x2F, 0x3B, 0x37, 0x0B];
+// For EAN-13, the leading digit picks L (0) or G (1) for each digit of the left half.
+const PARITY : [u8; 10] = [0x00, 0x0B, 0x0D, 0x0E, 0x13, 0x19, 0x1C, 0x15, 0x16, 0x1A];
+
+#[derive(Clone, Debug)]
+pub struct BarcodeReader {
+    // One entry per module, true for a bar.
+    modules : Vec<bool>,
+    cycles : usize,
+}
+
+impl BarcodeReader {
+    // Starts scanning `code`. Returns false if it is not an 8 or 13 digit string.
+    pub fn scan(&mut self, code : &str) -> bool {
+        let digits : Vec<usize> = code.chars().filter_map(|c| c.to_digit(10)).map(|d| d as usize).collect();
+        if digits.len() != code.len() || (digits.len() != 8 && digits.len() != 13) {
+            return false;
+        }
+        let (parity, left, right) = if digits.len() == 13 {
+            (PARITY[digits[0]], &digits[1..7], &digits[7..])
+        } else {
+            (0, &digits[..4], &digits[4..])
+        };
+
+        let mut modules = vec![false; QUIET_ZONE];
+        push_pattern(&mut modules, 0x05, 3);
+        for (index, &digit) in left.iter().enumerate() {
+            let even = (parity >> (left.len() - 1 - index)) & 0x01 > 0;
+            let code = if even { reverse(!L_CODES[digit] & 0x7F) } else { L_CODES[digit] };
+            push_pattern(&mut modules, code, 7);
+        }
+        push_pattern(&mut modules, 0x0A, 5);
+        for &digit in right {
+            push_pattern(&mut modules, !L_CODES[digit] & 0x7F, 7);
+        }
+        push_pattern(&mut modules, 0x05, 3);
+        modules.resize(modules.len() + QUIET_ZONE, false);
+
+        self.modules = modules;
+        self.cycles = 0;
+        true
+    }
+    pub fn tick(&mut self) {
+        if !self.modules.is_empty() {
+            self.cycles += 1;
+            if self.cycles >= self.modules.len() * CYCLES_PER_MODULE {
+                self.modules.clear();
+            }
+        }
+    }
+    // Bit 3 of $6000-$7FFF reads: clear while a bar is under the reader.
+    pub fn output(&self) -> u8 {
+        match self.modules.get(self.cycles / CYCLES_PER_MODULE) {
+            Some(true) => 0x00,
+            _ => 0x08
+        }
+    }
+    pub fn reset(&mut self) {
+        self.modules.clear();
+        self.cycles = 0;
+    }
+}
+
+fn push_pattern(modules : &mut Vec<bool>, pattern : u8, width : usize) {
+    for bit in (0..width).rev() {
+        modules.push((pattern >> bit) & 0x01 > 0);
+    }
+}
+
+fn reverse(code : u8) -> u8 {
+    code.reverse_bits() >> 1
+}
+
+pub fn new() -> BarcodeReader {
+    BarcodeReader {
+        modules: Vec::new(),
+        cycles: 0
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::coral::cartridge::types::Mirroring;
+use crate::coral::error::{CoralError, Result};
+
+// Board descriptions for the generic discrete mapper, written in a small subset of TOML:
+// `key = value` lines grouped under [register], [prg], [chr] and [mirroring] tables, with
+// integers (decimal, 0x or 0b), booleans, strings and single-line arrays. For example, UNROM:
+//
+//     name = "UNROM"
+//
+//     [register]
+//     mask = 0x8000
+//     match = 0x8000
+//     latch = "data"
+//     bus_conflicts = true
+//
+//     [prg]
+//     bank_size = 16
+//     banks = ["select", "last"]
+//     mask = 0x07
+//
+// Mirroring names follow `Mirroring`, so "horizontal" places the nametables side by side.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Integer(i64),
+    Boolean(bool),
+    Text(String),
+    Array(Vec<Value>)
+}
+
+// What gets latched when the register is written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Latch {
+    Data,
+    Address
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Slot {
+    // The bank picked by the register.
+    Select,
+    // A fixed bank. Negative numbers count back from the last bank.
+    Fixed(i64)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MirroringControl {
+    Header,
+    Fixed(Mirroring),
+    // Bit of the latched value that picks between the two arrangements.
+    Select(u32, [Mirroring; 2])
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Banking {
+    pub bank_size : usize,
+    pub slots : Vec<Slot>,
+    pub shift : u32,
+    pub mask : usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardDescription {
+    pub name : String,
+    pub register_mask : u16,
+    pub register_match : u16,
+    pub latch : Latch,
+    pub bus_conflicts : bool,
+    pub prg : Banking,
+    pub prg_ram_size : usize,
+    pub chr : Banking,
+    pub mirroring : MirroringControl,
+}
+
+fn invalid(line : usize, message : &str) -> CoralError {
+    let error_message = format!("Board description, line {}: {}", line, message);
+    CoralError::InvalidData(error_message)
+}
+
+fn strip_comment(line : &str) -> &str {
+    let mut in_string = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => { in_string = !in_string; }
+            '#' if !in_string => { return &line[..index]; }
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_integer(text : &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text)
+    };
+    let digits = digits.replace('_', "");
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<i64>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_value(text : &str) -> Option<Value> {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let mut values = vec![];
+        for item in inner.split(',') {
+            if item.trim().is_empty() {
+                continue;
+            }
+            values.push(parse_value(item)?);
+        }
+        return Some(Value::Array(values));
+    }
+    if let Some(inner) = text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return Some(Value::Text(inner.to_string()));
+    }
+    match text {
+        "true" => Some(Value::Boolean(true)),
+        "false" => Some(Value::Boolean(false)),
+        _ => parse_integer(text).map(Value::Integer)
+    }
+}
+
+// Flattens the tables into "table.key" entries, remembering the line of each.
+fn parse_entries(text : &str) -> Result<HashMap<String, (Value, usize)>> {
+    let mut entries = HashMap::new();
+    let mut table = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            table = format!("{}.", name.trim());
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| invalid(line_number, "Expected key = value"))?;
+        let value = parse_value(value).ok_or_else(|| invalid(line_number, "Invalid value"))?;
+        let key = format!("{}{}", table, key.trim());
+        if entries.insert(key.clone(), (value, line_number)).is_some() {
+            return Err(invalid(line_number, &format!("Duplicate key {}", key)));
+        }
+    }
+    Ok(entries)
+}
+
+struct Entries(HashMap<String, (Value, usize)>);
+
+impl Entries {
+    fn take(&mut self, key : &str) -> Option<(Value, usize)> {
+        self.0.remove(key)
+    }
+    fn integer(&mut self, key : &str, default : i64) -> Result<i64> {
+        match self.take(key) {
+            None => Ok(default),
+            Some((Value::Integer(value), _)) => Ok(value),
+            Some((_, line)) => Err(invalid(line, &format!("{} must be an integer", key)))
+        }
+    }
+    // Like `integer`, but the value must lie in `range`.
+    fn integer_in(&mut self, key : &str, default : i64, range : std::ops::RangeInclusive<i64>) -> Result<i64> {
+        let line = self.0.get(key).map(|(_, line)| *line);
+        let value = self.integer(key, default)?;
+        match line {
+            Some(line) if !range.contains(&value) => {
+                Err(invalid(line, &format!("{} must be between {} and {}", key, range.start(), range.end())))
+            }
+            _ => Ok(value)
+        }
+    }
+    fn boolean(&mut self, key : &str, default : bool) -> Result<bool> {
+        match self.take(key) {
+            None => Ok(default),
+            Some((Value::Boolean(value), _)) => Ok(value),
+            Some((_, line)) => Err(invalid(line, &format!("{} must be true or false", key)))
+        }
+    }
+    fn text(&mut self, key : &str, default : &str) -> Result<String> {
+        match self.take(key) {
+            None => Ok(default.to_string()),
+            Some((Value::Text(value), _)) => Ok(value),
+            Some((_, line)) => Err(invalid(line, &format!("{} must be a string", key)))
+        }
+    }
+    fn array(&mut self, key : &str) -> Result<Option<(Vec<Value>, usize)>> {
+        match self.take(key) {
+            None => Ok(None),
+            Some((Value::Array(values), line)) => Ok(Some((values, line))),
+            Some((_, line)) => Err(invalid(line, &format!("{} must be an array", key)))
+        }
+    }
+}
+
+fn parse_mirroring(name : &str) -> Result<Mirroring> {
+    match name {
+        "horizontal" => Ok(Mirroring::Horizontal),
+        "vertical" => Ok(Mirroring::Vertical),
+        "one_screen_lower" => Ok(Mirroring::OneScreenLower),
+        "one_screen_upper" => Ok(Mirroring::OneScreenUpper),
+        _ => Err(CoralError::InvalidData(format!("Unknown mirroring {}", name)))
+    }
+}
+
+fn parse_slot(value : &Value, line : usize) -> Result<Slot> {
+    match value {
+        Value::Text(name) if name == "select" => Ok(Slot::Select),
+        Value::Text(name) if name == "first" => Ok(Slot::Fixed(0)),
+        Value::Text(name) if name == "last" => Ok(Slot::Fixed(-1)),
+        Value::Integer(bank) => Ok(Slot::Fixed(*bank)),
+        _ => Err(invalid(line, "Bank slots must be \"select\", \"first\", \"last\" or a bank number"))
+    }
+}
+
+// `window` is the size of the address range being banked, in KB.
+fn parse_banking(entries : &mut Entries, table : &str, window : usize, sizes : &[usize]) -> Result<Banking> {
+    let key = |name : &str| format!("{}.{}", table, name);
+    let bank_size = entries.integer(&key("bank_size"), window as i64)? as usize;
+    if !sizes.contains(&bank_size) {
+        let error_message = format!("{}.bank_size must be one of {:?}", table, sizes);
+        return Err(CoralError::InvalidData(error_message));
+    }
+    let slots = match entries.array(&key("banks"))? {
+        None => vec![Slot::Select; window / bank_size],
+        Some((values, line)) => {
+            let slots = values.iter().map(|value| parse_slot(value, line)).collect::<Result<Vec<Slot>>>()?;
+            if slots.len() * bank_size != window {
+                return Err(invalid(line, &format!("{}.banks must cover {} KB", table, window)));
+            }
+            slots
+        }
+    };
+    // The latch is 16 bits wide. Without a mask, every bit above `shift` picks the bank.
+    let shift = entries.integer_in(&key("shift"), 0, 0..=15)? as u32;
+    let mask = entries.integer_in(&key("mask"), 0xFFFF, 0..=0xFFFF)? as usize;
+    Ok(Banking { bank_size: bank_size * 0x0400, slots, shift, mask })
+}
+
+impl BoardDescription {
+    pub fn parse(text : &str) -> Result<BoardDescription> {
+        let mut entries = Entries(parse_entries(text)?);
+
+        let name = entries.text("name", "Generic discrete board")?;
+        let register_mask = entries.integer_in("register.mask", 0x8000, 0..=0xFFFF)? as u16;
+        let register_match = entries.integer_in("register.match", 0x8000, 0..=0xFFFF)? as u16;
+        let latch = match entries.text("register.latch", "data")?.as_str() {
+            "data" => Latch::Data,
+            "address" => Latch::Address,
+            _ => return Err(CoralError::InvalidData("register.latch must be \"data\" or \"address\"".to_string()))
+        };
+        let bus_conflicts = entries.boolean("register.bus_conflicts", false)?;
+
+        let prg = parse_banking(&mut entries, "prg", 32, &[8, 16, 32])?;
+        let prg_ram_size = entries.integer_in("prg.ram", 0, 0..=32)? as usize * 0x0400;
+        let chr = parse_banking(&mut entries, "chr", 8, &[1, 2, 4, 8])?;
+
+        let mode = entries.text("mirroring.mode", "header")?;
+        let mirroring = match mode.as_str() {
+            "header" => MirroringControl::Header,
+            "select" => {
+                let bit = entries.integer_in("mirroring.bit", 0, 0..=15)? as u32;
+                let (values, line) = entries.array("mirroring.values")?.ok_or_else(|| CoralError::InvalidData("mirroring.values is required with mode = \"select\"".to_string()))?;
+                let names = values.iter().map(|value| match value {
+                    Value::Text(name) => parse_mirroring(name),
+                    _ => Err(invalid(line, "mirroring.values must be strings"))
+                }).collect::<Result<Vec<Mirroring>>>()?;
+                if names.len() != 2 {
+                    return Err(invalid(line, "mirroring.values must have two entries"));
+                }
+                MirroringControl::Select(bit, [names[0], names[1]])
+            }
+            name => MirroringControl::Fixed(parse_mirroring(name)?)
+        };
+
+        if let Some((key, (_, line))) = entries.0.iter().min_by_key(|(_, (_, line))| *line) {
+            return Err(invalid(*line, &format!("Unknown key {}", key)));
+        }
+
+        Ok(BoardDescription { name, register_mask, register_match, latch, bus_conflicts, prg, prg_ram_size, chr, mirroring })
+    }
+}
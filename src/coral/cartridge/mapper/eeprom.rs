@@ -0,0 +1,179 @@
+// Serial EEPROMs driven bit by bit over I²C, as found on Bandai boards.
+//
+// The 24C02 follows the standard protocol: a device select byte, a word address and then data,
+// all MSB first. The older X24C01 has no device select byte; the start condition is followed
+// directly by a 7-bit word address plus the read/write bit, and everything is sent LSB first.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Chip {
+    X24C01,
+    C24C02
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Idle,
+    DeviceSelect,
+    Address,
+    Write,
+    Read,
+    // Acknowledging a received byte, then moving to `next`.
+    SendAck,
+    // Waiting for the master to acknowledge a byte we sent.
+    WaitAck
+}
+
+#[derive(Clone, Debug)]
+pub struct Eeprom {
+    chip : Chip,
+    mode : Mode,
+    next : Mode,
+    shift : u8,
+    bit : u8,
+    address : u8,
+    scl : bool,
+    sda : bool,
+    output : bool,
+    pub data : Vec<u8>,
+}
+
+impl Eeprom {
+    pub fn reset(&mut self) {
+        self.mode = Mode::Idle;
+        self.next = Mode::Idle;
+        self.shift = 0;
+        self.bit = 0;
+        self.scl = false;
+        self.sda = false;
+        self.output = true;
+    }
+    // Level of the SDA line as driven by the EEPROM.
+    pub fn read(&self) -> bool {
+        self.output
+    }
+    // Updates the SCL and SDA lines driven by the host.
+    pub fn write(&mut self, scl : bool, sda : bool) {
+        if self.scl && scl && sda != self.sda {
+            if sda {
+                // Stop condition
+                self.mode = Mode::Idle;
+                self.output = true;
+            } else {
+                // Start condition
+                self.mode = if self.chip == Chip::X24C01 { Mode::Address } else { Mode::DeviceSelect };
+                self.shift = 0;
+                self.bit = 0;
+                self.output = true;
+            }
+        } else if scl && !self.scl {
+            self.rising_edge(sda);
+        } else if !scl && self.scl {
+            self.falling_edge();
+        }
+        self.scl = scl;
+        self.sda = sda;
+    }
+    fn lsb_first(&self) -> bool {
+        self.chip == Chip::X24C01
+    }
+    fn size_mask(&self) -> u8 {
+        (self.data.len() - 1) as u8
+    }
+    fn receive_bit(&mut self, sda : bool) -> bool {
+        if self.lsb_first() {
+            self.shift |= (sda as u8) << self.bit;
+        } else {
+            self.shift = (self.shift << 1) | sda as u8;
+        }
+        self.bit += 1;
+        self.bit == 8
+    }
+    fn acknowledge(&mut self, next : Mode) {
+        self.mode = Mode::SendAck;
+        self.next = next;
+    }
+    fn rising_edge(&mut self, sda : bool) {
+        match self.mode {
+            Mode::DeviceSelect => {
+                if self.receive_bit(sda) {
+                    if self.shift & 0xF0 != 0xA0 {
+                        self.mode = Mode::Idle;
+                    } else if self.shift & 0x01 > 0 {
+                        self.acknowledge(Mode::Read);
+                    } else {
+                        self.acknowledge(Mode::Address);
+                    }
+                }
+            }
+            Mode::Address => {
+                if self.receive_bit(sda) {
+                    match self.chip {
+                        Chip::X24C01 => {
+                            self.address = self.shift & 0x7F;
+                            let next = if self.shift & 0x80 > 0 { Mode::Read } else { Mode::Write };
+                            self.acknowledge(next);
+                        }
+                        Chip::C24C02 => {
+                            self.address = self.shift;
+                            self.acknowledge(Mode::Write);
+                        }
+                    }
+                }
+            }
+            Mode::Write => {
+                if self.receive_bit(sda) {
+                    let mask = self.size_mask();
+                    self.data[(self.address & mask) as usize] = self.shift;
+                    // Writes wrap around within the current page.
+                    let page = if self.chip == Chip::X24C01 { 0x03 } else { 0x07 };
+                    self.address = (self.address & !page) | (self.address.wrapping_add(1) & page);
+                    self.acknowledge(Mode::Write);
+                }
+            }
+            Mode::Read => {
+                self.bit += 1;
+                if self.bit == 8 {
+                    self.address = self.address.wrapping_add(1) & self.size_mask();
+                    self.mode = Mode::WaitAck;
+                }
+            }
+            Mode::SendAck => {
+                self.mode = self.next;
+                self.shift = 0;
+                self.bit = 0;
+            }
+            Mode::WaitAck => {
+                self.mode = if sda { Mode::Idle } else { Mode::Read };
+                self.bit = 0;
+            }
+            Mode::Idle => {}
+        }
+    }
+    fn falling_edge(&mut self) {
+        self.output = match self.mode {
+            Mode::SendAck => false,
+            Mode::Read => {
+                let byte = self.data[(self.address & self.size_mask()) as usize];
+                let index = if self.lsb_first() { self.bit } else { 7 - self.bit };
+                (byte >> index) & 0x01 > 0
+            }
+            _ => true
+        };
+    }
+}
+
+pub fn new(chip : Chip) -> Eeprom {
+    let size = if chip == Chip::X24C01 { 0x80 } else { 0x100 };
+    Eeprom {
+        chip,
+        mode: Mode::Idle,
+        next: Mode::Idle,
+        shift: 0,
+        bit: 0,
+        address: 0,
+        scl: false,
+        sda: false,
+        output: true,
+        data: vec![0; size]
+    }
+}
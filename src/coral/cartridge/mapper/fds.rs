@@ -0,0 +1,355 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::audio::fds::{self, FdsAudio};
+use crate::coral::cartridge::disk::{self, DiskImage};
+use crate::coral::cartridge::patch;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Famicom Disk System RAM adapter: 32 KB of PRG RAM at $6000-$DFFF, the BIOS at $E000-$FFFF,
+// 8 KB of CHR RAM, a cycle timer IRQ, the disk drive and the FDS sound channel.
+//
+// The drive streams the raw side one byte at a time, raising the disk IRQ whenever a byte has
+// been transferred. Reaching the end of the side stops the motor, after which the head needs a
+// while to return to the start.
+
+// CPU cycles per byte at the drive's 96.4 kbit/s.
+const BYTE_DELAY : u32 = 150;
+// CPU cycles for the head to return to the start of the disk.
+const REWIND_DELAY : u32 = 50000;
+// CPU cycles a newly inserted side reads as ejected, so the BIOS notices the change of disks.
+const INSERT_DELAY : u32 = 1_800_000;
+
+#[derive(Clone, Debug)]
+pub struct Fds {
+    bios : Vec<u8>,
+    prg_ram : Vec<u8>,
+    chr_ram : Vec<u8>,
+    // Sides as loaded, in the .fds layout. Saves are stored as a patch against these.
+    original : Vec<Vec<u8>>,
+    disks : Vec<Vec<u8>>,
+    side : Option<usize>,
+    insert_delay : u32,
+    disk_registers : bool,
+    sound_registers : bool,
+    // Timer IRQ
+    irq_reload : u16,
+    irq_counter : u16,
+    irq_repeat : bool,
+    irq_enabled : bool,
+    timer_irq : bool,
+    // Drive, controlled through $4025
+    motor_on : bool,
+    transfer_reset : bool,
+    read_mode : bool,
+    mirroring : Mirroring,
+    crc_control : bool,
+    transfer_enabled : bool,
+    disk_irq_enabled : bool,
+    disk_irq : bool,
+    transfer_complete : bool,
+    read_data : u8,
+    write_data : u8,
+    external : u8,
+    position : usize,
+    delay : u32,
+    scanning : bool,
+    end_of_head : bool,
+    gap_ended : bool,
+    crc : u16,
+    previous_crc_control : bool,
+    audio : FdsAudio,
+}
+
+impl Fds {
+    fn inserted(&self) -> bool {
+        self.side.is_some() && self.insert_delay == 0
+    }
+    fn write_control(&mut self, byte : u8){
+        self.motor_on = byte & 0x01 > 0;
+        self.transfer_reset = byte & 0x02 > 0;
+        self.read_mode = byte & 0x04 > 0;
+        self.mirroring = if byte & 0x08 > 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+        self.crc_control = byte & 0x10 > 0;
+        self.transfer_enabled = byte & 0x40 > 0;
+        self.disk_irq_enabled = byte & 0x80 > 0;
+        self.disk_irq = false;
+    }
+    fn disk_status(&mut self) -> u8 {
+        let mut status = 0;
+        if self.timer_irq { status |= 0x01; }
+        if self.transfer_complete { status |= 0x02; }
+        if self.end_of_head { status |= 0x40; }
+        self.transfer_complete = false;
+        self.timer_irq = false;
+        self.disk_irq = false;
+        status
+    }
+    fn drive_status(&self) -> u8 {
+        let mut status = 0;
+        if !self.inserted() { status |= 0x05; }
+        if !self.inserted() || !self.scanning { status |= 0x02; }
+        status
+    }
+    fn tick_timer(&mut self){
+        if !self.irq_enabled || !self.disk_registers {
+            return;
+        }
+        if self.irq_counter == 0 {
+            self.timer_irq = true;
+            self.irq_counter = self.irq_reload;
+            if !self.irq_repeat {
+                self.irq_enabled = false;
+            }
+        } else {
+            self.irq_counter -= 1;
+        }
+    }
+    fn transfer_byte(&mut self, side : usize){
+        let mut request_irq = self.disk_irq_enabled;
+        if self.read_mode {
+            let byte = self.disks[side][self.position];
+            if !self.previous_crc_control {
+                self.crc = disk::update_crc(self.crc, byte);
+            }
+            if !self.transfer_enabled {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if byte != 0 && !self.gap_ended {
+                // The $80 that closes the gap is not handed to the CPU.
+                self.gap_ended = true;
+                request_irq = false;
+            }
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = byte;
+                self.disk_irq |= request_irq;
+            }
+        } else {
+            let mut byte = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                byte = self.write_data;
+                self.disk_irq |= request_irq;
+            }
+            if !self.transfer_enabled {
+                byte = 0;
+            }
+            if !self.crc_control {
+                self.crc = disk::update_crc(self.crc, byte);
+            } else {
+                if !self.previous_crc_control {
+                    self.crc = disk::update_crc(disk::update_crc(self.crc, 0), 0);
+                }
+                byte = self.crc as u8;
+                self.crc >>= 8;
+            }
+            self.disks[side][self.position] = byte;
+            self.gap_ended = false;
+        }
+        self.previous_crc_control = self.crc_control;
+    }
+    fn tick_drive(&mut self){
+        if self.insert_delay > 0 {
+            self.insert_delay -= 1;
+            return;
+        }
+        let side = match self.side {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            }
+        };
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = REWIND_DELAY;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+        self.scanning = true;
+        self.transfer_byte(side);
+        self.position += 1;
+        if self.position >= self.disks[side].len() {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_DELAY;
+        }
+    }
+    fn current_sides(&self) -> Vec<u8> {
+        self.disks.iter().flat_map(|raw| disk::from_raw(raw)).collect()
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x4020 => { self.irq_reload = (self.irq_reload & 0xFF00) | byte as u16; }
+            0x4021 => { self.irq_reload = (self.irq_reload & 0x00FF) | ((byte as u16) << 8); }
+            0x4022 => {
+                self.irq_repeat = byte & 0x01 > 0;
+                self.irq_enabled = byte & 0x02 > 0 && self.disk_registers;
+                if self.irq_enabled {
+                    self.irq_counter = self.irq_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4023 => {
+                self.disk_registers = byte & 0x01 > 0;
+                self.sound_registers = byte & 0x02 > 0;
+                if !self.disk_registers {
+                    self.irq_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            0x4024 if self.disk_registers => {
+                self.write_data = byte;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 if self.disk_registers => { self.write_control(byte); }
+            0x4026 if self.disk_registers => { self.external = byte; }
+            0x4040..=0x408A if self.sound_registers => { self.audio.write(address, byte); }
+            0x6000..=0xDFFF => { return Some(address as usize - 0x6000); }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl MapperT for Fds {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x4030 if self.disk_registers => self.disk_status(),
+            0x4031 if self.disk_registers => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+                self.read_data
+            }
+            0x4032 if self.disk_registers => self.drive_status(),
+            // Bit 7 reports a good battery in the drive. The rest reads back $4026.
+            0x4033 if self.disk_registers => 0x80 | (self.external & 0x7F),
+            0x4040..=0x407F | 0x4090 | 0x4092 => self.audio.read(address),
+            0x6000..=0xDFFF => self.prg_ram[address as usize - 0x6000],
+            0xE000..=0xFFFF => self.bios[address as usize & 0x1FFF],
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.cpu_w_map(address, byte) {
+            self.prg_ram[mapped_address] = byte;
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        self.chr_ram[address as usize & 0x1FFF]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        self.chr_ram[address as usize & 0x1FFF] = byte;
+    }
+    fn reset(&mut self, kind : Reset) {
+        if kind == Reset::PowerOn {
+            self.prg_ram.fill(0);
+            self.chr_ram.fill(0);
+        }
+        self.disk_registers = false;
+        self.sound_registers = false;
+        self.irq_enabled = false;
+        self.timer_irq = false;
+        self.write_control(0);
+        self.transfer_complete = false;
+        self.end_of_head = true;
+        self.scanning = false;
+        self.audio.reset();
+    }
+    fn cpu_tick(&mut self) {
+        self.audio.tick();
+        self.tick_timer();
+        self.tick_drive();
+    }
+    fn irq(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+    // Changes to the disks, as an IPS patch against the sides as loaded.
+    fn save_data(&self) -> Option<Vec<u8>> {
+        let original = self.original.concat();
+        let current = self.current_sides();
+        if original == current { None } else { patch::create_ips(&original, &current).ok() }
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        let original = self.original.concat();
+        if let Ok(patched) = patch::apply_ips(&original, data) {
+            if patched.len() == original.len() {
+                self.disks = patched.chunks(disk::SIDE_SIZE).map(disk::to_raw).collect();
+            }
+        }
+    }
+    fn disk_sides(&self) -> usize {
+        self.disks.len()
+    }
+    fn disk_side(&self) -> Option<usize> {
+        self.side
+    }
+    fn insert_disk(&mut self, side : Option<usize>) {
+        self.side = side;
+        self.insert_delay = if side.is_some() { INSERT_DELAY } else { 0 };
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, image : DiskImage, bios : Vec<u8>){
+    let disks = image.sides.iter().map(|side| disk::to_raw(side)).collect();
+    let mut fds = Fds {
+        bios,
+        prg_ram: vec![0; 0x8000],
+        chr_ram: vec![0; 0x2000],
+        original: image.sides,
+        disks,
+        side: Some(0),
+        insert_delay: 0,
+        disk_registers: false,
+        sound_registers: false,
+        irq_reload: 0,
+        irq_counter: 0,
+        irq_repeat: false,
+        irq_enabled: false,
+        timer_irq: false,
+        motor_on: false,
+        transfer_reset: false,
+        read_mode: false,
+        mirroring: Mirroring::Horizontal,
+        crc_control: false,
+        transfer_enabled: false,
+        disk_irq_enabled: false,
+        disk_irq: false,
+        transfer_complete: false,
+        read_data: 0,
+        write_data: 0,
+        external: 0,
+        position: 0,
+        delay: 0,
+        scanning: false,
+        end_of_head: true,
+        gap_ended: false,
+        crc: 0,
+        previous_crc_control: false,
+        audio: fds::new(),
+    };
+    fds.reset(Reset::PowerOn);
+    cartridge.mapper = Mapper(Box::new(fds));
+}
@@ -0,0 +1,61 @@
+// Command decoder of the AMD-style flash chips (SST39SF0x0) used by self-flashing homebrew
+// boards. Unlock cycles go to $5555/$2AAA of the chip; programming can only clear bits,
+// erasing sets a 4 KB sector or the whole chip back to $FF.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    Idle,
+    Unlock1,
+    Unlock2,
+    Program,
+    EraseUnlock,
+    EraseUnlock1,
+    EraseUnlock2
+}
+
+#[derive(Clone, Debug)]
+pub struct Flash {
+    state : State,
+}
+
+impl Flash {
+    pub fn reset(&mut self) {
+        self.state = State::Idle;
+    }
+    // Handles a CPU write that reached the chip at `offset`. `data` is the whole chip.
+    pub fn write(&mut self, data : &mut [u8], offset : usize, byte : u8) {
+        let command = offset & 0x7FFF;
+        if byte == 0xF0 && self.state != State::Program {
+            self.state = State::Idle;
+            return;
+        }
+        self.state = match (self.state, command, byte) {
+            (State::Idle, 0x5555, 0xAA) => State::Unlock1,
+            (State::Unlock1, 0x2AAA, 0x55) => State::Unlock2,
+            (State::Unlock2, 0x5555, 0xA0) => State::Program,
+            (State::Unlock2, 0x5555, 0x80) => State::EraseUnlock,
+            (State::Program, _, _) => {
+                data[offset % data.len()] &= byte;
+                State::Idle
+            }
+            (State::EraseUnlock, 0x5555, 0xAA) => State::EraseUnlock1,
+            (State::EraseUnlock1, 0x2AAA, 0x55) => State::EraseUnlock2,
+            (State::EraseUnlock2, 0x5555, 0x10) => {
+                data.fill(0xFF);
+                State::Idle
+            }
+            (State::EraseUnlock2, _, 0x30) => {
+                let sector = (offset % data.len()) & !0x0FFF;
+                data[sector..sector + 0x1000].fill(0xFF);
+                State::Idle
+            }
+            _ => State::Idle
+        };
+    }
+}
+
+pub fn new() -> Flash {
+    Flash {
+        state: State::Idle
+    }
+}
@@ -0,0 +1,137 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::description::{Banking, BoardDescription, Latch, MirroringControl, Slot};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// A discrete board whose single register is decoded according to a BoardDescription instead of
+// hand-written code. Covers the latch-based boards (UxROM, CNROM, AxROM, GxROM and the like)
+// that only differ in which bits pick the banks and the mirroring.
+
+#[derive(Clone, Debug)]
+pub struct GenericDiscreteMapper {
+    description : BoardDescription,
+    latch : u16,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+// Offset into `data` for `address`, whose banked window starts at `base`.
+fn map(banking : &Banking, latch : u16, data_size : usize, base : u16, address : u16) -> usize {
+    let offset = (address - base) as usize;
+    // A ROM smaller than a bank, e.g. 16 KB of PRG in a 32 KB window, is mirrored to fill it.
+    let bank_size = banking.bank_size.min(data_size);
+    let bank_count = data_size / bank_size;
+    let bank = match banking.slots[offset / banking.bank_size] {
+        Slot::Select => ((latch as usize) >> banking.shift) & banking.mask,
+        Slot::Fixed(bank) if bank < 0 => (bank_count as i64 + bank).max(0) as usize,
+        Slot::Fixed(bank) => bank as usize
+    };
+    (bank % bank_count) * bank_size + (offset % bank_size)
+}
+
+impl GenericDiscreteMapper {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        map(&self.description.prg, self.latch, self.prg_data.len(), 0x8000, address)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if address & self.description.register_mask == self.description.register_match {
+            self.latch = match self.description.latch {
+                Latch::Address => address,
+                // The ROM drives the bus at the same time, so only bits that are 1 on both survive.
+                Latch::Data if self.description.bus_conflicts && address >= 0x8000 => {
+                    let mapped_address = self.cpu_r_map(address);
+                    (byte & self.prg_data[mapped_address]) as u16
+                }
+                Latch::Data => byte as u16
+            };
+            return None;
+        }
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => Some((address as usize - 0x6000) % self.prg_ram.len()),
+            _ => None
+        }
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        map(&self.description.chr, self.latch, self.chr_data.len(), 0x0000, address & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for GenericDiscreteMapper {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => self.prg_ram[(address as usize - 0x6000) % self.prg_ram.len()],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.cpu_w_map(address, byte) {
+            self.prg_ram[mapped_address] = byte;
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.latch = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.description.mirroring {
+            MirroringControl::Header => None,
+            MirroringControl::Fixed(mirroring) => Some(mirroring),
+            MirroringControl::Select(bit, values) => Some(values[((self.latch >> bit) & 0x01) as usize])
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        if self.prg_ram.is_empty() { None } else { Some(self.prg_ram.clone()) }
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, description : BoardDescription){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let prg_ram_size = description.prg_ram_size;
+
+    let generic = GenericDiscreteMapper {
+        description,
+        latch: 0,
+        prg_ram: vec![0; prg_ram_size],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(generic));
+}
@@ -51,8 +51,8 @@ impl MapperT for Mapper0 {
             None => {}
         }
     }
-    fn reset(&mut self) {
-        // TODO: Not implemented. Fix.
+    fn reset(&mut self, _kind : Reset) {
+        // NROM has no registers.
     }
     fn clone_self(&self) -> Box<dyn MapperT> {
         Box::new(self.clone())
@@ -66,7 +66,7 @@ pub fn choose(cartridge : &mut types::Cartridge){
     let chr_banks = cartridge.header.h_chr_size as usize;
 
     let prg_data_size = 0x4000 * prg_banks;
-    let chr_data_size = if chr_banks == 0 {0x2000} else {0x2000 * chr_banks};
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
 
     let mut prg_data = vec![0; prg_data_size];
     let mut chr_data = vec![0; chr_data_size];
@@ -0,0 +1,166 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::mmc1::{self, Mmc1, Register};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// NES-EVENT, the Nintendo World Championships 1990 cartridge. An MMC1 whose CHR register 0
+// drives extra PRG logic and a 30-bit countdown timer instead of CHR banking. The four DIP
+// switches set how long the timer runs before it raises an IRQ.
+//
+// At power-on the first 32 KB are locked in until bit 4 of CHR register 0 goes low and
+// then high again.
+
+// 6:15 minutes on an NTSC console.
+const DEFAULT_DIP_SWITCHES : u8 = 0x04;
+
+#[derive(Clone, Debug)]
+pub struct Mapper105 {
+    mmc1 : Mmc1,
+    init_state : u8,
+    timer : u32,
+    irq_pending : bool,
+    dip_switches : u8,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper105 {
+    fn timer_running(&self) -> bool {
+        self.mmc1.chr_0 & 0x10 == 0
+    }
+    fn timer_target(&self) -> u32 {
+        0x2000_0000 | ((self.dip_switches as u32 & 0x0F) << 25)
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        let bank = if self.init_state < 2 {
+            (uaddress >> 14) & 0x01
+        } else if self.mmc1.chr_0 & 0x08 == 0 {
+            // First 128 KB chip, 32 KB banks.
+            ((self.mmc1.chr_0 as usize >> 1) & 0x03) * 2 + ((uaddress >> 14) & 0x01)
+        } else {
+            // Second 128 KB chip, regular MMC1 banking.
+            8 + self.mmc1.prg_bank(address, 8)
+        };
+        (bank % prg_16k_banks) * 0x4000 + (uaddress & 0x3FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x6000..=0x7FFF => { return Some(address as usize & 0x1FFF); }
+            0x8000..=0xFFFF => { self.write_register(address, byte); }
+            _ => {}
+        }
+        None
+    }
+    fn write_register(&mut self, address : u16, byte : u8) {
+        if self.mmc1.write(address, byte) != Some(Register::Chr0) {
+            return;
+        }
+        let high = self.mmc1.chr_0 & 0x10 > 0;
+        self.init_state = match (self.init_state, high) {
+            (0, false) => 1,
+            (1, true) => 2,
+            (state, _) => state
+        };
+        if high {
+            self.timer = 0;
+            self.irq_pending = false;
+        }
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        address as usize & 0x1FFF
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+impl MapperT for Mapper105 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.mmc1.prg_ram_enabled() => self.prg_ram[address as usize & 0x1FFF],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.mmc1.prg_ram_enabled() {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.mmc1.reset();
+        self.init_state = 0;
+        self.timer = 0;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.timer_running() {
+            self.timer = self.timer.wrapping_add(1);
+            if self.timer == self.timer_target() {
+                self.irq_pending = true;
+            }
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mmc1.mirroring())
+    }
+    fn dip_switches(&self) -> Option<u8> {
+        Some(self.dip_switches)
+    }
+    fn set_dip_switches(&mut self, value : u8) {
+        self.dip_switches = value & 0x0F;
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let mut prg_data = vec![0; prg_data_size];
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+
+    let mapper105 = Mapper105 {
+        mmc1: mmc1::new(),
+        init_state: 0,
+        timer: 0,
+        irq_pending: false,
+        dip_switches: DEFAULT_DIP_SWITCHES,
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data: vec![0; 0x2000]
+    };
+    cartridge.mapper = Mapper(Box::new(mapper105));
+}
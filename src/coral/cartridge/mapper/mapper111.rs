@@ -0,0 +1,118 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::flash::{self, Flash};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Nametable;
+
+// GTROM (Cheapocabra). A single register at $5000-$5FFF/$7000-$7FFF selects a 32 KB PRG bank,
+// one of two 8 KB CHR RAM pages, one of two 8 KB four-screen nametable pages, and drives two LEDs.
+// The PRG flash can be rewritten by the program.
+
+#[derive(Clone, Debug)]
+pub struct Mapper111 {
+    register : u8,
+    flash : Flash,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    nt_ram : Vec<u8>,
+}
+
+impl Mapper111 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_32k_banks = (self.prg_data.len() / 0x8000).max(1);
+        let bank = (self.register & 0x0F) as usize % prg_32k_banks;
+        (bank * 0x8000 + (address as usize & 0x7FFF)) % self.prg_data.len()
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x5000..=0x5FFF | 0x7000..=0x7FFF => { self.register = byte; None }
+            0x8000..=0xFFFF => Some(self.cpu_r_map(address)),
+            _ => None
+        }
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let bank = ((self.register >> 4) & 0x01) as usize;
+        bank * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+    fn nt_map(&self, address : u16) -> usize {
+        let page = ((self.register >> 5) & 0x01) as usize;
+        // $3000-$3EFF mirrors the four nametables.
+        page * 0x2000 + (address as usize & 0x0FFF)
+    }
+}
+
+impl MapperT for Mapper111 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(offset) = optional_map {
+            self.flash.write(&mut self.prg_data, offset, byte);
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.register = 0;
+        self.flash.reset();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn nametable(&mut self, _address : u16) -> Option<Nametable> {
+        Some(Nametable::Cartridge)
+    }
+    fn nt_read(&mut self, address : u16) -> u8 {
+        self.nt_ram[self.nt_map(address)]
+    }
+    fn nt_write(&mut self, address : u16, byte : u8) {
+        let mapped_address = self.nt_map(address);
+        self.nt_ram[mapped_address] = byte;
+    }
+    // Bits 6 and 7 drive the green and red LEDs, which light up when the bit is clear.
+    fn leds(&self) -> u8 {
+        (!self.register >> 6) & 0x03
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_data.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_data.len() {
+            self.prg_data.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let mut prg_data = vec![0; prg_data_size];
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+
+    let mapper111 = Mapper111 {
+        register: 0,
+        flash: flash::new(),
+        prg_data,
+        chr_data: vec![0; 0x4000],
+        nt_ram: vec![0; 0x4000]
+    };
+    cartridge.mapper = Mapper(Box::new(mapper111));
+}
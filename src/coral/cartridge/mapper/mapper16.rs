@@ -0,0 +1,269 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::eeprom::{self, Chip, Eeprom};
+use crate::coral::cartridge::mapper::barcode::{self, BarcodeReader};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Bandai FCG-1/FCG-2 and LZ93D50. Sixteen registers (mirrored every 16 bytes) control CHR and
+// PRG banking, mirroring and a CPU cycle IRQ counter. LZ93D50 boards save to a serial EEPROM
+// wired to register $D instead of having battery-backed SRAM.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    // Mapper 16 submapper 4: registers at $6000-$7FFF, IRQ counter written directly.
+    Fcg,
+    // Mapper 16 submapper 5: registers at $8000-$FFFF, latched IRQ counter, 24C02 EEPROM.
+    Lz93d50,
+    // Mapper 16 without a submapper: registers at both ranges, 24C02 EEPROM.
+    Unspecified,
+    // Mapper 153: 8 KB of battery-backed SRAM, and the CHR registers select a 256 KB PRG half.
+    Lz93d50Sram,
+    // Mapper 157: Datach Joint ROM System, 24C02 EEPROM plus the barcode reader.
+    Datach,
+    // Mapper 159: LZ93D50 with a 24C01 EEPROM.
+    Lz93d50X24c01
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapper16 {
+    board : Board,
+    chr_banks : [usize; 8],
+    prg_bank : usize,
+    mirroring : u8,
+    irq_enabled : bool,
+    irq_counter : u16,
+    irq_latch : u16,
+    irq_pending : bool,
+    ram_enabled : bool,
+    eeprom : Option<Eeprom>,
+    barcode : Option<BarcodeReader>,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper16 {
+    fn registers_at(&self, address : u16) -> bool {
+        match (self.board, address) {
+            (Board::Fcg, 0x6000..=0x7FFF) => true,
+            (Board::Fcg, _) => false,
+            (Board::Unspecified, 0x6000..=0xFFFF) => true,
+            (_, 0x8000..=0xFFFF) => true,
+            _ => false
+        }
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        // Mapper 153 uses bit 0 of the CHR registers as PRG A18.
+        let outer = if self.board == Board::Lz93d50Sram { (self.chr_banks.iter().fold(0, |acc, b| acc | b) & 0x01) << 4 } else { 0 };
+        let bank = match address {
+            0x8000..=0xBFFF => outer | (self.prg_bank & 0x0F),
+            _ => outer | 0x0F
+        };
+        (bank % prg_16k_banks) * 0x4000 + (address as usize & 0x3FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if !self.registers_at(address) {
+            return match address {
+                0x6000..=0x7FFF => Some(address as usize & 0x1FFF),
+                _ => None
+            };
+        }
+        match address & 0x0F {
+            0x0..=0x7 => { self.chr_banks[(address & 0x07) as usize] = byte as usize; }
+            0x8 => { self.prg_bank = byte as usize; }
+            0x9 => { self.mirroring = byte & 0x03; }
+            0xA => {
+                self.irq_enabled = byte & 0x01 > 0;
+                self.irq_pending = false;
+                if self.board != Board::Fcg {
+                    self.irq_counter = self.irq_latch;
+                }
+            }
+            0xB => {
+                if self.board == Board::Fcg {
+                    self.irq_counter = (self.irq_counter & 0xFF00) | byte as u16;
+                } else {
+                    self.irq_latch = (self.irq_latch & 0xFF00) | byte as u16;
+                }
+            }
+            0xC => {
+                if self.board == Board::Fcg {
+                    self.irq_counter = (self.irq_counter & 0x00FF) | ((byte as u16) << 8);
+                } else {
+                    self.irq_latch = (self.irq_latch & 0x00FF) | ((byte as u16) << 8);
+                }
+            }
+            0xD => {
+                self.ram_enabled = byte & 0x20 > 0;
+                if let Some(eeprom) = self.eeprom.as_mut() {
+                    eeprom.write(byte & 0x20 > 0, byte & 0x40 > 0);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        // Mapper 153 boards use CHR RAM and ignore the CHR registers for CHR.
+        let bank = if self.board == Board::Lz93d50Sram { (uaddress >> 10) & 0x07 } else { self.chr_banks[(uaddress >> 10) & 0x07] };
+        (bank % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper16 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.board == Board::Lz93d50Sram && self.ram_enabled => self.prg_ram[address as usize & 0x1FFF],
+            0x6000..=0x7FFF if self.board == Board::Lz93d50Sram => 0,
+            0x6000..=0x7FFF => {
+                let sda = match &self.eeprom {
+                    Some(eeprom) => (eeprom.read() as u8) << 4,
+                    None => 0
+                };
+                let bar = match &self.barcode {
+                    Some(barcode) => barcode.output(),
+                    None => 0
+                };
+                sda | bar
+            }
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.board == Board::Lz93d50Sram && self.ram_enabled {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.chr_banks = [0; 8];
+        self.prg_bank = 0;
+        self.mirroring = 0;
+        self.irq_enabled = false;
+        self.irq_counter = 0;
+        self.irq_latch = 0;
+        self.irq_pending = false;
+        self.ram_enabled = false;
+        if let Some(eeprom) = self.eeprom.as_mut() {
+            eeprom.reset();
+        }
+        if let Some(barcode) = self.barcode.as_mut() {
+            barcode.reset();
+        }
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_enabled {
+            if self.irq_counter == 0 {
+                self.irq_pending = true;
+            }
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+        }
+        if let Some(barcode) = self.barcode.as_mut() {
+            barcode.tick();
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        match &self.eeprom {
+            Some(eeprom) => Some(eeprom.data.clone()),
+            None if self.board == Board::Lz93d50Sram => Some(self.prg_ram.clone()),
+            None => None
+        }
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        let target = match self.eeprom.as_mut() {
+            Some(eeprom) => &mut eeprom.data,
+            None => &mut self.prg_ram
+        };
+        if data.len() == target.len() {
+            target.copy_from_slice(data);
+        }
+    }
+    fn scan_barcode(&mut self, code : &str) -> bool {
+        match self.barcode.as_mut() {
+            Some(barcode) => barcode.scan(code),
+            None => false
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let eeprom = match board {
+        Board::Lz93d50 | Board::Unspecified | Board::Datach => Some(eeprom::new(Chip::C24C02)),
+        Board::Lz93d50X24c01 => Some(eeprom::new(Chip::X24C01)),
+        _ => None
+    };
+    // EEPROM contents survive power-off without a battery, so save them regardless of the header.
+    if eeprom.is_some() {
+        cartridge.header.h_battery = true;
+    }
+    let barcode = if board == Board::Datach { Some(barcode::new()) } else { None };
+    let prg_ram_size = if board == Board::Lz93d50Sram {0x2000} else {0};
+
+    let mapper16 = Mapper16 {
+        board,
+        chr_banks: [0; 8],
+        prg_bank: 0,
+        mirroring: 0,
+        irq_enabled: false,
+        irq_counter: 0,
+        irq_latch: 0,
+        irq_pending: false,
+        ram_enabled: false,
+        eeprom,
+        barcode,
+        prg_ram: vec![0; prg_ram_size],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper16));
+}
@@ -0,0 +1,187 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Jaleco SS88006. Bank numbers are written a nibble at a time. The IRQ counter counts CPU
+// cycles and can be narrowed to its low 4, 8 or 12 bits, which then wrap on their own.
+
+#[derive(Clone, Debug)]
+pub struct Mapper18 {
+    prg_banks : [usize; 3],
+    chr_banks : [usize; 8],
+    ram_control : u8,
+    mirroring : u8,
+    irq_latch : u16,
+    irq_counter : u16,
+    irq_control : u8,
+    irq_pending : bool,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+fn write_nibble(value : usize, high : bool, byte : u8) -> usize {
+    if high {
+        (value & 0x0F) | (((byte & 0x0F) as usize) << 4)
+    } else {
+        (value & 0xF0) | (byte & 0x0F) as usize
+    }
+}
+
+impl Mapper18 {
+    fn irq_mask(&self) -> u16 {
+        match self.irq_control {
+            c if c & 0x08 > 0 => 0x000F,
+            c if c & 0x04 > 0 => 0x00FF,
+            c if c & 0x02 > 0 => 0x0FFF,
+            _ => 0xFFFF
+        }
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF => self.prg_banks[2],
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        let high = address & 0x01 > 0;
+        match address & 0xF003 {
+            0x6000..=0x7FFF => { return Some(address as usize & 0x1FFF); }
+            0x8000 | 0x8001 => { self.prg_banks[0] = write_nibble(self.prg_banks[0], high, byte); }
+            0x8002 | 0x8003 => { self.prg_banks[1] = write_nibble(self.prg_banks[1], high, byte); }
+            0x9000 | 0x9001 => { self.prg_banks[2] = write_nibble(self.prg_banks[2], high, byte); }
+            0x9002 => { self.ram_control = byte & 0x03; }
+            0xA000..=0xD003 => {
+                let index = (((address - 0xA000) >> 11) & 0x06) as usize | ((address >> 1) & 0x01) as usize;
+                self.chr_banks[index] = write_nibble(self.chr_banks[index], high, byte);
+            }
+            0xE000..=0xE003 => {
+                let shift = (address & 0x03) * 4;
+                self.irq_latch = (self.irq_latch & !(0x0F << shift)) | (((byte & 0x0F) as u16) << shift);
+            }
+            0xF000 => { self.irq_counter = self.irq_latch; self.irq_pending = false; }
+            0xF001 => { self.irq_control = byte & 0x0F; self.irq_pending = false; }
+            0xF002 => { self.mirroring = byte & 0x03; }
+            // $F003 drives the uPD7756 ADPCM chip, which is not emulated.
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_banks[(uaddress >> 10) & 0x07] % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper18 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.ram_control & 0x01 > 0 => self.prg_ram[address as usize & 0x1FFF],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.ram_control == 0x03 {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0, 1, 2];
+        self.chr_banks = [0; 8];
+        self.ram_control = 0;
+        self.mirroring = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_control = 0;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_control & 0x01 == 0 {
+            return;
+        }
+        let mask = self.irq_mask();
+        let count = self.irq_counter & mask;
+        self.irq_counter = (self.irq_counter & !mask) | (count.wrapping_sub(1) & mask);
+        if count == 0 {
+            self.irq_pending = true;
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Vertical),
+            1 => Some(Mirroring::Horizontal),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper18 = Mapper18 {
+        prg_banks: [0, 1, 2],
+        chr_banks: [0; 8],
+        ram_control: 0,
+        mirroring: 0,
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_control: 0,
+        irq_pending: false,
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper18));
+}
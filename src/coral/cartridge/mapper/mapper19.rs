@@ -0,0 +1,211 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::audio::n163::{self, N163Audio};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Nametable;
+
+// Namco 163. Nametables can be individually mapped to console VRAM or to CHR-ROM pages, and so
+// can the pattern tables, and the chip's 128 bytes of internal RAM double as wavetable and
+// register memory for its sound.
+
+#[derive(Clone, Debug)] 
+pub struct Mapper19 {
+    prg_banks : [usize; 3],
+    chr_banks : [usize; 8],
+    nt_banks : [usize; 4],
+    // $E800 bits 6 and 7. When set, pattern banks $E0-$FF of the low or high pattern table select
+    // CHR ROM instead of console VRAM.
+    chr_ciram_disabled : [bool; 2],
+    sound_disabled : bool,
+    write_protect : u8,
+    irq_counter : u16,
+    irq_enabled : bool,
+    irq_pending : bool,
+    audio : N163Audio,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper19 {
+    fn prg_ram_writable(&self, address : u16) -> bool {
+        let section = (address as usize & 0x1FFF) >> 11;
+        (self.write_protect & 0xF0) == 0x40 && (self.write_protect >> section) & 0x01 == 0
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0] % prg_8k_banks,
+            0xA000..=0xBFFF => self.prg_banks[1] % prg_8k_banks,
+            0xC000..=0xDFFF => self.prg_banks[2] % prg_8k_banks,
+            _ => prg_8k_banks - 1
+        };
+        bank * 0x2000 + (uaddress & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x4800..=0x4FFF => { self.audio.write_data(byte); }
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | byte as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | (((byte & 0x7F) as u16) << 8);
+                self.irq_enabled = byte & 0x80 > 0;
+                self.irq_pending = false;
+            }
+            0x6000..=0x7FFF => { return Some(address as usize & 0x1FFF); }
+            0x8000..=0xBFFF => { self.chr_banks[((address - 0x8000) >> 11) as usize] = byte as usize; }
+            0xC000..=0xDFFF => { self.nt_banks[((address - 0xC000) >> 11) as usize] = byte as usize; }
+            0xE000..=0xE7FF => {
+                self.prg_banks[0] = (byte & 0x3F) as usize;
+                self.sound_disabled = byte & 0x40 > 0;
+            }
+            0xE800..=0xEFFF => {
+                self.prg_banks[1] = (byte & 0x3F) as usize;
+                self.chr_ciram_disabled = [byte & 0x40 > 0, byte & 0x80 > 0];
+            }
+            0xF000..=0xF7FF => { self.prg_banks[2] = (byte & 0x3F) as usize; }
+            0xF800..=0xFFFF => {
+                self.write_protect = byte;
+                self.audio.write_address(byte);
+            }
+            _ => {}
+        }
+        None
+    }
+    fn chr_page(&self, bank : usize, address : u16) -> usize {
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (bank % chr_1k_banks) * 0x0400 + (address as usize & 0x03FF)
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let slot = (address as usize >> 10) & 0x07;
+        self.chr_page(self.chr_banks[slot], address)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+
+impl MapperT for Mapper19 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x4800..=0x4FFF => self.audio.read_data(),
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => ((self.irq_counter >> 8) as u8) | if self.irq_enabled { 0x80 } else { 0x00 },
+            0x6000..=0x7FFF => self.prg_ram[address as usize & 0x1FFF],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.prg_ram_writable(address) {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0; 3];
+        self.chr_banks = [0; 8];
+        self.nt_banks = [0xE0, 0xE1, 0xE0, 0xE1];
+        self.chr_ciram_disabled = [false; 2];
+        self.sound_disabled = false;
+        self.write_protect = 0;
+        self.irq_counter = 0;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+        self.audio.reset();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_enabled && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7FFF {
+                self.irq_pending = true;
+            }
+        }
+        if !self.sound_disabled {
+            self.audio.tick();
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn audio_output(&self) -> f32 {
+        if self.sound_disabled { 0.0 } else { self.audio.output() }
+    }
+    fn nametable(&mut self, address : u16) -> Option<Nametable> {
+        let bank = self.nt_banks[((address >> 10) & 0x03) as usize];
+        if bank >= 0xE0 { Some(Nametable::Ciram(bank & 0x01)) } else { Some(Nametable::Cartridge) }
+    }
+    fn chr_ciram(&mut self, address : u16) -> Option<usize> {
+        let bank = self.chr_banks[(address as usize >> 10) & 0x07];
+        let half = (address as usize >> 12) & 0x01;
+        if bank >= 0xE0 && !self.chr_ciram_disabled[half] { Some(bank & 0x01) } else { None }
+    }
+    fn nt_read(&mut self, address : u16) -> u8 {
+        let bank = self.nt_banks[((address >> 10) & 0x03) as usize];
+        self.chr_data[self.chr_page(bank, address)]
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        let mut data = self.prg_ram.clone();
+        data.extend_from_slice(&self.audio.ram);
+        Some(data)
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() + self.audio.ram.len() {
+            let (prg_ram, internal_ram) = data.split_at(self.prg_ram.len());
+            self.prg_ram.copy_from_slice(prg_ram);
+            self.audio.ram.copy_from_slice(internal_ram);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper19 = Mapper19 {
+        prg_banks: [0; 3],
+        chr_banks: [0; 8],
+        nt_banks: [0xE0, 0xE1, 0xE0, 0xE1],
+        chr_ciram_disabled: [false; 2],
+        sound_disabled: false,
+        write_protect: 0,
+        irq_counter: 0,
+        irq_enabled: false,
+        irq_pending: false,
+        audio: n163::new(),
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper19));
+}
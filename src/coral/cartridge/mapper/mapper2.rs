@@ -5,7 +5,7 @@ use crate::coral::cartridge::types;
 #[derive(Clone, Debug)] 
 pub struct Mapper2 {
     selected_bank : usize,
-    switchable_banks : u8,
+    switchable_banks : u16,
     prg_data : Vec<u8>,
     chr_data : Vec<u8>,
 }
@@ -62,8 +62,8 @@ impl MapperT for Mapper2 {
             None => {}
         }
     }
-    fn reset(&mut self) {
-        // TODO: Not implemented. Fix.
+    fn reset(&mut self, _kind : Reset) {
+        self.selected_bank = 0;
     }
     fn clone_self(&self) -> Box<dyn MapperT> {
         Box::new(self.clone())
@@ -80,7 +80,7 @@ pub fn choose(cartridge : &mut types::Cartridge) {
     let chr_banks = cartridge.header.h_chr_size as usize;
 
     let prg_data_size = 0x4000 * prg_banks;
-    let chr_data_size = if chr_banks == 0 {0x2000} else {0x2000 * chr_banks};
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
 
     let mut prg_data = vec![0; prg_data_size];
     let mut chr_data = vec![0; chr_data_size];
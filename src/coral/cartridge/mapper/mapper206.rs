@@ -0,0 +1,156 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+
+// Namco 108 (a.k.a. Namcot 118, DxROM) and the boards derived from it. The chip is an early
+// MMC3 without IRQs, mirroring control or PRG RAM, and with the banking modes fixed.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    // Mapper 206: the plain chip.
+    Namco108,
+    // Mapper 76: R2-R5 select four 2 KB CHR banks, R0/R1 are unused.
+    Namco3446,
+    // Mapper 88: CHR A16 is tied to PPU A12, so each pattern table sees its own 64 KB.
+    Namco3433,
+    // Mapper 95: bit 5 of R0/R1 drives CIRAM A10.
+    Namco3425,
+    // Mapper 154: mapper 88 plus one-screen mirroring from bit 6 of any register write.
+    Namco3453
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapper206 {
+    board : Board,
+    bank_select : u8,
+    registers : [usize; 8],
+    one_screen : u8,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper206 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.registers[6] & 0x0F,
+            0xA000..=0xBFFF => self.registers[7] & 0x0F,
+            0xC000..=0xDFFF => prg_8k_banks - 2,
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if self.board == Board::Namco3453 && address >= 0x8000 {
+            self.one_screen = (byte >> 6) & 0x01;
+        }
+        match address {
+            0x8000..=0x9FFF if address & 0x01 == 0 => { self.bank_select = byte & 0x07; }
+            0x8000..=0x9FFF => { self.registers[self.bank_select as usize] = (byte & 0x3F) as usize; }
+            _ => {}
+        }
+        None
+    }
+    // Byte offset of the 1 KB CHR bank covering `address`.
+    fn chr_offset(&self, address : u16) -> usize {
+        let slot = ((address >> 10) & 0x07) as usize;
+        let bank = match self.board {
+            Board::Namco3446 => (self.registers[2 + slot / 2] << 1) | (slot & 0x01),
+            _ => {
+                let bank = match slot {
+                    0 | 1 => (self.registers[0] & 0xFE) | (slot & 0x01),
+                    2 | 3 => (self.registers[1] & 0xFE) | (slot & 0x01),
+                    _ => self.registers[slot - 2]
+                };
+                match self.board {
+                    Board::Namco3433 | Board::Namco3453 if slot < 4 => bank & 0x3F,
+                    Board::Namco3433 | Board::Namco3453 => bank | 0x40,
+                    Board::Namco3425 => bank & 0x1F,
+                    _ => bank
+                }
+            }
+        };
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (bank % chr_1k_banks) * 0x0400
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        self.chr_offset(address) + (address as usize & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper206 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.bank_select = 0;
+        self.registers = [0, 2, 4, 5, 6, 7, 0, 1];
+        self.one_screen = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match (self.board, self.one_screen) {
+            (Board::Namco3453, 0) => Some(Mirroring::OneScreenLower),
+            (Board::Namco3453, _) => Some(Mirroring::OneScreenUpper),
+            _ => None
+        }
+    }
+    fn nametable(&mut self, address : u16) -> Option<Nametable> {
+        if self.board != Board::Namco3425 {
+            return None;
+        }
+        // $2000/$2400 follow R0 and $2800/$2C00 follow R1.
+        let register = ((address >> 11) & 0x01) as usize;
+        Some(Nametable::Ciram((self.registers[register] >> 5) & 0x01))
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper206 = Mapper206 {
+        board,
+        bank_select: 0,
+        registers: [0, 2, 4, 5, 6, 7, 0, 1],
+        one_screen: 0,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper206));
+}
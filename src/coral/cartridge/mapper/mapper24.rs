@@ -0,0 +1,172 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::vrcirq::{self, VrcIrq};
+use crate::coral::cartridge::mapper::audio::vrc6::{self, Vrc6Audio};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Konami VRC6. Mapper 24 is VRC6a, mapper 26 is VRC6b, which has the A0 and A1 lines swapped.
+
+#[derive(Clone, Debug)] 
+pub struct Mapper24 {
+    swap_lines : bool,
+    prg_16k_bank : usize,
+    prg_8k_bank : usize,
+    chr_registers : [usize; 8],
+    banking_control : u8,
+    irq : VrcIrq,
+    audio : Vrc6Audio,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper24 {
+    fn normalise(&self, address : u16) -> u16 {
+        if self.swap_lines {
+            let a0 = (address & 0x01) << 1;
+            let a1 = (address & 0x02) >> 1;
+            (address & 0xF000) | a0 | a1
+        } else {
+            address & 0xF003
+        }
+    }
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking_control & 0x80 > 0
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0xBFFF => (self.prg_16k_bank * 2 + ((uaddress >> 13) & 0x01)) % prg_8k_banks,
+            0xC000..=0xDFFF => self.prg_8k_bank % prg_8k_banks,
+            _ => prg_8k_banks - 1
+        };
+        bank * 0x2000 + (uaddress & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if address < 0x8000 {
+            return Some(address as usize & 0x1FFF);
+        }
+        let register = self.normalise(address);
+        match register {
+            0x8000..=0x8003 => { self.prg_16k_bank = (byte & 0x0F) as usize; }
+            0x9000..=0xB002 => { self.audio.write(register, byte); }
+            0xB003 => { self.banking_control = byte; }
+            0xC000..=0xC003 => { self.prg_8k_bank = (byte & 0x1F) as usize; }
+            0xD000..=0xD003 => { self.chr_registers[(register & 0x03) as usize] = byte as usize; }
+            0xE000..=0xE003 => { self.chr_registers[4 + (register & 0x03) as usize] = byte as usize; }
+            0xF000 => { self.irq.write_latch(byte); }
+            0xF001 => { self.irq.write_control(byte); }
+            0xF002 => { self.irq.acknowledge(); }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let slot = (uaddress >> 10) & 0x07;
+        let a10 = slot & 0x01;
+        let page = match (self.banking_control & 0x03, slot) {
+            (0, _) => self.chr_registers[slot],
+            (1, _) => (self.chr_registers[slot >> 1] & !0x01) | a10,
+            (_, 0..=3) => self.chr_registers[slot],
+            (_, _) => (self.chr_registers[(slot >> 1) + 2] & !0x01) | a10
+        };
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (page % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+
+impl MapperT for Mapper24 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        if address >= 0x8000 {
+            let mapped_address = self.cpu_r_map(address);
+            self.prg_data[mapped_address]
+        } else if address >= 0x6000 && self.prg_ram_enabled() {
+            self.prg_ram[address as usize & 0x1FFF]
+        } else {
+            0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if address >= 0x6000 && self.prg_ram_enabled() {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_16k_bank = 0;
+        self.prg_8k_bank = 0;
+        self.chr_registers = [0; 8];
+        self.banking_control = 0;
+        self.irq.reset();
+        self.audio.reset();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+        self.audio.tick();
+    }
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match (self.banking_control >> 2) & 0x03 {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let swap_lines = cartridge.header.h_mapper == 26;
+
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper24 = Mapper24 {
+        swap_lines,
+        prg_16k_bank: 0,
+        prg_8k_bank: 0,
+        chr_registers: [0; 8],
+        banking_control: 0,
+        irq: vrcirq::new(),
+        audio: vrc6::new(),
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper24));
+}
@@ -0,0 +1,146 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Action 53. $5000-$5FFF selects one of four registers, written through $8000-$FFFF. An outer
+// bank picks the game, and the mode register emulates the NROM, UNROM, BNROM, ANROM or CNROM
+// style banking of each game within the size given by the game size bits.
+
+#[derive(Clone, Debug)]
+pub struct Mapper28 {
+    select : u8,
+    chr_bank : usize,
+    inner_bank : usize,
+    mode : u8,
+    outer_bank : usize,
+    mirroring : u8,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper28 {
+    fn prg_bank(&self, address : u16) -> usize {
+        let cpu_a14 = ((address >> 14) & 0x01) as usize;
+        let bank_mode = ((self.mode >> 2) & 0x03) as usize;
+        let game_size = ((self.mode >> 4) & 0x03) as usize;
+        // UNROM-style modes fix the half of the game that A14 selects on the outer bank.
+        if (bank_mode ^ cpu_a14) & 0x03 == 0x02 {
+            return (self.outer_bank << 1) | cpu_a14;
+        }
+        let inner = if bank_mode & 0x02 == 0 { (self.inner_bank << 1) | cpu_a14 } else { self.inner_bank };
+        let mask = (2 << game_size) - 1;
+        ((self.outer_bank << 1) & !mask) | (inner & mask)
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        (self.prg_bank(address) % prg_16k_banks) * 0x4000 + (address as usize & 0x3FFF)
+    }
+    // Bit 4 of the CHR and inner bank registers drives the one-screen page in one-screen modes.
+    fn write_one_screen(&mut self, byte : u8) {
+        if self.mode & 0x02 == 0 {
+            self.mirroring = (self.mirroring & 0x02) | ((byte >> 4) & 0x01);
+        }
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x5000..=0x5FFF => { self.select = byte & 0x81; }
+            0x8000..=0xFFFF => {
+                match self.select {
+                    0x00 => {
+                        self.chr_bank = (byte & 0x03) as usize;
+                        self.write_one_screen(byte);
+                    }
+                    0x01 => {
+                        self.inner_bank = (byte & 0x0F) as usize;
+                        self.write_one_screen(byte);
+                    }
+                    0x80 => {
+                        self.mode = byte & 0x3F;
+                        self.mirroring = byte & 0x03;
+                    }
+                    _ => { self.outer_bank = byte as usize; }
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_8k_banks = self.chr_data.len() / 0x2000;
+        (self.chr_bank % chr_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+impl MapperT for Mapper28 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        // The menu lives in the last bank, so the outer bank powers up as $FF.
+        self.select = 0;
+        self.chr_bank = 0;
+        self.inner_bank = 0;
+        self.mode = 0;
+        self.outer_bank = 0xFF;
+        self.mirroring = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::OneScreenLower),
+            1 => Some(Mirroring::OneScreenUpper),
+            2 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = cartridge.header.chr_ram_size(0x8000);
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    let chr_len = cartridge.chr_data.len().min(chr_data_size);
+    chr_data[..chr_len].copy_from_slice(&cartridge.chr_data[..chr_len]);
+
+    let mapper28 = Mapper28 {
+        select: 0,
+        chr_bank: 0,
+        inner_bank: 0,
+        mode: 0,
+        outer_bank: 0xFF,
+        mirroring: 0,
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper28));
+}
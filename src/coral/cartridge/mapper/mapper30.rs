@@ -0,0 +1,155 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::flash::{self, Flash};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+
+// UNROM 512. UxROM-style PRG banking with 32 KB of banked CHR RAM. Header bits pick the
+// nametable layout, and boards with the battery bit set can rewrite their own flash.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Layout {
+    Header,
+    // One-screen, page selected by bit 7 of the bank register.
+    OneScreen,
+    // Four-screen, with the nametables in the last 8 KB of CHR RAM.
+    FourScreen
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapper30 {
+    prg_bank : usize,
+    chr_bank : usize,
+    one_screen : u8,
+    layout : Layout,
+    flash : Option<Flash>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper30 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        let bank = match address {
+            0x8000..=0xBFFF => self.prg_bank % prg_16k_banks,
+            _ => prg_16k_banks - 1
+        };
+        bank * 0x4000 + (address as usize & 0x3FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        // On self-flashable boards only $C000-$FFFF reaches the bank register.
+        if self.flash.is_some() && address < 0xC000 {
+            return Some(self.cpu_r_map(address));
+        }
+        self.prg_bank = (byte & 0x1F) as usize;
+        self.chr_bank = ((byte >> 5) & 0x03) as usize;
+        self.one_screen = byte >> 7;
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_8k_banks = self.chr_data.len() / 0x2000;
+        (self.chr_bank % chr_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+impl MapperT for Mapper30 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if address < 0x8000 {
+            return;
+        }
+        let optional_map = self.cpu_w_map(address, byte);
+        if let (Some(offset), Some(flash)) = (optional_map, self.flash.as_mut()) {
+            flash.write(&mut self.prg_data, offset, byte);
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_bank = 0;
+        self.chr_bank = 0;
+        self.one_screen = 0;
+        if let Some(flash) = self.flash.as_mut() {
+            flash.reset();
+        }
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match (self.layout, self.one_screen) {
+            (Layout::OneScreen, 0) => Some(Mirroring::OneScreenLower),
+            (Layout::OneScreen, _) => Some(Mirroring::OneScreenUpper),
+            _ => None
+        }
+    }
+    fn nametable(&mut self, _address : u16) -> Option<Nametable> {
+        if self.layout == Layout::FourScreen { Some(Nametable::Cartridge) } else { None }
+    }
+    fn nt_read(&mut self, address : u16) -> u8 {
+        self.chr_data[0x6000 + (address as usize & 0x0FFF)]
+    }
+    fn nt_write(&mut self, address : u16, byte : u8) {
+        self.chr_data[0x6000 + (address as usize & 0x0FFF)] = byte;
+    }
+    // The flash holds the whole program, so that is what gets saved.
+    fn save_data(&self) -> Option<Vec<u8>> {
+        self.flash.as_ref().map(|_| self.prg_data.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if self.flash.is_some() && data.len() == self.prg_data.len() {
+            self.prg_data.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = cartridge.header.chr_ram_size(0x8000);
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    let chr_len = cartridge.chr_data.len().min(chr_data_size);
+    chr_data[..chr_len].copy_from_slice(&cartridge.chr_data[..chr_len]);
+
+    // Flag 6 bits 3 and 0 select the layout. Bit 0 set is Mirroring::Horizontal here.
+    let layout = match (cartridge.header.h_alt_layout, cartridge.header.h_mirroring) {
+        (false, _) => Layout::Header,
+        (true, Mirroring::Horizontal) => Layout::FourScreen,
+        (true, _) => Layout::OneScreen
+    };
+    let flash = if cartridge.header.h_battery { Some(flash::new()) } else { None };
+
+    let mapper30 = Mapper30 {
+        prg_bank: 0,
+        chr_bank: 0,
+        one_screen: 0,
+        layout,
+        flash,
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper30));
+}
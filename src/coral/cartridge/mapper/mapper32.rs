@@ -0,0 +1,118 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Irem G-101. Two switchable 8 KB PRG banks, eight 1 KB CHR banks and mirroring control.
+// Submapper 1 (Major League) hardwires one-screen mirroring and the PRG layout.
+
+#[derive(Clone, Debug)]
+pub struct Mapper32 {
+    prg_banks : [usize; 2],
+    chr_banks : [usize; 8],
+    control : u8,
+    major_league : bool,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper32 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let swapped = self.control & 0x02 > 0 && !self.major_league;
+        let bank = match address {
+            0x8000..=0x9FFF if swapped => prg_8k_banks - 2,
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF if swapped => self.prg_banks[0],
+            0xC000..=0xDFFF => prg_8k_banks - 2,
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address & 0xF000 {
+            0x8000 => { self.prg_banks[0] = (byte & 0x1F) as usize; }
+            0x9000 => { self.control = byte; }
+            0xA000 => { self.prg_banks[1] = (byte & 0x1F) as usize; }
+            0xB000 => { self.chr_banks[(address & 0x07) as usize] = byte as usize; }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_banks[(uaddress >> 10) & 0x07] % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper32 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0, 1];
+        self.chr_banks = [0; 8];
+        self.control = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        if self.major_league {
+            return Some(Mirroring::OneScreenLower);
+        }
+        match self.control & 0x01 {
+            0 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper32 = Mapper32 {
+        prg_banks: [0, 1],
+        chr_banks: [0; 8],
+        control: 0,
+        major_league: cartridge.header.h_submapper == 1,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper32));
+}
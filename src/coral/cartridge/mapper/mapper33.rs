@@ -0,0 +1,168 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Taito TC0190 (mapper 33) and TC0690 (mapper 48). Two switchable 8 KB PRG banks, two 2 KB and
+// four 1 KB CHR banks. The TC0690 moves the mirroring control to $E000 and adds an MMC3-like
+// scanline IRQ.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    Tc0190,
+    Tc0690
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapper33 {
+    board : Board,
+    prg_banks : [usize; 2],
+    chr_banks : [usize; 6],
+    mirroring : u8,
+    irq_latch : u8,
+    irq_counter : u8,
+    irq_reload : bool,
+    irq_enabled : bool,
+    irq_pending : bool,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper33 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF => prg_8k_banks - 2,
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        let tc0690 = self.board == Board::Tc0690;
+        match address & 0xE003 {
+            0x8000 => {
+                self.prg_banks[0] = (byte & 0x3F) as usize;
+                if !tc0690 {
+                    self.mirroring = (byte >> 6) & 0x01;
+                }
+            }
+            0x8001 => { self.prg_banks[1] = (byte & 0x3F) as usize; }
+            0x8002 | 0x8003 => { self.chr_banks[(address & 0x01) as usize] = byte as usize; }
+            0xA000..=0xA003 => { self.chr_banks[2 + (address & 0x03) as usize] = byte as usize; }
+            // The reload value is written inverted.
+            0xC000 if tc0690 => { self.irq_latch = byte ^ 0xFF; }
+            0xC001 if tc0690 => { self.irq_counter = 0; self.irq_reload = true; }
+            0xC002 if tc0690 => { self.irq_enabled = true; }
+            0xC003 if tc0690 => { self.irq_enabled = false; self.irq_pending = false; }
+            0xE000 if tc0690 => { self.mirroring = (byte >> 6) & 0x01; }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let bank = match uaddress {
+            0x0000..=0x07FF => (self.chr_banks[0] << 1) | ((uaddress >> 10) & 0x01),
+            0x0800..=0x0FFF => (self.chr_banks[1] << 1) | ((uaddress >> 10) & 0x01),
+            _ => self.chr_banks[2 + ((uaddress >> 10) & 0x03)]
+        };
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (bank % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper33 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0, 1];
+        self.chr_banks = [0; 6];
+        self.mirroring = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn scanline(&mut self) {
+        if self.board != Board::Tc0690 {
+            return;
+        }
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper33 = Mapper33 {
+        board,
+        prg_banks: [0, 1],
+        chr_banks: [0; 6],
+        mirroring: 0,
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_reload: false,
+        irq_enabled: false,
+        irq_pending: false,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper33));
+}
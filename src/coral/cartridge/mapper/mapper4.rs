@@ -0,0 +1,285 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+
+// Nintendo MMC3 and the boards built around it. They share the banking and the scanline IRQ,
+// and differ in how PRG RAM, nametables and CHR RAM are wired.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    Mmc3,
+    // 1 KB of internal RAM at $7000-$7FFF, with separate protection for each 512 byte half.
+    Mmc6,
+    // TKSROM/TLSROM (mapper 118): CHR bank bit 7 drives CIRAM A10.
+    TxSrom,
+    // TQROM (mapper 119): CHR bank bit 6 selects 8 KB of CHR RAM instead of ROM.
+    TqRom
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapper4 {
+    board : Board,
+    bank_select : u8,
+    registers : [usize; 8],
+    mirroring : u8,
+    ram_protect : u8,
+    irq_latch : u8,
+    irq_counter : u8,
+    irq_reload : bool,
+    irq_enabled : bool,
+    irq_pending : bool,
+    four_screen : bool,
+    nt_ram : Vec<u8>,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+    // TQROM only. Other boards without CHR ROM keep their CHR RAM in chr_data.
+    chr_ram : Vec<u8>,
+}
+
+impl Mapper4 {
+    fn prg_bank(&self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let prg_mode = self.bank_select & 0x40 > 0;
+        let bank = match (address >> 13) & 0x03 {
+            0 if prg_mode => prg_8k_banks - 2,
+            0 => self.registers[6],
+            1 => self.registers[7],
+            2 if prg_mode => self.registers[6],
+            2 => prg_8k_banks - 2,
+            _ => prg_8k_banks - 1
+        };
+        bank % prg_8k_banks
+    }
+    // 1 KB CHR bank register value for the pattern table slot containing `address`.
+    fn chr_bank(&self, address : u16) -> usize {
+        let mut slot = ((address >> 10) & 0x07) as usize;
+        if self.bank_select & 0x80 > 0 {
+            slot ^= 0x04;
+        }
+        match slot {
+            0 | 1 => (self.registers[0] & 0xFE) | (slot & 0x01),
+            2 | 3 => (self.registers[1] & 0xFE) | (slot & 0x01),
+            _ => self.registers[slot - 2]
+        }
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        self.prg_bank(address) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x6000..=0x7FFF => { return Some(address as usize); }
+            0x8000..=0x9FFF if address & 0x01 == 0 => { self.bank_select = byte; }
+            0x8000..=0x9FFF => { self.registers[(self.bank_select & 0x07) as usize] = byte as usize; }
+            0xA000..=0xBFFF if address & 0x01 == 0 => { self.mirroring = byte & 0x01; }
+            // The MMC6 ignores its protection register while its RAM is disabled.
+            0xA000..=0xBFFF if self.board != Board::Mmc6 || self.bank_select & 0x20 > 0 => { self.ram_protect = byte; }
+            0xC000..=0xDFFF if address & 0x01 == 0 => { self.irq_latch = byte; }
+            0xC000..=0xDFFF => { self.irq_counter = 0; self.irq_reload = true; }
+            0xE000..=0xFFFF if address & 0x01 == 0 => { self.irq_enabled = false; self.irq_pending = false; }
+            0xE000..=0xFFFF => { self.irq_enabled = true; }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let bank = self.chr_bank(address);
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (bank % chr_1k_banks) * 0x0400 + (address as usize & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+    // TQROM only: offset into CHR RAM if the slot is currently mapped to it.
+    fn chr_ram_map(&self, address : u16) -> Option<usize> {
+        let bank = self.chr_bank(address);
+        if self.board == Board::TqRom && bank & 0x40 > 0 {
+            Some((bank & 0x07) * 0x0400 + (address as usize & 0x03FF))
+        } else {
+            None
+        }
+    }
+    // Offset into the MMC6 RAM, or None if the half holding `address` cannot be accessed.
+    fn mmc6_ram_map(&self, address : u16, write : bool) -> Option<usize> {
+        if self.bank_select & 0x20 == 0 {
+            return None;
+        }
+        let high_half = address & 0x0200 > 0;
+        let enable_bit = if high_half { 0x80 } else { 0x20 };
+        let write_bit = if high_half { 0x40 } else { 0x10 };
+        let allowed = self.ram_protect & enable_bit > 0 && (!write || self.ram_protect & write_bit > 0);
+        if allowed { Some(address as usize & 0x03FF) } else { None }
+    }
+    fn clock_irq_counter(&mut self) {
+        let previous = self.irq_counter;
+        let reload = self.irq_reload;
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+        // The MMC6 (like the early MMC3A) only fires when the counter reaches zero by decrementing
+        // or by an explicit reload, not every time it is refilled with a latch of zero.
+        let fire = match self.board {
+            Board::Mmc6 => self.irq_counter == 0 && (previous > 0 || reload),
+            _ => self.irq_counter == 0
+        };
+        if fire && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl MapperT for Mapper4 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x7000..=0x7FFF if self.board == Board::Mmc6 => {
+                match self.mmc6_ram_map(address, false) {
+                    Some(offset) => self.prg_ram[offset],
+                    None => 0
+                }
+            }
+            0x6000..=0x7FFF if self.board == Board::Mmc6 => 0,
+            0x6000..=0x7FFF if self.ram_protect & 0x80 > 0 => self.prg_ram[address as usize & 0x1FFF],
+            0x6000..=0x7FFF => 0,
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(address) = optional_map {
+            let address = address as u16;
+            match self.board {
+                Board::Mmc6 => {
+                    if address >= 0x7000 {
+                        if let Some(offset) = self.mmc6_ram_map(address, true) {
+                            self.prg_ram[offset] = byte;
+                        }
+                    }
+                }
+                _ => {
+                    if self.ram_protect & 0xC0 == 0x80 {
+                        self.prg_ram[address as usize & 0x1FFF] = byte;
+                    }
+                }
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        if let Some(offset) = self.chr_ram_map(address) {
+            return self.chr_ram[offset];
+        }
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(offset) = self.chr_ram_map(address) {
+            self.chr_ram[offset] = byte;
+        } else if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.bank_select = 0;
+        self.registers = [0, 2, 4, 5, 6, 7, 0, 1];
+        self.mirroring = 0;
+        self.ram_protect = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn scanline(&mut self) {
+        self.clock_irq_counter();
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        if self.four_screen {
+            return None;
+        }
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+    fn nametable(&mut self, address : u16) -> Option<Nametable> {
+        let quadrant = (address >> 10) & 0x03;
+        if self.four_screen {
+            return match quadrant {
+                0 | 1 => Some(Nametable::Ciram(quadrant as usize)),
+                _ => Some(Nametable::Cartridge)
+            };
+        }
+        if self.board == Board::TxSrom {
+            // CIRAM A10 follows CHR A17 of the pattern slot the nametable address would select.
+            let bank = self.chr_bank(address & 0x0FFF);
+            return Some(Nametable::Ciram((bank >> 7) & 0x01));
+        }
+        None
+    }
+    fn nt_read(&mut self, address : u16) -> u8 {
+        self.nt_ram[address as usize & 0x07FF]
+    }
+    fn nt_write(&mut self, address : u16, byte : u8) {
+        self.nt_ram[address as usize & 0x07FF] = byte;
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let prg_ram_size = if board == Board::Mmc6 {0x0400} else {cartridge.header.prg_ram_size(0x2000)};
+    let chr_ram_size = if board == Board::TqRom {0x2000} else {0};
+
+    let mapper4 = Mapper4 {
+        board,
+        bank_select: 0,
+        registers: [0, 2, 4, 5, 6, 7, 0, 1],
+        mirroring: 0,
+        ram_protect: 0,
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_reload: false,
+        irq_enabled: false,
+        irq_pending: false,
+        four_screen: cartridge.header.h_alt_layout,
+        nt_ram: vec![0; 0x0800],
+        prg_ram: vec![0; prg_ram_size],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0,
+        chr_ram: vec![0; chr_ram_size]
+    };
+    cartridge.mapper = Mapper(Box::new(mapper4));
+}
@@ -0,0 +1,204 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Tengen RAMBO-1. An MMC3 derivative with a 1 KB CHR mode, a third switchable PRG bank and an
+// IRQ counter that can be clocked either by scanlines or by CPU cycles divided by four. The IRQ
+// line goes low a few cycles after the counter reaches zero rather than immediately, so the CPU
+// acknowledges the IRQ that much later than on the MMC3. Writing $E000 clears the IRQ straight
+// away, and also cancels one that is still waiting out the delay.
+
+// CPU cycles between the counter reaching zero and the IRQ being asserted, per clock source.
+const SCANLINE_IRQ_DELAY : u8 = 2;
+const CYCLE_IRQ_DELAY : u8 = 1;
+
+#[derive(Clone, Debug)]
+pub struct Mapper64 {
+    bank_select : u8,
+    registers : [usize; 16],
+    mirroring : u8,
+    irq_latch : u8,
+    irq_counter : u8,
+    irq_reload : bool,
+    irq_enabled : bool,
+    irq_cycle_mode : bool,
+    irq_prescaler : u8,
+    irq_delay : u8,
+    irq_pending : bool,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper64 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let prg_mode = self.bank_select & 0x40 > 0;
+        let bank = match (address >> 13) & 0x03 {
+            0 if prg_mode => self.registers[15],
+            0 => self.registers[6],
+            1 if prg_mode => self.registers[6],
+            1 => self.registers[7],
+            2 if prg_mode => self.registers[7],
+            2 => self.registers[15],
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address & 0xE001 {
+            0x8000 => { self.bank_select = byte; }
+            0x8001 => { self.registers[(self.bank_select & 0x0F) as usize] = byte as usize; }
+            0xA000 => { self.mirroring = byte & 0x01; }
+            0xC000 => { self.irq_latch = byte; }
+            0xC001 => {
+                self.irq_cycle_mode = byte & 0x01 > 0;
+                self.irq_prescaler = 0;
+                self.irq_reload = true;
+            }
+            0xE000 => {
+                self.irq_enabled = false;
+                self.irq_delay = 0;
+                self.irq_pending = false;
+            }
+            0xE001 => { self.irq_enabled = true; }
+            _ => {}
+        }
+        None
+    }
+    fn chr_bank(&self, address : u16) -> usize {
+        let mut slot = ((address >> 10) & 0x07) as usize;
+        if self.bank_select & 0x80 > 0 {
+            slot ^= 0x04;
+        }
+        let one_k_mode = self.bank_select & 0x20 > 0;
+        match slot {
+            0 => if one_k_mode { self.registers[0] } else { self.registers[0] & 0xFE },
+            1 => if one_k_mode { self.registers[8] } else { self.registers[0] | 0x01 },
+            2 => if one_k_mode { self.registers[1] } else { self.registers[1] & 0xFE },
+            3 => if one_k_mode { self.registers[9] } else { self.registers[1] | 0x01 },
+            _ => self.registers[slot - 2]
+        }
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_bank(address) % chr_1k_banks) * 0x0400 + (address as usize & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+    fn clock_irq_counter(&mut self, delay : u8) {
+        if self.irq_reload {
+            // A reload lands one count later than on the MMC3 for latches above one.
+            self.irq_counter = if self.irq_latch <= 1 { self.irq_latch.wrapping_add(1) } else { self.irq_latch.wrapping_add(2) };
+            self.irq_reload = false;
+        } else if self.irq_counter == 0 {
+            self.irq_counter = self.irq_latch.wrapping_add(1);
+        }
+        self.irq_counter = self.irq_counter.wrapping_sub(1);
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_delay = delay;
+        }
+    }
+}
+
+impl MapperT for Mapper64 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.bank_select = 0;
+        self.registers = [0; 16];
+        self.mirroring = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.irq_cycle_mode = false;
+        self.irq_prescaler = 0;
+        self.irq_delay = 0;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_delay > 0 {
+            self.irq_delay -= 1;
+            if self.irq_delay == 0 {
+                self.irq_pending = true;
+            }
+        }
+        if self.irq_cycle_mode {
+            self.irq_prescaler = (self.irq_prescaler + 1) & 0x03;
+            if self.irq_prescaler == 0 {
+                self.clock_irq_counter(CYCLE_IRQ_DELAY);
+            }
+        }
+    }
+    fn scanline(&mut self) {
+        if !self.irq_cycle_mode {
+            self.clock_irq_counter(SCANLINE_IRQ_DELAY);
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper64 = Mapper64 {
+        bank_select: 0,
+        registers: [0; 16],
+        mirroring: 0,
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_reload: false,
+        irq_enabled: false,
+        irq_cycle_mode: false,
+        irq_prescaler: 0,
+        irq_delay: 0,
+        irq_pending: false,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper64));
+}
@@ -0,0 +1,139 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Irem H3001. Three switchable 8 KB PRG banks, eight 1 KB CHR banks and a 16-bit CPU cycle
+// counter that raises an IRQ when it reaches zero.
+
+#[derive(Clone, Debug)]
+pub struct Mapper65 {
+    prg_banks : [usize; 3],
+    chr_banks : [usize; 8],
+    mirroring : u8,
+    irq_enabled : bool,
+    irq_counter : u16,
+    irq_latch : u16,
+    irq_pending : bool,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper65 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF => self.prg_banks[2],
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x8000 => { self.prg_banks[0] = byte as usize; }
+            0x9001 => { self.mirroring = byte >> 7; }
+            0x9003 => { self.irq_enabled = byte & 0x80 > 0; self.irq_pending = false; }
+            0x9004 => { self.irq_counter = self.irq_latch; self.irq_pending = false; }
+            0x9005 => { self.irq_latch = (self.irq_latch & 0x00FF) | ((byte as u16) << 8); }
+            0x9006 => { self.irq_latch = (self.irq_latch & 0xFF00) | byte as u16; }
+            0xA000 => { self.prg_banks[1] = byte as usize; }
+            0xB000..=0xB007 => { self.chr_banks[(address & 0x07) as usize] = byte as usize; }
+            0xC000 => { self.prg_banks[2] = byte as usize; }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_banks[(uaddress >> 10) & 0x07] % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper65 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        self.prg_banks = [0, 1, prg_8k_banks - 2];
+        self.chr_banks = [0; 8];
+        self.mirroring = 0;
+        self.irq_enabled = false;
+        self.irq_counter = 0;
+        self.irq_latch = 0;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_enabled && self.irq_counter > 0 {
+            self.irq_counter -= 1;
+            if self.irq_counter == 0 {
+                self.irq_pending = true;
+            }
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            _ => Some(Mirroring::Vertical)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper65 = Mapper65 {
+        prg_banks: [0, 1, prg_banks * 2 - 2],
+        chr_banks: [0; 8],
+        mirroring: 0,
+        irq_enabled: false,
+        irq_counter: 0,
+        irq_latch: 0,
+        irq_pending: false,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper65));
+}
@@ -0,0 +1,152 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+
+// Sunsoft-4. Four 2 KB CHR banks, a 16 KB PRG bank and PRG RAM. The nametables can be taken
+// from 1 KB pages of the upper 128 KB of CHR ROM instead of console VRAM.
+
+#[derive(Clone, Debug)]
+pub struct Mapper68 {
+    chr_banks : [usize; 4],
+    nt_banks : [usize; 2],
+    control : u8,
+    prg_bank : usize,
+    ram_enabled : bool,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper68 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        let bank = match address {
+            0x8000..=0xBFFF => self.prg_bank % prg_16k_banks,
+            _ => prg_16k_banks - 1
+        };
+        bank * 0x4000 + (address as usize & 0x3FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address & 0xF000 {
+            0x6000 | 0x7000 => { return Some(address as usize & 0x1FFF); }
+            0x8000 | 0x9000 | 0xA000 | 0xB000 => { self.chr_banks[((address - 0x8000) >> 12) as usize] = byte as usize; }
+            0xC000 | 0xD000 => { self.nt_banks[((address - 0xC000) >> 12) as usize] = (byte | 0x80) as usize; }
+            0xE000 => { self.control = byte; }
+            0xF000 => {
+                self.prg_bank = (byte & 0x0F) as usize;
+                self.ram_enabled = byte & 0x10 > 0;
+            }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let chr_2k_banks = self.chr_data.len() / 0x0800;
+        (self.chr_banks[(uaddress >> 11) & 0x03] % chr_2k_banks) * 0x0800 + (uaddress & 0x07FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+    fn rom_nametables(&self) -> bool {
+        self.control & 0x10 > 0
+    }
+    fn board_mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::Horizontal,
+            1 => Mirroring::Vertical,
+            2 => Mirroring::OneScreenLower,
+            _ => Mirroring::OneScreenUpper
+        }
+    }
+}
+
+impl MapperT for Mapper68 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.ram_enabled => self.prg_ram[address as usize & 0x1FFF],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.ram_enabled {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.chr_banks = [0; 4];
+        self.nt_banks = [0x80; 2];
+        self.control = 0;
+        self.prg_bank = 0;
+        self.ram_enabled = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.board_mirroring())
+    }
+    fn nametable(&mut self, _address : u16) -> Option<Nametable> {
+        if self.rom_nametables() { Some(Nametable::Cartridge) } else { None }
+    }
+    // The mirroring picks which of the two nametable registers backs each nametable.
+    fn nt_read(&mut self, address : u16) -> u8 {
+        let register = self.board_mirroring().page(address);
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        let bank = self.nt_banks[register] % chr_1k_banks;
+        self.chr_data[bank * 0x0400 + (address as usize & 0x03FF)]
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper68 = Mapper68 {
+        chr_banks: [0; 4],
+        nt_banks: [0x80; 2],
+        control: 0,
+        prg_bank: 0,
+        ram_enabled: false,
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper68));
+}
@@ -0,0 +1,191 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::audio::sunsoft5b::{self, Sunsoft5bAudio};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Sunsoft FME-7 and 5B. All registers are reached through a command/parameter pair at
+// $8000/$A000; the 5B adds its sound chip at $C000/$E000.
+
+#[derive(Clone, Debug)] 
+pub struct Mapper69 {
+    command : u8,
+    chr_banks : [usize; 8],
+    prg_banks : [usize; 4],
+    ram_select : bool,
+    ram_enabled : bool,
+    mirroring : u8,
+    irq_enabled : bool,
+    irq_counter_enabled : bool,
+    irq_counter : u16,
+    irq_pending : bool,
+    audio : Sunsoft5bAudio,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper69 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x6000..=0x7FFF => self.prg_banks[0] % prg_8k_banks,
+            0x8000..=0x9FFF => self.prg_banks[1] % prg_8k_banks,
+            0xA000..=0xBFFF => self.prg_banks[2] % prg_8k_banks,
+            0xC000..=0xDFFF => self.prg_banks[3] % prg_8k_banks,
+            _ => prg_8k_banks - 1
+        };
+        bank * 0x2000 + (uaddress & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x6000..=0x7FFF => { return Some(address as usize & 0x1FFF); }
+            0x8000..=0x9FFF => { self.command = byte & 0x0F; }
+            0xA000..=0xBFFF => { self.write_parameter(byte); }
+            0xC000..=0xDFFF => { self.audio.write_address(byte); }
+            0xE000..=0xFFFF => { self.audio.write_data(byte); }
+            _ => {}
+        }
+        None
+    }
+    fn write_parameter(&mut self, byte : u8){
+        match self.command {
+            0x0..=0x7 => { self.chr_banks[self.command as usize] = byte as usize; }
+            0x8 => {
+                self.prg_banks[0] = (byte & 0x3F) as usize;
+                self.ram_select = byte & 0x40 > 0;
+                self.ram_enabled = byte & 0x80 > 0;
+            }
+            0x9..=0xB => { self.prg_banks[(self.command - 0x8) as usize] = (byte & 0x3F) as usize; }
+            0xC => { self.mirroring = byte & 0x03; }
+            0xD => {
+                self.irq_enabled = byte & 0x01 > 0;
+                self.irq_counter_enabled = byte & 0x80 > 0;
+                self.irq_pending = false;
+            }
+            0xE => { self.irq_counter = (self.irq_counter & 0xFF00) | byte as u16; }
+            _ => { self.irq_counter = (self.irq_counter & 0x00FF) | ((byte as u16) << 8); }
+        }
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let slot = (uaddress >> 10) & 0x07;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_banks[slot] % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+
+impl MapperT for Mapper69 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.ram_select && self.ram_enabled => self.prg_ram[address as usize & 0x1FFF],
+            0x6000..=0x7FFF if self.ram_select => 0,
+            0x6000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.ram_select && self.ram_enabled {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.command = 0;
+        self.chr_banks = [0; 8];
+        self.prg_banks = [0; 4];
+        self.ram_select = false;
+        self.ram_enabled = false;
+        self.mirroring = 0;
+        self.irq_enabled = false;
+        self.irq_counter_enabled = false;
+        self.irq_counter = 0;
+        self.irq_pending = false;
+        self.audio.reset();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+            if self.irq_counter == 0xFFFF && self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+        self.audio.tick();
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper69 = Mapper69 {
+        command: 0,
+        chr_banks: [0; 8],
+        prg_banks: [0; 4],
+        ram_select: false,
+        ram_enabled: false,
+        mirroring: 0,
+        irq_enabled: false,
+        irq_counter_enabled: false,
+        irq_counter: 0,
+        irq_pending: false,
+        audio: sunsoft5b::new(),
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper69));
+}
@@ -0,0 +1,111 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Irem 74HC161/32 (Holy Diver) and Jaleco JF-16 (Uchuusen: Cosmo Carrier). One register selects
+// a 16 KB PRG bank, an 8 KB CHR bank and the mirroring, which the two boards wire differently.
+
+#[derive(Clone, Debug)]
+pub struct Mapper78 {
+    register : u8,
+    // Holy Diver switches between horizontal and vertical, Cosmo Carrier between one-screen pages.
+    holy_diver : bool,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper78 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_16k_banks = self.prg_data.len() / 0x4000;
+        let bank = match address {
+            0x8000..=0xBFFF => (self.register & 0x07) as usize,
+            _ => prg_16k_banks - 1
+        };
+        (bank % prg_16k_banks) * 0x4000 + (address as usize & 0x3FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if address >= 0x8000 {
+            self.register = byte;
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_8k_banks = self.chr_data.len() / 0x2000;
+        let bank = (self.register >> 4) as usize;
+        (bank % chr_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper78 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        self.cpu_w_map(address, byte);
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.register = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match (self.holy_diver, self.register & 0x08 > 0) {
+            (true, false) => Some(Mirroring::Vertical),
+            (true, true) => Some(Mirroring::Horizontal),
+            (false, false) => Some(Mirroring::OneScreenLower),
+            (false, true) => Some(Mirroring::OneScreenUpper)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    // Submapper 3 is Holy Diver and 1 is Cosmo Carrier. Older iNES dumps of Holy Diver set
+    // the four-screen bit to tell them apart.
+    let holy_diver = match cartridge.header.h_submapper {
+        3 => true,
+        1 => false,
+        _ => cartridge.header.h_alt_layout
+    };
+
+    let mapper78 = Mapper78 {
+        register: 0,
+        holy_diver,
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper78));
+}
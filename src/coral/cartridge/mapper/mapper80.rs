@@ -0,0 +1,138 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Taito X1-005. The registers sit at $7EF0-$7EFF, next to 128 bytes of internal battery-backed
+// RAM at $7F00-$7FFF that only responds while $A3 is latched in $7EF8/$7EF9.
+
+const RAM_UNLOCK : u8 = 0xA3;
+
+#[derive(Clone, Debug)]
+pub struct Mapper80 {
+    prg_banks : [usize; 3],
+    chr_banks : [usize; 6],
+    mirroring : u8,
+    ram_latch : u8,
+    internal_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper80 {
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF => self.prg_banks[2],
+            _ => prg_8k_banks - 1
+        };
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x7EF0..=0x7EF5 => { self.chr_banks[(address - 0x7EF0) as usize] = byte as usize; }
+            0x7EF6 | 0x7EF7 => { self.mirroring = byte & 0x01; }
+            0x7EF8 | 0x7EF9 => { self.ram_latch = byte; }
+            0x7EFA..=0x7EFF => { self.prg_banks[((address - 0x7EFA) >> 1) as usize] = byte as usize; }
+            0x7F00..=0x7FFF => { return Some(address as usize & 0x7F); }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        // The 2 KB registers are given in 1 KB units with the low bit ignored.
+        let bank = match uaddress {
+            0x0000..=0x07FF => (self.chr_banks[0] & 0xFE) | ((uaddress >> 10) & 0x01),
+            0x0800..=0x0FFF => (self.chr_banks[1] & 0xFE) | ((uaddress >> 10) & 0x01),
+            _ => self.chr_banks[2 + ((uaddress >> 10) & 0x03)]
+        };
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (bank % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Mapper80 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x7F00..=0x7FFF if self.ram_latch == RAM_UNLOCK => self.internal_ram[address as usize & 0x7F],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if self.ram_latch == RAM_UNLOCK {
+                self.internal_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0, 1, 2];
+        self.chr_banks = [0; 6];
+        self.mirroring = 0;
+        self.ram_latch = 0;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring {
+            0 => Some(Mirroring::Vertical),
+            _ => Some(Mirroring::Horizontal)
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.internal_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.internal_ram.len() {
+            self.internal_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper80 = Mapper80 {
+        prg_banks: [0, 1, 2],
+        chr_banks: [0; 6],
+        mirroring: 0,
+        ram_latch: 0,
+        internal_ram: vec![0; 0x80],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper80));
+}
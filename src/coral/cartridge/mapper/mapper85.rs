@@ -0,0 +1,164 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::vrcirq::{self, VrcIrq};
+use crate::coral::cartridge::mapper::audio::vrc7::{self, Vrc7Audio};
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Konami VRC7. VRC7a selects odd registers with A4 and VRC7b with A3; both are accepted.
+
+#[derive(Clone, Debug)] 
+pub struct Mapper85 {
+    prg_banks : [usize; 3],
+    chr_banks : [usize; 8],
+    control : u8,
+    irq : VrcIrq,
+    audio : Vrc7Audio,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+}
+
+impl Mapper85 {
+    fn normalise(&self, address : u16) -> u16 {
+        if address & 0x0018 > 0 { (address & 0xF000) | 0x0010 } else { address & 0xF000 }
+    }
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & 0x80 > 0
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0] % prg_8k_banks,
+            0xA000..=0xBFFF => self.prg_banks[1] % prg_8k_banks,
+            0xC000..=0xDFFF => self.prg_banks[2] % prg_8k_banks,
+            _ => prg_8k_banks - 1
+        };
+        bank * 0x2000 + (uaddress & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        if address < 0x8000 {
+            return Some(address as usize & 0x1FFF);
+        }
+        // The audio ports are decoded from A5 and A4 only.
+        match address & 0xF030 {
+            0x9010 => { self.audio.write_address(byte); return None; }
+            0x9030 => { self.audio.write_data(byte); return None; }
+            _ => {}
+        }
+        let register = self.normalise(address);
+        match register {
+            0x8000 => { self.prg_banks[0] = (byte & 0x3F) as usize; }
+            0x8010 => { self.prg_banks[1] = (byte & 0x3F) as usize; }
+            0x9000 => { self.prg_banks[2] = (byte & 0x3F) as usize; }
+            0xA000..=0xD010 => {
+                let index = (((register - 0xA000) >> 12) * 2 + ((register >> 4) & 0x01)) as usize;
+                self.chr_banks[index] = byte as usize;
+            }
+            0xE000 => {
+                self.control = byte;
+                self.audio.silence(byte & 0x40 > 0);
+            }
+            0xE010 => { self.irq.write_latch(byte); }
+            0xF000 => { self.irq.write_control(byte); }
+            0xF010 => { self.irq.acknowledge(); }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let uaddress = address as usize;
+        let slot = (uaddress >> 10) & 0x07;
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_banks[slot] % chr_1k_banks) * 0x0400 + (uaddress & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        Some(self.ppu_r_map(address))
+    }
+}
+
+
+impl MapperT for Mapper85 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        if address >= 0x8000 {
+            let mapped_address = self.cpu_r_map(address);
+            self.prg_data[mapped_address]
+        } else if address >= 0x6000 && self.prg_ram_enabled() {
+            self.prg_ram[address as usize & 0x1FFF]
+        } else {
+            0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        let optional_map = self.cpu_w_map(address, byte);
+        if let Some(mapped_address) = optional_map {
+            if address >= 0x6000 && self.prg_ram_enabled() {
+                self.prg_ram[mapped_address] = byte;
+            }
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_banks = [0; 3];
+        self.chr_banks = [0; 8];
+        self.control = 0;
+        self.irq.reset();
+        self.audio.reset();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+        self.audio.tick();
+    }
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.control & 0x03 {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper85 = Mapper85 {
+        prg_banks: [0; 3],
+        chr_banks: [0; 8],
+        control: 0,
+        irq: vrcirq::new(),
+        audio: vrc7::new(),
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data
+    };
+    cartridge.mapper = Mapper(Box::new(mapper85));
+}
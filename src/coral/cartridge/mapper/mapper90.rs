@@ -0,0 +1,384 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+
+// J.Y. Company ASIC. Four PRG banking modes with an optional bank at $6000, four CHR modes with
+// 16-bit bank registers, an 8x8 multiplier, and an IRQ counter with a prescaler that can be
+// clocked by CPU cycles, scanlines, PPU reads or CPU writes. Mapper 209 can take nametables from
+// CHR ROM when enabled in $D000, mapper 211 always does, and mapper 90 never does.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    M90,
+    M209,
+    M211
+}
+
+// IRQ clock sources, bits 0-1 of $C001.
+const IRQ_CPU_CYCLES : u8 = 0;
+const IRQ_SCANLINES : u8 = 1;
+const IRQ_PPU_READS : u8 = 2;
+const IRQ_CPU_WRITES : u8 = 3;
+
+#[derive(Clone, Debug)]
+pub struct Mapper90 {
+    board : Board,
+    prg_registers : [usize; 4],
+    chr_registers : [usize; 8],
+    nt_registers : [usize; 4],
+    // $D000-$D003.
+    mode : u8,
+    mirroring : u8,
+    nt_select : u8,
+    outer_bank : u8,
+    chr_latches : [usize; 2],
+    multiplicand : u8,
+    multiplier : u8,
+    scratch : u8,
+    irq_mode : u8,
+    irq_enabled : bool,
+    irq_prescaler : u8,
+    irq_counter : u8,
+    irq_xor : u8,
+    irq_pending : bool,
+    dip_switches : u8,
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+impl Mapper90 {
+    // 8 KB bank mapped at `address`, $6000-$FFFF.
+    fn prg_bank(&self, address : u16) -> usize {
+        let slot = ((address >> 13) & 0x03) as usize;
+        let outer = ((self.outer_bank >> 1) & 0x03) as usize;
+        let last_register = self.mode & 0x04 > 0;
+        let register = |index : usize| {
+            // Mode 3 reverses the 7 bits of each register.
+            if self.mode & 0x03 == 3 { ((self.prg_registers[index] as u8).reverse_bits() >> 1) as usize } else { self.prg_registers[index] }
+        };
+        if address < 0x8000 {
+            let bank = match self.mode & 0x03 {
+                0 => (register(3) << 2) + 3,
+                1 => (register(3) << 1) + 1,
+                _ => register(3)
+            };
+            return (bank & 0x3F) | (outer << 6);
+        }
+        match self.mode & 0x03 {
+            0 => {
+                let bank = if last_register { register(3) } else { 0x0F };
+                (((bank & 0x0F) | (outer << 4)) << 2) | slot
+            }
+            1 => {
+                let bank = match slot {
+                    0 | 1 => register(1),
+                    _ if last_register => register(3),
+                    _ => 0x1F
+                };
+                (((bank & 0x1F) | (outer << 5)) << 1) | (slot & 0x01)
+            }
+            _ => {
+                let bank = match slot {
+                    3 if !last_register => 0x3F,
+                    _ => register(slot)
+                };
+                (bank & 0x3F) | (outer << 6)
+            }
+        }
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        (self.prg_bank(address) % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        let value = byte as usize;
+        match address {
+            0x5800 => { self.multiplicand = byte; }
+            0x5801 => { self.multiplier = byte; }
+            0x5803 => { self.scratch = byte; }
+            0x6000..=0x7FFF if !self.prg_rom_at_6000() => { return Some(address as usize & 0x1FFF); }
+            0x8000..=0x8FFF => { self.prg_registers[(address & 0x03) as usize] = value & 0x7F; }
+            0x9000..=0x9FFF => {
+                let register = (address & 0x07) as usize;
+                self.chr_registers[register] = (self.chr_registers[register] & 0xFF00) | value;
+            }
+            0xA000..=0xAFFF => {
+                let register = (address & 0x07) as usize;
+                self.chr_registers[register] = (self.chr_registers[register] & 0x00FF) | (value << 8);
+            }
+            0xB000..=0xBFFF => {
+                let register = (address & 0x03) as usize;
+                self.nt_registers[register] = if address & 0x04 == 0 {
+                    (self.nt_registers[register] & 0xFF00) | value
+                } else {
+                    (self.nt_registers[register] & 0x00FF) | (value << 8)
+                };
+            }
+            0xC000..=0xCFFF => { self.write_irq(address, byte); }
+            0xD000..=0xDFFF => {
+                match address & 0x03 {
+                    0 => { self.mode = byte; }
+                    1 => { self.mirroring = byte; }
+                    2 => { self.nt_select = byte; }
+                    _ => { self.outer_bank = byte; }
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+    fn write_irq(&mut self, address : u16, byte : u8) {
+        match address & 0x07 {
+            0 if byte & 0x01 > 0 => { self.irq_enabled = true; }
+            0 | 2 => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            1 => { self.irq_mode = byte; }
+            3 => { self.irq_enabled = true; }
+            4 => { self.irq_prescaler = byte ^ self.irq_xor; }
+            5 => { self.irq_counter = byte ^ self.irq_xor; }
+            6 => { self.irq_xor = byte; }
+            _ => {}
+        }
+    }
+    fn chr_bank(&self, address : u16) -> usize {
+        let slot = ((address >> 10) & 0x07) as usize;
+        let mode = ((self.mode >> 3) & 0x03) as usize;
+        let register = match mode {
+            0 => 0,
+            1 if self.outer_bank & 0x80 > 0 => self.chr_latches[slot >> 2],
+            1 => slot & 0x04,
+            2 => slot & 0x06,
+            _ => slot
+        };
+        let mut bank = self.chr_registers[register];
+        if self.outer_bank & 0x20 == 0 {
+            // The outer bank replaces the upper register bits, in 256 KB blocks.
+            let outer = ((self.outer_bank & 0x01) | ((self.outer_bank & 0x18) >> 2)) as usize;
+            bank = (bank & (0xFF >> (3 - mode))) | (outer << (5 + mode));
+        }
+        // Registers count in units of the mode's bank size.
+        let units = 3 - mode;
+        (bank << units) | (slot & ((1 << units) - 1))
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        (self.chr_bank(address) % chr_1k_banks) * 0x0400 + (address as usize & 0x03FF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable { Some(self.ppu_r_map(address)) } else { None }
+    }
+    fn prg_rom_at_6000(&self) -> bool {
+        self.mode & 0x80 > 0
+    }
+    fn rom_nametables(&self) -> bool {
+        match self.board {
+            Board::M90 => false,
+            Board::M209 => self.mode & 0x20 > 0,
+            Board::M211 => true
+        }
+    }
+    // MMC4-style latches, used in 4 KB CHR mode when bit 7 of $D003 is set.
+    fn update_chr_latches(&mut self, address : u16) {
+        match address {
+            0x0FD8 => { self.chr_latches[0] = 0; }
+            0x0FE8 => { self.chr_latches[0] = 2; }
+            0x1FD8..=0x1FDF => { self.chr_latches[1] = 4; }
+            0x1FE8..=0x1FEF => { self.chr_latches[1] = 6; }
+            _ => {}
+        }
+    }
+    fn clock_irq(&mut self) {
+        let increment = match self.irq_mode >> 6 {
+            1 => true,
+            2 => false,
+            _ => return
+        };
+        // The prescaler is either 8 or 3 bits wide. The counter is clocked when it wraps.
+        let mask = if self.irq_mode & 0x04 > 0 { 0x07 } else { 0xFF };
+        let prescaler = if increment { self.irq_prescaler.wrapping_add(1) } else { self.irq_prescaler.wrapping_sub(1) };
+        self.irq_prescaler = (self.irq_prescaler & !mask) | (prescaler & mask);
+        let wrapped = if increment { self.irq_prescaler & mask == 0 } else { self.irq_prescaler & mask == mask };
+        if !wrapped {
+            return;
+        }
+        self.irq_counter = if increment { self.irq_counter.wrapping_add(1) } else { self.irq_counter.wrapping_sub(1) };
+        let expired = if increment { self.irq_counter == 0x00 } else { self.irq_counter == 0xFF };
+        if expired && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+    fn irq_source(&self) -> u8 {
+        self.irq_mode & 0x03
+    }
+}
+
+impl MapperT for Mapper90 {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        let product = self.multiplicand as u16 * self.multiplier as u16;
+        match address {
+            0x5000 | 0x5400 | 0x5C00 => self.dip_switches << 6,
+            0x5800 => product as u8,
+            0x5801 => (product >> 8) as u8,
+            0x5803 => self.scratch,
+            0x6000..=0x7FFF if !self.prg_rom_at_6000() => self.prg_ram[address as usize & 0x1FFF],
+            0x6000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if self.irq_source() == IRQ_CPU_WRITES {
+            self.clock_irq();
+        }
+        if let Some(mapped_address) = self.cpu_w_map(address, byte) {
+            self.prg_ram[mapped_address] = byte;
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        if self.irq_source() == IRQ_PPU_READS {
+            self.clock_irq();
+        }
+        let mapped_address = self.ppu_r_map(address);
+        self.update_chr_latches(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, _kind : Reset) {
+        self.prg_registers = [0; 4];
+        self.chr_registers = [0; 8];
+        self.nt_registers = [0; 4];
+        self.mode = 0;
+        self.mirroring = 0;
+        self.nt_select = 0;
+        self.outer_bank = 0;
+        self.chr_latches = [0, 4];
+        self.multiplicand = 0;
+        self.multiplier = 0;
+        self.scratch = 0;
+        self.irq_mode = 0;
+        self.irq_enabled = false;
+        self.irq_prescaler = 0;
+        self.irq_counter = 0;
+        self.irq_xor = 0;
+        self.irq_pending = false;
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn cpu_tick(&mut self) {
+        if self.irq_source() == IRQ_CPU_CYCLES {
+            self.clock_irq();
+        }
+    }
+    fn scanline(&mut self) {
+        if self.irq_source() == IRQ_SCANLINES {
+            self.clock_irq();
+        }
+    }
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        match self.mirroring & 0x03 {
+            0 => Some(Mirroring::Horizontal),
+            1 => Some(Mirroring::Vertical),
+            2 => Some(Mirroring::OneScreenLower),
+            _ => Some(Mirroring::OneScreenUpper)
+        }
+    }
+    // Each nametable register picks either a CIRAM page or a 1 KB page of CHR ROM. Bit 6 of
+    // $D000 forces CHR ROM, otherwise it is used when bit 7 differs from bit 7 of $D002.
+    fn nametable(&mut self, address : u16) -> Option<Nametable> {
+        if !self.rom_nametables() {
+            return None;
+        }
+        let register = self.nt_registers[((address >> 10) & 0x03) as usize];
+        if self.mode & 0x40 > 0 || (register as u8 ^ self.nt_select) & 0x80 > 0 {
+            Some(Nametable::Cartridge)
+        } else {
+            Some(Nametable::Ciram(register & 0x01))
+        }
+    }
+    fn nt_read(&mut self, address : u16) -> u8 {
+        if self.irq_source() == IRQ_PPU_READS {
+            self.clock_irq();
+        }
+        let chr_1k_banks = self.chr_data.len() / 0x0400;
+        let bank = self.nt_registers[((address >> 10) & 0x03) as usize] % chr_1k_banks;
+        self.chr_data[bank * 0x0400 + (address as usize & 0x03FF)]
+    }
+    fn nt_write(&mut self, address : u16, byte : u8) {
+        if self.chr_writable {
+            let chr_1k_banks = self.chr_data.len() / 0x0400;
+            let bank = self.nt_registers[((address >> 10) & 0x03) as usize] % chr_1k_banks;
+            self.chr_data[bank * 0x0400 + (address as usize & 0x03FF)] = byte;
+        }
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        Some(self.prg_ram.clone())
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+    // Two solder pads read back in bits 6-7 of $5000. Multicarts use them to pick a menu.
+    fn dip_switches(&self) -> Option<u8> {
+        Some(self.dip_switches)
+    }
+    fn set_dip_switches(&mut self, value : u8) {
+        self.dip_switches = value & 0x03;
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let mapper90 = Mapper90 {
+        board,
+        prg_registers: [0; 4],
+        chr_registers: [0; 8],
+        nt_registers: [0; 4],
+        mode: 0,
+        mirroring: 0,
+        nt_select: 0,
+        outer_bank: 0,
+        chr_latches: [0, 4],
+        multiplicand: 0,
+        multiplier: 0,
+        scratch: 0,
+        irq_mode: 0,
+        irq_enabled: false,
+        irq_prescaler: 0,
+        irq_counter: 0,
+        irq_xor: 0,
+        irq_pending: false,
+        dip_switches: 0,
+        prg_ram: vec![0; cartridge.header.prg_ram_size(0x2000)],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    cartridge.mapper = Mapper(Box::new(mapper90));
+}
@@ -0,0 +1,89 @@
+use crate::coral::cartridge::types::Mirroring;
+
+// Serial register interface of the Nintendo MMC1, shared by the boards built on it.
+//
+// Writes to $8000-$FFFF shift bit 0 into a 5-bit register; the fifth write copies it into the
+// register selected by A13-A14. Writing a value with bit 7 set clears the shift register.
+
+#[derive(Clone, Debug)]
+pub struct Mmc1 {
+    shift : u8,
+    count : u8,
+    pub control : u8,
+    pub chr_0 : u8,
+    pub chr_1 : u8,
+    pub prg : u8
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Register {
+    Control,
+    Chr0,
+    Chr1,
+    Prg
+}
+
+impl Mmc1 {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    // Returns the register that was loaded, if this write completed one.
+    pub fn write(&mut self, address : u16, byte : u8) -> Option<Register> {
+        if byte & 0x80 > 0 {
+            self.shift = 0;
+            self.count = 0;
+            self.control |= 0x0C;
+            return None;
+        }
+        self.shift |= (byte & 0x01) << self.count;
+        self.count += 1;
+        if self.count < 5 {
+            return None;
+        }
+        let value = self.shift;
+        self.shift = 0;
+        self.count = 0;
+        let register = match (address >> 13) & 0x03 {
+            0 => { self.control = value; Register::Control }
+            1 => { self.chr_0 = value; Register::Chr0 }
+            2 => { self.chr_1 = value; Register::Chr1 }
+            _ => { self.prg = value; Register::Prg }
+        };
+        Some(register)
+    }
+    pub fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::OneScreenLower,
+            1 => Mirroring::OneScreenUpper,
+            2 => Mirroring::Horizontal,
+            _ => Mirroring::Vertical
+        }
+    }
+    // 16 KB PRG bank mapped at `address`, out of `prg_16k_banks`.
+    pub fn prg_bank(&self, address : u16, prg_16k_banks : usize) -> usize {
+        let bank = (self.prg & 0x0F) as usize;
+        let upper = address >= 0xC000;
+        let bank = match (self.control >> 2) & 0x03 {
+            0 | 1 => (bank & 0x0E) | upper as usize,
+            2 if upper => bank,
+            2 => 0,
+            _ if upper => prg_16k_banks - 1,
+            _ => bank
+        };
+        bank % prg_16k_banks
+    }
+    pub fn prg_ram_enabled(&self) -> bool {
+        self.prg & 0x10 == 0
+    }
+}
+
+pub fn new() -> Mmc1 {
+    Mmc1 {
+        shift: 0,
+        count: 0,
+        control: 0x0C,
+        chr_0: 0,
+        chr_1: 0,
+        prg: 0
+    }
+}
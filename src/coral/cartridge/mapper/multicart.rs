@@ -0,0 +1,268 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::Mirroring;
+
+// Discrete outer-bank multicarts. A write to $8000-$FFFF latches the address (and on some boards
+// the data), which selects the PRG banks, an 8 KB CHR bank and the mirroring. Console reset
+// clears the latch so the menu comes back; the reset-based boards count resets instead.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    // 100-in-1 Contra Function 16. Four PRG modes and 8 KB of PRG RAM.
+    M15,
+    // 68-in-1 and similar, 16 KB or 32 KB PRG.
+    M58,
+    // Reset-based NROM 4-in-1. Each reset starts the next game.
+    M60,
+    // 20-in-1, 16 KB or 32 KB PRG with a separate low bit.
+    M61,
+    // 1200-in-1 and similar, mirrored 16 KB PRG.
+    M200,
+    // 21-in-1, 32 KB PRG.
+    M201,
+    // 150-in-1, 16 KB or 32 KB PRG.
+    M202,
+    // 35-in-1, latches the data instead of the address.
+    M203,
+    // 64-in-1.
+    M204,
+    // 52 Games, 58-in-1 and 64-in-1. Four nibbles of RAM at $5800-$5FFF.
+    M225,
+    // 1200-in-1 and similar, with UNROM-like modes.
+    M227
+}
+
+#[derive(Clone, Debug)]
+pub struct Multicart {
+    board : Board,
+    address : u16,
+    data : u8,
+    resets : usize,
+    // 8 KB units.
+    prg_banks : [usize; 4],
+    chr_bank : usize,
+    mirroring : Option<Mirroring>,
+    chr_protected : bool,
+    registers : [u8; 4],
+    prg_ram : Vec<u8>,
+    prg_data : Vec<u8>,
+    chr_data : Vec<u8>,
+    chr_writable : bool,
+}
+
+// Mirroring bit where 0 places the nametables side by side.
+fn mirroring(bit : u16) -> Option<Mirroring> {
+    if bit == 0 { Some(Mirroring::Horizontal) } else { Some(Mirroring::Vertical) }
+}
+
+impl Multicart {
+    fn prg16(&mut self, first : usize, second : usize) {
+        self.prg_banks = [first * 2, first * 2 + 1, second * 2, second * 2 + 1];
+    }
+    fn prg32(&mut self, bank : usize) {
+        self.prg16(bank * 2, bank * 2 + 1);
+    }
+    fn update(&mut self) {
+        let a = self.address;
+        let address = a as usize;
+        let data = self.data as usize;
+        self.chr_protected = false;
+        match self.board {
+            Board::M15 => {
+                let bank = data & 0x3F;
+                match a & 0x03 {
+                    0 => self.prg16(bank & !0x01, bank | 0x01),
+                    1 => self.prg16(bank, bank | 0x07),
+                    2 => self.prg_banks = [bank * 2 + (data >> 7); 4],
+                    _ => self.prg16(bank, bank)
+                }
+                self.chr_bank = 0;
+                self.mirroring = mirroring(self.data as u16 & 0x40);
+                self.chr_protected = a & 0x03 == 0 || a & 0x03 == 3;
+            }
+            Board::M58 => {
+                if a & 0x40 > 0 { self.prg16(address & 0x07, address & 0x07); } else { self.prg32((address & 0x06) >> 1); }
+                self.chr_bank = (address >> 3) & 0x07;
+                self.mirroring = mirroring(a & 0x80);
+            }
+            Board::M60 => {
+                let game = self.resets & 0x03;
+                self.prg16(game, game);
+                self.chr_bank = game;
+            }
+            Board::M61 => {
+                if a & 0x10 > 0 {
+                    let bank = ((address & 0x0F) << 1) | ((address & 0x20) >> 5);
+                    self.prg16(bank, bank);
+                } else {
+                    self.prg32(address & 0x0F);
+                }
+                self.chr_bank = (address >> 8) & 0x0F;
+                self.mirroring = mirroring(a & 0x80);
+            }
+            Board::M200 => {
+                self.prg16(address & 0x07, address & 0x07);
+                self.chr_bank = address & 0x07;
+                self.mirroring = mirroring(a & 0x08);
+            }
+            Board::M201 => {
+                self.prg32(address & 0xFF);
+                self.chr_bank = address & 0xFF;
+            }
+            Board::M202 => {
+                let bank = (address >> 1) & 0x07;
+                let second = if a & 0x09 == 0x09 { bank + 1 } else { bank };
+                self.prg16(bank, second);
+                self.chr_bank = bank;
+                self.mirroring = mirroring(a & 0x01);
+            }
+            Board::M203 => {
+                self.prg16(data >> 2, data >> 2);
+                self.chr_bank = data & 0x03;
+            }
+            Board::M204 => {
+                let outer = address & 0x06;
+                let (first, second) = if outer == 0x06 { (outer, outer + 1) } else { (outer + (address & 0x01), outer + (address & 0x01)) };
+                self.prg16(first, second);
+                self.chr_bank = first;
+                self.mirroring = mirroring(a & 0x10);
+            }
+            Board::M225 => {
+                let high = (address >> 8) & 0x40;
+                let bank = ((address >> 6) & 0x3F) | high;
+                if a & 0x1000 > 0 { self.prg16(bank, bank); } else { self.prg32(bank >> 1); }
+                self.chr_bank = (address & 0x3F) | high;
+                self.mirroring = mirroring(a & 0x2000);
+            }
+            Board::M227 => {
+                let bank = ((address >> 2) & 0x1F) | ((address & 0x100) >> 3);
+                let size_32k = a & 0x01 > 0;
+                let last_bank = a & 0x200 > 0;
+                if a & 0x80 > 0 {
+                    if size_32k { self.prg32(bank >> 1); } else { self.prg16(bank, bank); }
+                    self.chr_protected = true;
+                } else {
+                    let first = if size_32k { bank & 0x3E } else { bank };
+                    let second = if last_bank { bank | 0x07 } else { bank & 0x38 };
+                    self.prg16(first, second);
+                }
+                self.chr_bank = 0;
+                self.mirroring = mirroring(a & 0x02);
+            }
+        }
+    }
+    fn cpu_r_map(&mut self, address : u16) -> usize {
+        let prg_8k_banks = self.prg_data.len() / 0x2000;
+        let bank = self.prg_banks[((address >> 13) & 0x03) as usize];
+        (bank % prg_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        match address {
+            0x5800..=0x5FFF if self.board == Board::M225 => { self.registers[(address & 0x03) as usize] = byte & 0x0F; }
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => { return Some(address as usize & 0x1FFF); }
+            0x8000..=0xFFFF if self.board != Board::M60 => {
+                self.address = address;
+                self.data = byte;
+                self.update();
+            }
+            _ => {}
+        }
+        None
+    }
+    fn ppu_r_map(&mut self, address : u16) -> usize {
+        let chr_8k_banks = self.chr_data.len() / 0x2000;
+        (self.chr_bank % chr_8k_banks) * 0x2000 + (address as usize & 0x1FFF)
+    }
+    fn ppu_w_map(&mut self, address : u16, _byte : u8) -> Option<usize> {
+        if self.chr_writable && !self.chr_protected { Some(self.ppu_r_map(address)) } else { None }
+    }
+}
+
+impl MapperT for Multicart {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x5800..=0x5FFF if self.board == Board::M225 => self.registers[(address & 0x03) as usize],
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => self.prg_ram[address as usize & 0x1FFF],
+            0x8000..=0xFFFF => {
+                let mapped_address = self.cpu_r_map(address);
+                self.prg_data[mapped_address]
+            }
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.cpu_w_map(address, byte) {
+            self.prg_ram[mapped_address] = byte;
+        }
+    }
+    fn ppu_read(&mut self, address : u16) -> u8 {
+        let mapped_address = self.ppu_r_map(address);
+        self.chr_data[mapped_address]
+    }
+    fn ppu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.ppu_w_map(address, byte) {
+            self.chr_data[mapped_address] = byte;
+        }
+    }
+    fn reset(&mut self, kind : Reset) {
+        match kind {
+            Reset::PowerOn => {
+                self.resets = 0;
+                self.registers = [0; 4];
+            }
+            Reset::Soft => { self.resets = self.resets.wrapping_add(1); }
+        }
+        self.address = 0;
+        self.data = 0;
+        self.update();
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn mirroring(&self) -> Option<Mirroring> {
+        self.mirroring
+    }
+    fn save_data(&self) -> Option<Vec<u8>> {
+        if self.prg_ram.is_empty() { None } else { Some(self.prg_ram.clone()) }
+    }
+    fn load_save_data(&mut self, data : &[u8]) {
+        if data.len() == self.prg_ram.len() {
+            self.prg_ram.copy_from_slice(data);
+        }
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, board : Board){
+    let prg_banks = cartridge.header.h_prg_size as usize;
+    let chr_banks = cartridge.header.h_chr_size as usize;
+
+    let prg_data_size = 0x4000 * prg_banks;
+    let chr_data_size = if chr_banks == 0 {cartridge.header.chr_ram_size(0x2000)} else {0x2000 * chr_banks};
+
+    let mut prg_data = vec![0; prg_data_size];
+    let mut chr_data = vec![0; chr_data_size];
+
+    prg_data[..cartridge.prg_data.len()].copy_from_slice(&cartridge.prg_data);
+    chr_data[..cartridge.chr_data.len()].copy_from_slice(&cartridge.chr_data);
+
+    let prg_ram_size = if board == Board::M15 {0x2000} else {0};
+
+    let mut multicart = Multicart {
+        board,
+        address: 0,
+        data: 0,
+        resets: 0,
+        prg_banks: [0, 1, 2, 3],
+        chr_bank: 0,
+        mirroring: None,
+        chr_protected: false,
+        registers: [0; 4],
+        prg_ram: vec![0; prg_ram_size],
+        prg_data,
+        chr_data,
+        chr_writable: chr_banks == 0
+    };
+    multicart.update();
+    cartridge.mapper = Mapper(Box::new(multicart));
+}
@@ -0,0 +1,279 @@
+use crate::coral::cartridge::mapper::types::*;
+use crate::coral::cartridge::mapper::audio::fds::{self, FdsAudio};
+use crate::coral::cartridge::mapper::audio::n163::{self, N163Audio};
+use crate::coral::cartridge::mapper::audio::sunsoft5b::{self, Sunsoft5bAudio};
+use crate::coral::cartridge::mapper::audio::vrc6::{self, Vrc6Audio};
+use crate::coral::cartridge::mapper::audio::vrc7::{self, Vrc7Audio};
+use crate::coral::cartridge::nsf;
+use crate::coral::cartridge::nsf::NsfFile;
+use crate::coral::cartridge::types;
+use crate::coral::cartridge::types::TVSystem;
+
+// Synthetic cartridge that plays an NSF. $6000-$FFFF is 40 KB of memory: $6000-$7FFF is RAM,
+// $8000-$FFFF holds the tune, 4 KB at a time when it bankswitches through $5FF8-$5FFF.
+// Switching a bank copies it in, so FDS tunes, which may write to $8000-$DFFF, work the same way.
+//
+// A small driver at $4100 takes over the vectors. On reset it clears RAM, silences the APU and
+// calls INIT with the track in A and the region in X, then calls PLAY whenever the cartridge
+// says a play period has passed. Selecting another track makes the driver start over.
+
+const DRIVER : u16 = 0x4100;
+const PLAY_DUE : u16 = 0x4180;
+const RESTART : u16 = 0x4181;
+const TRACK : u16 = 0x4182;
+const REGION : u16 = 0x4183;
+const MEMORY_START : usize = 0x6000;
+const BANK_SIZE : usize = 0x1000;
+
+#[derive(Clone, Debug)]
+pub struct NsfPlayer {
+    nsf : NsfFile,
+    driver : Vec<u8>,
+    // The tune padded so that its banks start on 4 KB boundaries.
+    rom : Vec<u8>,
+    memory : Vec<u8>,
+    track : usize,
+    restart : bool,
+    play_period : f64,
+    play_timer : f64,
+    play_due : bool,
+    exram : Vec<u8>,
+    multiplier : [u8; 2],
+    vrc6 : Vrc6Audio,
+    vrc7 : Vrc7Audio,
+    fds : FdsAudio,
+    n163 : N163Audio,
+    sunsoft5b : Sunsoft5bAudio,
+}
+
+fn driver(init : u16, play : u16) -> Vec<u8> {
+    let [init_low, init_high] = init.to_le_bytes();
+    let [play_low, play_high] = play.to_le_bytes();
+    vec![
+        0x78,                   // $4100  SEI
+        0xD8,                   // $4101  CLD
+        0xA2, 0xFF,             // $4102  LDX #$FF
+        0x9A,                   // $4104  TXS
+        0xA9, 0x00,             // $4105  LDA #$00
+        0xAA,                   // $4107  TAX
+        0x9D, 0x00, 0x00,       // $4108  STA $0000,X
+        0x9D, 0x00, 0x01,       // $410B  STA $0100,X
+        0x9D, 0x00, 0x02,       // $410E  STA $0200,X
+        0x9D, 0x00, 0x03,       // $4111  STA $0300,X
+        0x9D, 0x00, 0x04,       // $4114  STA $0400,X
+        0x9D, 0x00, 0x05,       // $4117  STA $0500,X
+        0x9D, 0x00, 0x06,       // $411A  STA $0600,X
+        0x9D, 0x00, 0x07,       // $411D  STA $0700,X
+        0xE8,                   // $4120  INX
+        0xD0, 0xE5,             // $4121  BNE $4108
+        0xA2, 0x13,             // $4123  LDX #$13
+        0x9D, 0x00, 0x40,       // $4125  STA $4000,X
+        0xCA,                   // $4128  DEX
+        0x10, 0xFA,             // $4129  BPL $4125
+        0xA9, 0x0F,             // $412B  LDA #$0F
+        0x8D, 0x15, 0x40,       // $412D  STA $4015
+        0xA9, 0x40,             // $4130  LDA #$40
+        0x8D, 0x17, 0x40,       // $4132  STA $4017
+        0xAD, 0x82, 0x41,       // $4135  LDA TRACK
+        0xAE, 0x83, 0x41,       // $4138  LDX REGION
+        0x20, init_low, init_high,  // $413B  JSR INIT
+        0xAD, 0x81, 0x41,       // $413E  LDA RESTART
+        0xF0, 0x03,             // $4141  BEQ $4146
+        0x4C, 0x00, 0x41,       // $4143  JMP $4100
+        0xAD, 0x80, 0x41,       // $4146  LDA PLAY_DUE
+        0xF0, 0xF3,             // $4149  BEQ $413E
+        0x20, play_low, play_high,  // $414B  JSR PLAY
+        0x4C, 0x3E, 0x41,       // $414E  JMP $413E
+        0x40,                   // $4151  RTI
+    ]
+}
+const DRIVER_RTI : u16 = 0x4151;
+
+impl NsfPlayer {
+    fn has(&self, chip : u8) -> bool {
+        self.nsf.info.expansion & chip > 0
+    }
+    fn switch_bank(&mut self, slot : usize, bank : u8){
+        let bank_count = self.rom.len() / BANK_SIZE;
+        let source = (bank as usize % bank_count) * BANK_SIZE;
+        let destination = slot * BANK_SIZE;
+        self.memory[destination..destination + BANK_SIZE].copy_from_slice(&self.rom[source..source + BANK_SIZE]);
+    }
+    // Puts the tune's memory back the way INIT expects to find it.
+    fn start_track(&mut self){
+        self.memory.fill(0);
+        match self.nsf.banks {
+            Some(banks) => {
+                for (index, bank) in banks.iter().enumerate() {
+                    self.switch_bank(index + 2, *bank);
+                }
+                if self.has(nsf::FDS) {
+                    self.switch_bank(0, banks[6]);
+                    self.switch_bank(1, banks[7]);
+                }
+            }
+            None => {
+                let start = self.nsf.load_address as usize - MEMORY_START;
+                let length = self.nsf.data.len().min(self.memory.len() - start);
+                self.memory[start..start + length].copy_from_slice(&self.nsf.data[..length]);
+            }
+        }
+        self.exram.fill(0);
+        self.play_timer = 0.0;
+        self.play_due = false;
+        self.vrc6.reset();
+        self.vrc7.reset();
+        self.fds.reset();
+        self.n163.reset();
+        self.sunsoft5b.reset();
+    }
+    fn write_expansion(&mut self, address : u16, byte : u8){
+        if self.has(nsf::VRC6) {
+            if let 0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002 = address {
+                self.vrc6.write(address, byte);
+            }
+        }
+        if self.has(nsf::VRC7) {
+            match address {
+                0x9010 => { self.vrc7.write_address(byte); }
+                0x9030 => { self.vrc7.write_data(byte); }
+                _ => {}
+            }
+        }
+        if self.has(nsf::FDS) && (0x4040..=0x408A).contains(&address) {
+            self.fds.write(address, byte);
+        }
+        if self.has(nsf::N163) {
+            match address {
+                0x4800..=0x4FFF => { self.n163.write_data(byte); }
+                0xF800..=0xFFFF => { self.n163.write_address(byte); }
+                _ => {}
+            }
+        }
+        if self.has(nsf::SUNSOFT_5B) {
+            match address {
+                0xC000..=0xDFFF => { self.sunsoft5b.write_address(byte); }
+                0xE000..=0xFFFF => { self.sunsoft5b.write_data(byte); }
+                _ => {}
+            }
+        }
+    }
+    fn cpu_w_map(&mut self, address : u16, byte : u8) -> Option<usize> {
+        self.write_expansion(address, byte);
+        match address {
+            0x5205..=0x5206 if self.has(nsf::MMC5) => { self.multiplier[(address - 0x5205) as usize] = byte; }
+            0x5C00..=0x5FF5 if self.has(nsf::MMC5) => { self.exram[(address - 0x5C00) as usize] = byte; }
+            0x5FF6..=0x5FF7 if self.has(nsf::FDS) && self.nsf.banks.is_some() => { self.switch_bank((address - 0x5FF6) as usize, byte); }
+            0x5FF8..=0x5FFF if self.nsf.banks.is_some() => { self.switch_bank((address - 0x5FF8) as usize + 2, byte); }
+            0x6000..=0x7FFF => { return Some(address as usize - MEMORY_START); }
+            0x8000..=0xDFFF if self.has(nsf::FDS) => { return Some(address as usize - MEMORY_START); }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl MapperT for NsfPlayer {
+    fn cpu_read(&mut self, address : u16) -> u8 {
+        match address {
+            0x4040..=0x407F | 0x4090 | 0x4092 if self.has(nsf::FDS) => self.fds.read(address),
+            0x4100..=0x417F => self.driver.get((address - DRIVER) as usize).copied().unwrap_or(0),
+            PLAY_DUE => std::mem::take(&mut self.play_due) as u8,
+            RESTART => {
+                let restart = std::mem::take(&mut self.restart);
+                if restart {
+                    self.start_track();
+                }
+                restart as u8
+            }
+            TRACK => self.track as u8,
+            REGION => (self.nsf.info.region == TVSystem::PAL) as u8,
+            0x4800..=0x4FFF if self.has(nsf::N163) => self.n163.read_data(),
+            0x5205 if self.has(nsf::MMC5) => (self.multiplier[0] as u16 * self.multiplier[1] as u16) as u8,
+            0x5206 if self.has(nsf::MMC5) => ((self.multiplier[0] as u16 * self.multiplier[1] as u16) >> 8) as u8,
+            0x5C00..=0x5FF5 if self.has(nsf::MMC5) => self.exram[(address - 0x5C00) as usize],
+            0xFFFA | 0xFFFE => DRIVER_RTI as u8,
+            0xFFFB | 0xFFFF => (DRIVER_RTI >> 8) as u8,
+            0xFFFC => DRIVER as u8,
+            0xFFFD => (DRIVER >> 8) as u8,
+            0x6000..=0xFFFF => self.memory[address as usize - MEMORY_START],
+            _ => 0
+        }
+    }
+    fn cpu_write(&mut self, address : u16, byte : u8) {
+        if let Some(mapped_address) = self.cpu_w_map(address, byte) {
+            self.memory[mapped_address] = byte;
+        }
+    }
+    fn ppu_read(&mut self, _address : u16) -> u8 {
+        0
+    }
+    fn ppu_write(&mut self, _address : u16, _byte : u8) {}
+    fn reset(&mut self, _kind : Reset) {
+        self.restart = false;
+        self.start_track();
+    }
+    fn cpu_tick(&mut self) {
+        self.play_timer += 1.0;
+        if self.play_timer >= self.play_period {
+            self.play_timer -= self.play_period;
+            self.play_due = true;
+        }
+        if self.has(nsf::VRC6) { self.vrc6.tick(); }
+        if self.has(nsf::VRC7) { self.vrc7.tick(); }
+        if self.has(nsf::FDS) { self.fds.tick(); }
+        if self.has(nsf::N163) { self.n163.tick(); }
+        if self.has(nsf::SUNSOFT_5B) { self.sunsoft5b.tick(); }
+    }
+    fn audio_output(&self) -> f32 {
+        let mut level = 0.0;
+        if self.has(nsf::VRC6) { level += self.vrc6.output(); }
+        if self.has(nsf::VRC7) { level += self.vrc7.output(); }
+        if self.has(nsf::FDS) { level += self.fds.output(); }
+        if self.has(nsf::N163) { level += self.n163.output(); }
+        if self.has(nsf::SUNSOFT_5B) { level += self.sunsoft5b.output(); }
+        level
+    }
+    fn clone_self(&self) -> Box<dyn MapperT> {
+        Box::new(self.clone())
+    }
+    fn track(&self) -> Option<usize> {
+        Some(self.track)
+    }
+    fn select_track(&mut self, track : usize) {
+        self.track = track;
+        self.restart = true;
+    }
+}
+
+
+pub fn choose(cartridge : &mut types::Cartridge, nsf : NsfFile){
+    let padding = match nsf.banks {
+        Some(_) => nsf.load_address as usize & (BANK_SIZE - 1),
+        None => 0
+    };
+    let mut rom = vec![0; padding];
+    rom.extend_from_slice(&nsf.data);
+    rom.resize(rom.len().div_ceil(BANK_SIZE).max(1) * BANK_SIZE, 0);
+
+    let mut player = NsfPlayer {
+        driver: driver(nsf.init_address, nsf.play_address),
+        rom,
+        memory: vec![0; 0x10000 - MEMORY_START],
+        track: nsf.info.first_track,
+        restart: false,
+        play_period: nsf.play_period(),
+        play_timer: 0.0,
+        play_due: false,
+        exram: vec![0; 0x3F6],
+        multiplier: [0; 2],
+        vrc6: vrc6::new(),
+        vrc7: vrc7::new(),
+        fds: fds::new(),
+        n163: n163::new(),
+        sunsoft5b: sunsoft5b::new(),
+        nsf,
+    };
+    player.start_track();
+    cartridge.mapper = Mapper(Box::new(player));
+}
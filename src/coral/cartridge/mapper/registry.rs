@@ -0,0 +1,52 @@
+use std::sync::{OnceLock, RwLock};
+
+use crate::coral::cartridge::types::Cartridge;
+use crate::coral::error::Result;
+
+// Builds the mapper of a cartridge whose header has already been parsed, storing it in
+// `cartridge.mapper`. PRG and CHR data are in `cartridge.prg_data` and `cartridge.chr_data`.
+pub type Factory = fn(&mut Cartridge) -> Result<()>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapperInfo {
+    pub mapper : u16,
+    // None matches any submapper without an entry of its own.
+    pub submapper : Option<u8>,
+    pub name : String,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub info : MapperInfo,
+    pub factory : Factory,
+}
+
+fn registry() -> &'static RwLock<Vec<Entry>> {
+    static REGISTRY : OnceLock<RwLock<Vec<Entry>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(super::builtin_mappers()))
+}
+
+// Registers a factory for a mapper number, and optionally a single submapper. An existing entry
+// for the same pair, built-in or not, is replaced. Cartridges loaded afterwards use it.
+pub fn register(mapper : u16, submapper : Option<u8>, name : &str, factory : Factory) {
+    let info = MapperInfo { mapper, submapper, name: name.to_string() };
+    let mut entries = registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    entries.retain(|entry| entry.info.mapper != mapper || entry.info.submapper != submapper);
+    entries.push(Entry { info, factory });
+}
+
+// Every registered mapper, ordered by mapper and submapper.
+pub fn supported_mappers() -> Vec<MapperInfo> {
+    let entries = registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut mappers : Vec<MapperInfo> = entries.iter().map(|entry| entry.info.clone()).collect();
+    mappers.sort_by_key(|info| (info.mapper, info.submapper));
+    mappers
+}
+
+// Factory for a mapper and submapper, preferring an entry for that exact submapper.
+pub fn find(mapper : u16, submapper : u8) -> Option<Factory> {
+    let entries = registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let exact = entries.iter().find(|entry| entry.info.mapper == mapper && entry.info.submapper == Some(submapper));
+    let any = entries.iter().find(|entry| entry.info.mapper == mapper && entry.info.submapper.is_none());
+    exact.or(any).map(|entry| entry.factory)
+}
@@ -1,10 +1,51 @@
+use crate::coral::cartridge::types::{Mirroring, Nametable};
+pub use crate::coral::cartridge::types::Reset;
+
 pub trait MapperT {
     fn cpu_read(&mut self, address : u16) -> u8;
     fn cpu_write(&mut self, address : u16, byte : u8);
     fn ppu_read(&mut self, address : u16) -> u8;
     fn ppu_write(&mut self, address : u16, byte : u8);
     fn clone_self(&self) -> Box<dyn MapperT>;
-    fn reset(&mut self);
+    fn reset(&mut self, kind : Reset);
+
+    // Called once per CPU cycle. Used by boards with cycle-based IRQ counters or audio.
+    fn cpu_tick(&mut self) {}
+    // Called once per rendered scanline, standing in for the PPU A12 rise that MMC3-style
+    // IRQ counters watch for.
+    fn scanline(&mut self) {}
+    // State of the cartridge IRQ line. The CPU is interrupted while this returns true.
+    fn irq(&self) -> bool { false }
+    // Expansion audio level, on the same linear scale as the 2A03 output.
+    fn audio_output(&self) -> f32 { 0.0 }
+    // Board LEDs, one bit per LED, set while lit.
+    fn leds(&self) -> u8 { 0 }
+    // Mirroring selected by the board. None falls back to the mirroring in the header.
+    fn mirroring(&self) -> Option<Mirroring> { None }
+    // Per-nametable mapping for boards that go beyond the fixed mirroring modes.
+    // None falls back to mirroring().
+    fn nametable(&mut self, _address : u16) -> Option<Nametable> { None }
+    fn nt_read(&mut self, _address : u16) -> u8 { 0 }
+    fn nt_write(&mut self, _address : u16, _byte : u8) {}
+    // CIRAM page mapped into the pattern tables at `address`, for boards that can use console
+    // VRAM as CHR. None leaves the access to ppu_read and ppu_write.
+    fn chr_ciram(&mut self, _address : u16) -> Option<usize> { None }
+    // Contents of the memory that survives power-off. Only used when the header has a battery.
+    fn save_data(&self) -> Option<Vec<u8>> { None }
+    fn load_save_data(&mut self, _data : &[u8]) {}
+    // Setting of the DIP switches on the board, or None if it has none.
+    fn dip_switches(&self) -> Option<u8> { None }
+    fn set_dip_switches(&mut self, _value : u8) {}
+    // Feeds a barcode to boards with a reader. Returns false if there is none or the code is invalid.
+    fn scan_barcode(&mut self, _code : &str) -> bool { false }
+    // Number of disk sides in a disk drive, 0 for boards without one.
+    fn disk_sides(&self) -> usize { 0 }
+    // Side in the drive, or None while the disk is ejected.
+    fn disk_side(&self) -> Option<usize> { None }
+    fn insert_disk(&mut self, _side : Option<usize>) {}
+    // Track being played by an NSF player, or None for anything else.
+    fn track(&self) -> Option<usize> { None }
+    fn select_track(&mut self, _track : usize) {}
 }
 
 
@@ -24,8 +65,68 @@ impl Mapper {
     pub fn ppu_write(&mut self, address : u16, byte : u8){
         self.0.ppu_write(address, byte)
     }
-    pub fn reset(&mut self){
-        self.0.reset();
+    pub fn reset(&mut self, kind : Reset){
+        self.0.reset(kind);
+    }
+    pub fn cpu_tick(&mut self){
+        self.0.cpu_tick();
+    }
+    pub fn scanline(&mut self){
+        self.0.scanline();
+    }
+    pub fn irq(&self) -> bool {
+        self.0.irq()
+    }
+    pub fn audio_output(&self) -> f32 {
+        self.0.audio_output()
+    }
+    pub fn leds(&self) -> u8 {
+        self.0.leds()
+    }
+    pub fn mirroring(&self) -> Option<Mirroring> {
+        self.0.mirroring()
+    }
+    pub fn nametable(&mut self, address : u16) -> Option<Nametable> {
+        self.0.nametable(address)
+    }
+    pub fn nt_read(&mut self, address : u16) -> u8 {
+        self.0.nt_read(address)
+    }
+    pub fn nt_write(&mut self, address : u16, byte : u8){
+        self.0.nt_write(address, byte)
+    }
+    pub fn chr_ciram(&mut self, address : u16) -> Option<usize> {
+        self.0.chr_ciram(address)
+    }
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        self.0.save_data()
+    }
+    pub fn load_save_data(&mut self, data : &[u8]){
+        self.0.load_save_data(data)
+    }
+    pub fn dip_switches(&self) -> Option<u8> {
+        self.0.dip_switches()
+    }
+    pub fn set_dip_switches(&mut self, value : u8){
+        self.0.set_dip_switches(value)
+    }
+    pub fn scan_barcode(&mut self, code : &str) -> bool {
+        self.0.scan_barcode(code)
+    }
+    pub fn disk_sides(&self) -> usize {
+        self.0.disk_sides()
+    }
+    pub fn disk_side(&self) -> Option<usize> {
+        self.0.disk_side()
+    }
+    pub fn insert_disk(&mut self, side : Option<usize>){
+        self.0.insert_disk(side)
+    }
+    pub fn track(&self) -> Option<usize> {
+        self.0.track()
+    }
+    pub fn select_track(&mut self, track : usize){
+        self.0.select_track(track)
     }
 }
 
@@ -0,0 +1,67 @@
+// IRQ counter shared by the Konami VRC6 and VRC7.
+//
+// In scanline mode a prescaler divides the CPU clock by 113.667 (341 / 3), approximating one
+// clock per scanline. In cycle mode the counter is clocked on every CPU cycle.
+
+#[derive(Clone, Debug)] 
+pub struct VrcIrq {
+    latch : u8,
+    counter : u8,
+    prescaler : i32,
+    enabled : bool,
+    enable_after_ack : bool,
+    cycle_mode : bool,
+    pending : bool
+}
+
+impl VrcIrq {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    pub fn write_latch(&mut self, byte : u8){
+        self.latch = byte;
+    }
+    pub fn write_control(&mut self, byte : u8){
+        self.enable_after_ack = byte & 0x01 > 0;
+        self.enabled = byte & 0x02 > 0;
+        self.cycle_mode = byte & 0x04 > 0;
+        self.pending = false;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = 341;
+        }
+    }
+    pub fn acknowledge(&mut self){
+        self.pending = false;
+        self.enabled = self.enable_after_ack;
+    }
+    pub fn tick(&mut self){
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock();
+        } else {
+            self.prescaler -= 3;
+            if self.prescaler <= 0 {
+                self.prescaler += 341;
+                self.clock();
+            }
+        }
+    }
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+    fn clock(&mut self){
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+pub fn new() -> VrcIrq {
+    VrcIrq { latch: 0, counter: 0, prescaler: 341, enabled: false, enable_after_ack: false, cycle_mode: false, pending: false }
+}
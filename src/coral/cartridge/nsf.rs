@@ -0,0 +1,195 @@
+use crate::coral::cartridge::types::TVSystem;
+use crate::coral::error::{CoralError, Result};
+
+// NSF and NSFe music files. An NSF is a 128-byte header followed by the program data. An NSFe
+// is "NSFE" followed by chunks, each a 32-bit length, a four-letter id and the data. Chunks whose
+// id starts with an uppercase letter must be understood; the others can be skipped.
+
+// Expansion audio flags.
+pub const VRC6 : u8 = 0x01;
+pub const VRC7 : u8 = 0x02;
+pub const FDS : u8 = 0x04;
+pub const MMC5 : u8 = 0x08;
+pub const N163 : u8 = 0x10;
+pub const SUNSOFT_5B : u8 = 0x20;
+
+const NSF_MAGIC : &[u8] = b"NESM\x1A";
+const NSFE_MAGIC : &[u8] = b"NSFE";
+const NSF_HEADER_SIZE : usize = 0x80;
+// Play periods in microseconds when the file does not give one.
+const DEFAULT_NTSC_RATE : u16 = 16639;
+const DEFAULT_PAL_RATE : u16 = 19997;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NsfInfo {
+    pub title : String,
+    pub artist : String,
+    pub copyright : String,
+    pub tracks : usize,
+    // 0-based.
+    pub first_track : usize,
+    // Names of the tracks, from NSFe files. Empty when the file has none.
+    pub track_labels : Vec<String>,
+    // NTSC, PAL or MultiRegion.
+    pub region : TVSystem,
+    pub expansion : u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NsfFile {
+    pub info : NsfInfo,
+    pub load_address : u16,
+    pub init_address : u16,
+    pub play_address : u16,
+    // Initial banks for $8000-$FFFF, or None if the tune does not bankswitch.
+    pub banks : Option<[u8; 8]>,
+    // Play periods in microseconds.
+    pub ntsc_rate : u16,
+    pub pal_rate : u16,
+    pub data : Vec<u8>,
+}
+
+fn invalid(message : String) -> CoralError {
+    CoralError::InvalidData(message)
+}
+
+fn word(data : &[u8], offset : usize) -> u16 {
+    data[offset] as u16 | ((data[offset + 1] as u16) << 8)
+}
+
+// Text up to the first null byte.
+fn text(data : &[u8]) -> String {
+    let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+fn region(flags : u8) -> TVSystem {
+    if flags & 0x02 > 0 { TVSystem::MultiRegion } else if flags & 0x01 > 0 { TVSystem::PAL } else { TVSystem::NTSC }
+}
+
+fn banks(data : &[u8]) -> Option<[u8; 8]> {
+    let mut banks = [0; 8];
+    banks[..data.len().min(8)].copy_from_slice(&data[..data.len().min(8)]);
+    if banks.iter().any(|bank| *bank != 0) { Some(banks) } else { None }
+}
+
+fn parse_nsf(data : &[u8]) -> Result<NsfFile> {
+    if data.len() < NSF_HEADER_SIZE {
+        return Err(invalid("Failed to parse: NSF header is truncated.".to_string()));
+    }
+    let header = &data[..NSF_HEADER_SIZE];
+    // NSF2 can give the length of the program data, with metadata following it.
+    let data_length = header[0x7D] as usize | ((header[0x7E] as usize) << 8) | ((header[0x7F] as usize) << 16);
+    let end = if header[0x05] >= 2 && data_length > 0 { (NSF_HEADER_SIZE + data_length).min(data.len()) } else { data.len() };
+    let info = NsfInfo {
+        title: text(&header[0x0E..0x2E]),
+        artist: text(&header[0x2E..0x4E]),
+        copyright: text(&header[0x4E..0x6E]),
+        tracks: header[0x06] as usize,
+        first_track: (header[0x07] as usize).saturating_sub(1),
+        track_labels: vec![],
+        region: region(header[0x7A]),
+        expansion: header[0x7B],
+    };
+    Ok(NsfFile {
+        info,
+        load_address: word(header, 0x08),
+        init_address: word(header, 0x0A),
+        play_address: word(header, 0x0C),
+        banks: banks(&header[0x70..0x78]),
+        ntsc_rate: word(header, 0x6E),
+        pal_rate: word(header, 0x78),
+        data: data[NSF_HEADER_SIZE..end].to_vec(),
+    })
+}
+
+fn parse_nsfe(data : &[u8]) -> Result<NsfFile> {
+    let mut nsf = NsfFile {
+        info: NsfInfo { title: String::new(), artist: String::new(), copyright: String::new(), tracks: 1, first_track: 0, track_labels: vec![], region: TVSystem::NTSC, expansion: 0 },
+        load_address: 0,
+        init_address: 0,
+        play_address: 0,
+        banks: None,
+        ntsc_rate: DEFAULT_NTSC_RATE,
+        pal_rate: DEFAULT_PAL_RATE,
+        data: vec![],
+    };
+    let mut has_info = false;
+    let mut position = NSFE_MAGIC.len();
+    while position + 8 <= data.len() {
+        let length = u32::from_le_bytes([data[position], data[position + 1], data[position + 2], data[position + 3]]) as usize;
+        let id = &data[position + 4..position + 8];
+        let chunk = data.get(position + 8..position + 8 + length).ok_or_else(|| invalid(format!("Failed to parse: NSFe chunk {} is truncated.", text(id))))?;
+        position += 8 + length;
+        match id {
+            b"INFO" => {
+                if chunk.len() < 8 {
+                    return Err(invalid("Failed to parse: NSFe INFO chunk is too short.".to_string()));
+                }
+                nsf.load_address = word(chunk, 0);
+                nsf.init_address = word(chunk, 2);
+                nsf.play_address = word(chunk, 4);
+                nsf.info.region = region(chunk[6]);
+                nsf.info.expansion = chunk[7];
+                nsf.info.tracks = chunk.get(8).copied().unwrap_or(1) as usize;
+                nsf.info.first_track = chunk.get(9).copied().unwrap_or(0) as usize;
+                has_info = true;
+            }
+            b"DATA" => { nsf.data = chunk.to_vec(); }
+            b"BANK" => { nsf.banks = banks(chunk); }
+            b"RATE" => {
+                if chunk.len() >= 2 { nsf.ntsc_rate = word(chunk, 0); }
+                if chunk.len() >= 4 { nsf.pal_rate = word(chunk, 2); }
+            }
+            b"NEND" => { break; }
+            b"auth" => {
+                let mut fields = chunk.split(|byte| *byte == 0).map(text);
+                nsf.info.title = fields.next().unwrap_or_default();
+                nsf.info.artist = fields.next().unwrap_or_default();
+                nsf.info.copyright = fields.next().unwrap_or_default();
+            }
+            b"tlbl" => {
+                nsf.info.track_labels = chunk.split(|byte| *byte == 0).map(text).collect();
+                nsf.info.track_labels.truncate(nsf.info.tracks);
+            }
+            id if id[0].is_ascii_uppercase() => {
+                return Err(invalid(format!("Failed to parse: Unsupported NSFe chunk {}.", text(id))));
+            }
+            _ => {}
+        }
+    }
+    if !has_info || nsf.data.is_empty() {
+        return Err(invalid("Failed to parse: NSFe file needs INFO and DATA chunks.".to_string()));
+    }
+    Ok(nsf)
+}
+
+pub fn is_nsf(data : &[u8]) -> bool {
+    data.starts_with(NSF_MAGIC) || data.starts_with(NSFE_MAGIC)
+}
+
+pub fn parse(data : &[u8]) -> Result<NsfFile> {
+    let nsf = if data.starts_with(NSF_MAGIC) {
+        parse_nsf(data)?
+    } else if data.starts_with(NSFE_MAGIC) {
+        parse_nsfe(data)?
+    } else {
+        return Err(CoralError::InvalidHeader("Failed to parse: Missing magic numbers. File is not a valid NSF or NSFe file.".to_string()));
+    };
+    if nsf.info.tracks == 0 || nsf.load_address < 0x6000 {
+        return Err(invalid(format!("Failed to parse: Invalid NSF with {} tracks loaded at ${:04X}.", nsf.info.tracks, nsf.load_address)));
+    }
+    Ok(nsf)
+}
+
+impl NsfFile {
+    // CPU cycles between calls to PLAY.
+    pub fn play_period(&self) -> f64 {
+        let rate = match self.info.region {
+            TVSystem::PAL => self.pal_rate,
+            _ => self.ntsc_rate
+        };
+        let rate = if rate == 0 { DEFAULT_NTSC_RATE } else { rate };
+        rate as f64 * 1.789773
+    }
+}
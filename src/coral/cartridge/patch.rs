@@ -0,0 +1,264 @@
+use crate::coral::cartridge::database::crc32;
+use crate::coral::error::{CoralError, Result};
+
+// Soft patches applied to ROMs when they are loaded. The original file is never written to.
+//
+// IPS records are a 24-bit big-endian offset and a 16-bit size followed by the data, or a zero
+// size, a 16-bit run length and a single byte to repeat. The patch starts with "PATCH" and ends
+// with "EOF".
+//
+// UPS and BPS patches start with "UPS1" or "BPS1" and the sizes of the source and target, and end
+// with the CRC32s of the source, the target and the patch itself. Numbers are variable-length,
+// seven bits per byte with the last byte flagged by bit 7. UPS hunks skip ahead and then XOR the
+// source up to a zero byte. BPS actions copy from the source, the patch or the target so far.
+
+const IPS_MAGIC : &[u8] = b"PATCH";
+const IPS_EOF : &[u8] = b"EOF";
+const IPS_EOF_OFFSET : usize = 0x454F46;
+const IPS_MAX_OFFSET : usize = 0xFFFFFF;
+const UPS_MAGIC : &[u8] = b"UPS1";
+const BPS_MAGIC : &[u8] = b"BPS1";
+const FOOTER_SIZE : usize = 12;
+// UPS and BPS give the size of the patched ROM up front. Anything above this is not a NES ROM, and
+// is refused before allocating it.
+const MAX_TARGET_SIZE : usize = 32 * 1024 * 1024;
+
+fn invalid_patch(kind : &str, message : &str) -> CoralError {
+    CoralError::InvalidData(format!("Invalid {} patch: {}", kind, message))
+}
+
+fn invalid(message : &str) -> CoralError {
+    invalid_patch("IPS", message)
+}
+
+fn take<'a>(patch : &'a [u8], position : &mut usize, length : usize) -> Result<&'a [u8]> {
+    let bytes = patch.get(*position..*position + length).ok_or_else(|| invalid("Unexpected end of patch"))?;
+    *position += length;
+    Ok(bytes)
+}
+
+pub fn apply_ips(data : &[u8], patch : &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(IPS_MAGIC) {
+        return Err(invalid("Missing PATCH header"));
+    }
+    let mut output = data.to_vec();
+    let mut position = IPS_MAGIC.len();
+    loop {
+        let offset = take(patch, &mut position, 3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = ((offset[0] as usize) << 16) | ((offset[1] as usize) << 8) | offset[2] as usize;
+        let size = take(patch, &mut position, 2)?;
+        let size = ((size[0] as usize) << 8) | size[1] as usize;
+        let record = if size == 0 {
+            let run = take(patch, &mut position, 3)?;
+            vec![run[2]; ((run[0] as usize) << 8) | run[1] as usize]
+        } else {
+            take(patch, &mut position, size)?.to_vec()
+        };
+        if output.len() < offset + record.len() {
+            output.resize(offset + record.len(), 0);
+        }
+        output[offset..offset + record.len()].copy_from_slice(&record);
+    }
+    // Some patches are followed by the size to truncate the output to.
+    if let Ok(size) = take(patch, &mut position, 3) {
+        output.truncate(((size[0] as usize) << 16) | ((size[1] as usize) << 8) | size[2] as usize);
+    }
+    Ok(output)
+}
+
+// Patch that turns `original` into `modified`. Both must be the same size and no larger than the
+// 16 MB IPS can address.
+pub fn create_ips(original : &[u8], modified : &[u8]) -> Result<Vec<u8>> {
+    if original.len() != modified.len() || original.len() > IPS_MAX_OFFSET {
+        return Err(CoralError::InvalidInput("IPS patches need equally sized data under 16 MB".to_string()));
+    }
+    let mut patch = IPS_MAGIC.to_vec();
+    let mut offset = 0;
+    while offset < modified.len() {
+        if original[offset] == modified[offset] {
+            offset += 1;
+            continue;
+        }
+        // An offset that reads as "EOF" would end the patch, so start one byte earlier.
+        let start = if offset == IPS_EOF_OFFSET { offset - 1 } else { offset };
+        let mut end = offset;
+        while end < modified.len() && end - start < 0xFFFF && original[end] != modified[end] {
+            end += 1;
+        }
+        patch.extend_from_slice(&[(start >> 16) as u8, (start >> 8) as u8, start as u8]);
+        patch.extend_from_slice(&[((end - start) >> 8) as u8, (end - start) as u8]);
+        patch.extend_from_slice(&modified[start..end]);
+        offset = end;
+    }
+    patch.extend_from_slice(IPS_EOF);
+    Ok(patch)
+}
+
+// Applies an IPS, UPS or BPS patch, told apart by its magic.
+pub fn apply(data : &[u8], patch : &[u8]) -> Result<Vec<u8>> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(data, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(data, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(data, patch)
+    } else {
+        Err(CoralError::InvalidData("Unknown patch format. Expected an IPS, UPS or BPS patch.".to_string()))
+    }
+}
+
+fn read_number(kind : &str, body : &[u8], position : &mut usize) -> Result<usize> {
+    let mut number = 0usize;
+    let mut shift = 1usize;
+    loop {
+        let byte = *body.get(*position).ok_or_else(|| invalid_patch(kind, "Unexpected end of patch"))?;
+        *position += 1;
+        number = shift.checked_mul((byte & 0x7F) as usize).and_then(|value| number.checked_add(value))
+            .ok_or_else(|| invalid_patch(kind, "Number is too large"))?;
+        if byte & 0x80 > 0 {
+            return Ok(number);
+        }
+        shift = shift.checked_shl(7).filter(|shift| *shift < 1 << 56).ok_or_else(|| invalid_patch(kind, "Number is too large"))?;
+        number += shift;
+    }
+}
+
+fn read_crc(footer : &[u8], index : usize) -> u32 {
+    u32::from_le_bytes([footer[index * 4], footer[index * 4 + 1], footer[index * 4 + 2], footer[index * 4 + 3]])
+}
+
+// Splits off the footer, after checking the patch's own checksum and that it applies to `data`.
+fn checked_body<'a>(kind : &str, data : &[u8], patch : &'a [u8]) -> Result<(&'a [u8], u32)> {
+    if patch.len() < 4 + FOOTER_SIZE {
+        return Err(invalid_patch(kind, "Patch is truncated"));
+    }
+    let (body, footer) = patch.split_at(patch.len() - FOOTER_SIZE);
+    let patch_crc = crc32(&patch[..patch.len() - 4]);
+    if patch_crc != read_crc(footer, 2) {
+        return Err(invalid_patch(kind, &format!("Patch checksum {:08X} does not match {:08X}", patch_crc, read_crc(footer, 2))));
+    }
+    let source_crc = crc32(data);
+    if source_crc != read_crc(footer, 0) {
+        return Err(invalid_patch(kind, &format!("ROM checksum {:08X} does not match the {:08X} the patch expects", source_crc, read_crc(footer, 0))));
+    }
+    Ok((body, read_crc(footer, 1)))
+}
+
+fn check_target_size(kind : &str, target_size : usize) -> Result<()> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(invalid_patch(kind, &format!("Patched ROM would be {} bytes, more than the {} allowed", target_size, MAX_TARGET_SIZE)));
+    }
+    Ok(())
+}
+
+fn check_target(kind : &str, output : &[u8], target_crc : u32) -> Result<()> {
+    let crc = crc32(output);
+    if crc != target_crc {
+        return Err(invalid_patch(kind, &format!("Patched ROM checksum {:08X} does not match {:08X}", crc, target_crc)));
+    }
+    Ok(())
+}
+
+pub fn apply_ups(data : &[u8], patch : &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(UPS_MAGIC) {
+        return Err(invalid_patch("UPS", "Missing UPS1 header"));
+    }
+    let (body, target_crc) = checked_body("UPS", data, patch)?;
+    let mut position = UPS_MAGIC.len();
+    let source_size = read_number("UPS", body, &mut position)?;
+    let target_size = read_number("UPS", body, &mut position)?;
+    if source_size != data.len() {
+        return Err(invalid_patch("UPS", &format!("Expected a ROM of {} bytes, found {}", source_size, data.len())));
+    }
+    check_target_size("UPS", target_size)?;
+    let mut output = data.to_vec();
+    output.resize(target_size, 0);
+    let mut offset = 0;
+    while position < body.len() {
+        offset += read_number("UPS", body, &mut position)?;
+        loop {
+            let byte = *body.get(position).ok_or_else(|| invalid_patch("UPS", "Unexpected end of patch"))?;
+            position += 1;
+            if byte == 0 {
+                break;
+            }
+            if let Some(target) = output.get_mut(offset) {
+                *target ^= byte;
+            }
+            offset += 1;
+        }
+        offset += 1;
+    }
+    check_target("UPS", &output, target_crc)?;
+    Ok(output)
+}
+
+// Moves a BPS copy offset by a signed amount, stored with the sign in bit 0.
+fn relative_offset(offset : usize, delta : usize) -> Result<usize> {
+    let moved = if delta & 0x01 > 0 { offset.checked_sub(delta >> 1) } else { offset.checked_add(delta >> 1) };
+    moved.ok_or_else(|| invalid_patch("BPS", "Copy offset is out of range"))
+}
+
+pub fn apply_bps(data : &[u8], patch : &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(BPS_MAGIC) {
+        return Err(invalid_patch("BPS", "Missing BPS1 header"));
+    }
+    let (body, target_crc) = checked_body("BPS", data, patch)?;
+    let mut position = BPS_MAGIC.len();
+    let source_size = read_number("BPS", body, &mut position)?;
+    let target_size = read_number("BPS", body, &mut position)?;
+    let metadata_size = read_number("BPS", body, &mut position)?;
+    if source_size != data.len() {
+        return Err(invalid_patch("BPS", &format!("Expected a ROM of {} bytes, found {}", source_size, data.len())));
+    }
+    check_target_size("BPS", target_size)?;
+    position = position.checked_add(metadata_size).filter(|position| *position <= body.len())
+        .ok_or_else(|| invalid_patch("BPS", "Unexpected end of patch"))?;
+
+    let out_of_range = || invalid_patch("BPS", "Action reaches past the end of its data");
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while position < body.len() {
+        let action = read_number("BPS", body, &mut position)?;
+        let length = (action >> 2) + 1;
+        if output.len() + length > target_size {
+            return Err(out_of_range());
+        }
+        match action & 0x03 {
+            // Source read, from the same offset in the source.
+            0 => {
+                let offset = output.len();
+                output.extend_from_slice(data.get(offset..offset + length).ok_or_else(out_of_range)?);
+            }
+            // Target read, from the patch.
+            1 => {
+                output.extend_from_slice(body.get(position..position + length).ok_or_else(out_of_range)?);
+                position += length;
+            }
+            // Source copy.
+            2 => {
+                source_offset = relative_offset(source_offset, read_number("BPS", body, &mut position)?)?;
+                output.extend_from_slice(data.get(source_offset..source_offset + length).ok_or_else(out_of_range)?);
+                source_offset += length;
+            }
+            // Target copy, byte by byte since the copy can overlap what it writes.
+            _ => {
+                target_offset = relative_offset(target_offset, read_number("BPS", body, &mut position)?)?;
+                for _ in 0..length {
+                    let byte = *output.get(target_offset).ok_or_else(out_of_range)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if output.len() != target_size {
+        return Err(invalid_patch("BPS", &format!("Patched ROM is {} bytes instead of {}", output.len(), target_size)));
+    }
+    check_target("BPS", &output, target_crc)?;
+    Ok(output)
+}
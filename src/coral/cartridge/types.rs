@@ -1,9 +1,47 @@
 use super::mapper;
+use super::database;
+use super::nsf;
+use super::patch;
+use crate::coral::error::{CoralError, Result};
 
+// Named after the nametable arrangement: Horizontal places $2000 and $2400 side by side
+// (what NESdev calls vertical mirroring), Vertical stacks $2000 on top of $2800.
 #[derive(Copy, Clone, Debug, PartialEq)] 
 pub enum Mirroring {
     Horizontal,
-    Vertical
+    Vertical,
+    OneScreenLower,
+    OneScreenUpper
+}
+
+impl Mirroring {
+    // CIRAM page (0 or 1) that backs the nametable containing `address`.
+    pub fn page(&self, address : u16) -> usize {
+        let nametable_choice = (address & 0x0FFF) >> 10;
+        match self {
+            Mirroring::Horizontal => (nametable_choice & 0x01) as usize,
+            Mirroring::Vertical => (nametable_choice >> 1) as usize,
+            Mirroring::OneScreenLower => 0,
+            Mirroring::OneScreenUpper => 1
+        }
+    }
+}
+
+// Memory behind one of the four nametables.
+#[derive(Copy, Clone, Debug, PartialEq)] 
+pub enum Nametable {
+    // One of the two 1 KB pages of console VRAM.
+    Ciram(usize),
+    // Memory supplied by the board itself, accessed through MapperT::nt_read and nt_write.
+    Cartridge
+}
+
+// Power-on clears everything. A soft reset (the console's reset button) leaves some boards,
+// typically multicarts, with state they use to pick the next game.
+#[derive(Copy, Clone, Debug, PartialEq)] 
+pub enum Reset {
+    PowerOn,
+    Soft
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)] 
@@ -15,43 +53,143 @@ pub enum ConsoleType {
     Undefined
 }
 
+// What a ROM file holds, going by its contents rather than its name.
+#[derive(Copy, Clone, Debug, PartialEq)] 
+pub enum Format {
+    // iNES, NES 2.0 or UNIF cartridge image.
+    Cartridge,
+    // Famicom Disk System image, which needs the BIOS.
+    Disk,
+    // NSF or NSFe tune.
+    Music
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)] 
 pub enum TVSystem {
     NTSC,
-    PAL
+    PAL,
+    // Runs on either, e.g. by checking the region at boot.
+    MultiRegion,
+    Dendy
+}
+
+// PPU fitted to a Vs. System cabinet. Most of them scramble the palette.
+#[derive(Copy, Clone, Debug, PartialEq)] 
+pub enum VsPpu {
+    Rp2c03,
+    Rp2c04_0001,
+    Rp2c04_0002,
+    Rp2c04_0003,
+    Rp2c04_0004,
+    Rc2c05_01,
+    Rc2c05_02,
+    Rc2c05_03,
+    Rc2c05_04,
+    Unknown(u8)
+}
+
+// Protection and wiring variant of a Vs. System game.
+#[derive(Copy, Clone, Debug, PartialEq)] 
+pub enum VsHardware {
+    Unisystem,
+    RbiBaseball,
+    TkoBoxing,
+    SuperXevious,
+    IceClimberJapan,
+    DualSystem,
+    RaidOnBungelingBay,
+    Unknown(u8)
 }
 
 #[derive(Copy, Clone, Debug)] 
 pub struct Header {
-    pub h_prg_size : u8,
-    pub h_chr_size : u8,
+    // ROM sizes in 16 KB (PRG) and 8 KB (CHR) units, rounded up. The exact sizes in bytes
+    // follow, which only differ for NES 2.0 images using the exponent-multiplier notation.
+    pub h_prg_size : u16,
+    pub h_chr_size : u16,
+    pub h_prg_rom_bytes : usize,
+    pub h_chr_rom_bytes : usize,
     pub h_chr_ram : bool,
     pub h_mirroring : Mirroring,
     pub h_battery : bool,
     pub h_trainer : bool,
     pub h_alt_layout : bool,
-    pub h_mapper : u8,
+    pub h_mapper : u16,
+    pub h_submapper : u8,
     pub h_console : ConsoleType,
     pub h_nes2 : bool,
-    pub h_prg_ram_size : u8,
-    pub h_tv_system : TVSystem
+    // RAM sizes in bytes. NVRAM is the battery-backed part. They are only known when they come
+    // from a NES 2.0 header or the game database, since iNES headers rarely fill them in.
+    pub h_ram_sizes_known : bool,
+    pub h_prg_ram_size : usize,
+    pub h_prg_nvram_size : usize,
+    pub h_chr_ram_size : usize,
+    pub h_chr_nvram_size : usize,
+    pub h_tv_system : TVSystem,
+    pub h_vs_ppu : VsPpu,
+    pub h_vs_hardware : VsHardware,
+    // Console type for ConsoleType::Extended, numbered as in the NES 2.0 specification.
+    pub h_extended_console : u8,
+    pub h_misc_roms : u8,
+    // Default expansion port device, numbered as in the NES 2.0 specification. 0 is unspecified.
+    pub h_expansion_device : u8
 }
 
+impl Header {
+    // PRG RAM the board should get. Only known sizes are used, and never below what the board
+    // needs to decode its RAM window.
+    pub fn prg_ram_size(&self, minimum : usize) -> usize {
+        let size = self.h_prg_ram_size + self.h_prg_nvram_size;
+        if self.h_ram_sizes_known { size.max(minimum) } else { minimum }
+    }
+    pub fn chr_ram_size(&self, minimum : usize) -> usize {
+        let size = self.h_chr_ram_size + self.h_chr_nvram_size;
+        if self.h_ram_sizes_known { size.max(minimum) } else { minimum }
+    }
+}
+
+// ROMs of PlayChoice-10 dumps that are read by the arcade's own hardware rather than the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayChoice {
+    // 8 KB of instructions shown on the top screen.
+    pub inst_rom : Vec<u8>,
+    // Decryption key PROM, followed by its CounterOut data. Many dumps leave these out.
+    pub prom_data : Option<[u8; 16]>,
+    pub prom_counter_out : Option<[u8; 16]>
+}
 
 #[derive(Clone)] 
 pub struct Cartridge {
     pub header : Header,
+    // Copiers put the trainer in their own RAM at $7000-$71FF, over whatever the board has there.
+    // The game sees `trainer_ram`, which is loaded from `trainer` at power-on.
     pub trainer : [u8; 512],
+    pub trainer_ram : [u8; 512],
     pub prg_data : Vec<u8>,
     pub chr_data : Vec<u8>,
+    // Miscellaneous ROMs of NES 2.0 images.
+    pub misc_data : Vec<u8>,
+    pub playchoice : Option<PlayChoice>,
+    // What the game database corrected in the header, if the game was found.
+    pub database : Option<database::Report>,
+    // Information about the tune when the cartridge is an NSF player.
+    pub nsf : Option<nsf::NsfInfo>,
+    // Board name of UNIF images, which have no mapper number.
+    pub board : Option<String>,
     pub mapper : mapper::types::Mapper
 }
 
 impl Cartridge {
     pub fn cpu_read(&mut self, address : u16) -> u8{
-        self.mapper.cpu_read(address)
+        match address {
+            0x7000..=0x71FF if self.header.h_trainer => self.trainer_ram[(address - 0x7000) as usize],
+            _ => self.mapper.cpu_read(address)
+        }
     }
     pub fn cpu_write(&mut self, address : u16, byte : u8){
+        if self.header.h_trainer && (0x7000..=0x71FF).contains(&address) {
+            self.trainer_ram[(address - 0x7000) as usize] = byte;
+        }
         self.mapper.cpu_write(address, byte)
     }
     pub fn ppu_read(&mut self, address : u16) -> u8{
@@ -60,8 +198,117 @@ impl Cartridge {
     pub fn ppu_write(&mut self, address : u16, byte : u8){
         self.mapper.ppu_write(address, byte)
     }
-    pub fn reset(&mut self){
-        self.mapper.reset()
+    pub fn reset(&mut self, kind : Reset){
+        if kind == Reset::PowerOn {
+            self.trainer_ram = self.trainer;
+        }
+        self.mapper.reset(kind)
+    }
+    pub fn cpu_tick(&mut self){
+        self.mapper.cpu_tick()
+    }
+    pub fn irq(&self) -> bool {
+        self.mapper.irq()
+    }
+    pub fn audio_output(&self) -> f32 {
+        self.mapper.audio_output()
+    }
+    pub fn leds(&self) -> u8 {
+        self.mapper.leds()
+    }
+    pub fn scanline(&mut self){
+        self.mapper.scanline()
+    }
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.mirroring().unwrap_or(self.header.h_mirroring)
+    }
+    pub fn nametable(&mut self, address : u16) -> Nametable {
+        match self.mapper.nametable(address) {
+            Some(nametable) => nametable,
+            None => Nametable::Ciram(self.mirroring().page(address))
+        }
+    }
+    pub fn nt_read(&mut self, address : u16) -> u8 {
+        self.mapper.nt_read(address)
+    }
+    pub fn nt_write(&mut self, address : u16, byte : u8){
+        self.mapper.nt_write(address, byte)
+    }
+    pub fn chr_ciram(&mut self, address : u16) -> Option<usize> {
+        self.mapper.chr_ciram(address)
+    }
+    // Battery-backed memory, if the cartridge has a battery.
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        if self.header.h_battery { self.mapper.save_data() } else { None }
+    }
+    // Save data must be the size of the memory the board saves, or for disk images an IPS patch.
+    pub fn load_save_data(&mut self, data : &[u8]) -> Result<()> {
+        if !self.header.h_battery {
+            return Ok(());
+        }
+        if self.mapper.disk_sides() > 0 {
+            patch::apply_ips(&[], data).map_err(|error| {
+                CoralError::InvalidSaveState(format!("Save data is not a valid disk patch. {}", error))
+            })?;
+        } else if let Some(current) = self.mapper.save_data() {
+            if current.len() != data.len() {
+                let error_message = format!("Save data is {} bytes, but the cartridge saves {}.", data.len(), current.len());
+                return Err(CoralError::InvalidSaveState(error_message));
+            }
+        }
+        self.mapper.load_save_data(data);
+        Ok(())
+    }
+    pub fn dip_switches(&self) -> Option<u8> {
+        self.mapper.dip_switches()
+    }
+    // Sets the DIP switches of the board. Meant to be called before the console is powered on.
+    pub fn set_dip_switches(&mut self, value : u8) -> Result<()> {
+        if self.mapper.dip_switches().is_none() {
+            let error_message = format!("Mapper {} has no DIP switches", self.header.h_mapper);
+            return Err(CoralError::InvalidInput(error_message));
+        }
+        self.mapper.set_dip_switches(value);
+        Ok(())
+    }
+    // Scans an EAN-13 or EAN-8 code with the barcode reader of the board, e.g. the Datach.
+    pub fn scan_barcode(&mut self, code : &str) -> Result<()> {
+        if self.mapper.scan_barcode(code) {
+            Ok(())
+        } else {
+            let error_message = format!("Could not scan barcode {}", code);
+            Err(CoralError::InvalidInput(error_message))
+        }
+    }
+    pub fn disk_sides(&self) -> usize {
+        self.mapper.disk_sides()
+    }
+    pub fn disk_side(&self) -> Option<usize> {
+        self.mapper.disk_side()
+    }
+    // Puts a side of the disk in the drive, or ejects the disk with None.
+    pub fn insert_disk(&mut self, side : Option<usize>) -> Result<()> {
+        if let Some(side) = side {
+            if side >= self.mapper.disk_sides() {
+                let error_message = format!("There is no disk side {}", side + 1);
+                return Err(CoralError::InvalidInput(error_message));
+            }
+        }
+        self.mapper.insert_disk(side);
+        Ok(())
+    }
+    pub fn track(&self) -> Option<usize> {
+        self.mapper.track()
+    }
+    // Starts playing another track of an NSF, counting from 0.
+    pub fn select_track(&mut self, track : usize) -> Result<()> {
+        let tracks = self.nsf.as_ref().map(|info| info.tracks).unwrap_or(0);
+        if track >= tracks {
+            let error_message = format!("There is no track {}", track + 1);
+            return Err(CoralError::InvalidInput(error_message));
+        }
+        self.mapper.select_track(track);
+        Ok(())
     }
 
 }
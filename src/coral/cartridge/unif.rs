@@ -0,0 +1,134 @@
+use crate::coral::cartridge::types::{Mirroring, TVSystem};
+use crate::coral::error::{CoralError, Result};
+
+// UNIF images. A 32-byte header, "UNIF" followed by the revision and padding, then chunks made
+// of a four-letter id, a 32-bit length and the data. The board is given by name in MAPR instead
+// of a mapper number, and the ROMs are split over PRG0-PRGF and CHR0-CHRF, concatenated in order.
+
+pub const MAGIC : &[u8] = b"UNIF";
+const HEADER_SIZE : usize = 32;
+
+// Board names without their NES-, HVC-, UNL-, BMC- or BTL- prefix, with the mapper and submapper
+// Coral implements them with.
+const BOARDS : &[(&str, u16, u8)] = &[
+    ("NROM", 0, 0), ("NROM-128", 0, 0), ("NROM-256", 0, 0), ("RROM", 0, 0), ("RROM-128", 0, 0),
+    ("UNROM", 2, 0), ("UOROM", 2, 0),
+    ("TBROM", 4, 0), ("TEROM", 4, 0), ("TFROM", 4, 0), ("TGROM", 4, 0), ("TKROM", 4, 0),
+    ("TLROM", 4, 0), ("TNROM", 4, 0), ("TR1ROM", 4, 0), ("TSROM", 4, 0), ("TVROM", 4, 0),
+    ("HKROM", 4, 1),
+    ("KONAMI-VRC-6A", 24, 0), ("KONAMI-VRC-6B", 26, 0),
+    ("UNROM-512-8", 30, 0), ("UNROM-512-16", 30, 0), ("UNROM-512-32", 30, 0),
+    ("IREM-G101", 32, 0),
+    ("TAITO-TC0190FMC", 33, 0), ("TAITO-TC0190FMC+PAL16R4", 48, 0),
+    ("IREM-H3001", 65, 0),
+    ("NTBROM", 68, 0), ("SUNSOFT-4", 68, 0),
+    ("BTR", 69, 0), ("JLROM", 69, 0), ("JSROM", 69, 0), ("SUNSOFT-FME-7", 69, 0), ("SUNSOFT-5B", 69, 0),
+    ("NAMCOT-3446", 76, 0),
+    ("TAITO-X1-005", 80, 0),
+    ("KONAMI-VRC-7", 85, 0),
+    ("NAMCOT-3433", 88, 0),
+    ("NAMCOT-3425", 95, 0),
+    ("EVENT", 105, 0),
+    ("TKSROM", 118, 0), ("TLSROM", 118, 0),
+    ("TQROM", 119, 0),
+    ("NAMCOT-3453", 154, 0),
+    ("DEROM", 206, 0), ("DE1ROM", 206, 0), ("DRROM", 206, 0), ("NAMCOT-3401", 206, 0), ("NAMCOT-3405", 206, 0),
+    ("NAMCOT-3406", 206, 0), ("NAMCOT-3407", 206, 0), ("NAMCOT-3413", 206, 0), ("NAMCOT-3414", 206, 0),
+    ("NAMCOT-3415", 206, 0), ("NAMCOT-3416", 206, 0), ("NAMCOT-3417", 206, 0),
+];
+
+const PREFIXES : &[&str] = &["NES-", "HVC-", "UNL-", "BMC-", "BTL-"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnifImage {
+    pub board : String,
+    pub prg : Vec<u8>,
+    pub chr : Vec<u8>,
+    // None when MIRR is missing or says the mapper controls the mirroring.
+    pub mirroring : Option<Mirroring>,
+    pub four_screen : bool,
+    pub battery : bool,
+    pub tv_system : TVSystem,
+}
+
+fn invalid(message : String) -> CoralError {
+    CoralError::InvalidData(message)
+}
+
+// Text up to the first null byte.
+fn text(data : &[u8]) -> String {
+    let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+// Index of a PRGn or CHRn chunk, n being a hex digit.
+fn rom_index(id : &[u8], kind : &[u8]) -> Option<usize> {
+    if !id.starts_with(kind) {
+        return None;
+    }
+    (id[3] as char).to_digit(16).map(|index| index as usize)
+}
+
+pub fn parse(data : &[u8]) -> Result<UnifImage> {
+    if !data.starts_with(MAGIC) {
+        return Err(CoralError::InvalidHeader("Failed to parse: Missing magic numbers. File is not a valid UNIF file.".to_string()));
+    }
+    let mut board = None;
+    let mut prg : [Option<&[u8]>; 16] = [None; 16];
+    let mut chr : [Option<&[u8]>; 16] = [None; 16];
+    let mut image = UnifImage { board: String::new(), prg: vec![], chr: vec![], mirroring: None, four_screen: false, battery: false, tv_system: TVSystem::NTSC };
+
+    let mut position = HEADER_SIZE;
+    while position + 8 <= data.len() {
+        let id = &data[position..position + 4];
+        let length = u32::from_le_bytes([data[position + 4], data[position + 5], data[position + 6], data[position + 7]]) as usize;
+        let chunk = data.get(position + 8..position + 8 + length).ok_or_else(|| invalid(format!("Failed to parse: UNIF chunk {} is truncated.", text(id))))?;
+        position += 8 + length;
+        if let Some(index) = rom_index(id, b"PRG") {
+            prg[index] = Some(chunk);
+            continue;
+        }
+        if let Some(index) = rom_index(id, b"CHR") {
+            chr[index] = Some(chunk);
+            continue;
+        }
+        match id {
+            b"MAPR" => { board = Some(text(chunk)); }
+            // 0 and 1 are the NESdev horizontal and vertical mirroring, which Coral names the
+            // other way round.
+            b"MIRR" => match chunk.first() {
+                Some(0) => { image.mirroring = Some(Mirroring::Vertical); }
+                Some(1) => { image.mirroring = Some(Mirroring::Horizontal); }
+                Some(2) => { image.mirroring = Some(Mirroring::OneScreenLower); }
+                Some(3) => { image.mirroring = Some(Mirroring::OneScreenUpper); }
+                Some(4) => { image.four_screen = true; }
+                _ => {}
+            },
+            b"BATR" => { image.battery = chunk.first().is_none_or(|battery| *battery > 0); }
+            b"TVCI" => {
+                image.tv_system = match chunk.first() {
+                    Some(1) => TVSystem::PAL,
+                    Some(2) => TVSystem::MultiRegion,
+                    _ => TVSystem::NTSC
+                };
+            }
+            // Names, dumper information, CRCs and the like.
+            _ => {}
+        }
+    }
+
+    image.board = board.ok_or_else(|| invalid("Failed to parse: UNIF file has no MAPR chunk.".to_string()))?;
+    image.prg = prg.iter().flatten().copied().collect::<Vec<&[u8]>>().concat();
+    image.chr = chr.iter().flatten().copied().collect::<Vec<&[u8]>>().concat();
+    if image.prg.is_empty() {
+        return Err(invalid("Failed to parse: UNIF file has no PRG chunks.".to_string()));
+    }
+    Ok(image)
+}
+
+// Mapper and submapper for a board name, with or without its prefix.
+pub fn board_mapper(board : &str) -> Option<(u16, u8)> {
+    let board = board.to_ascii_uppercase();
+    let name = PREFIXES.iter().find_map(|prefix| board.strip_prefix(prefix)).unwrap_or(&board);
+    BOARDS.iter().find(|(known, _, _)| *known == name).map(|(_, mapper, submapper)| (*mapper, *submapper))
+}
@@ -0,0 +1,83 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Errors of the cartridge loaders, the bus and the frontend. Messages of the variants that carry
+// one are complete sentences, e.g. "Failed to parse: NSFe chunk DATA is truncated."
+
+#[derive(Debug)]
+pub enum CoralError {
+    Io(io::Error),
+    // Missing magic numbers, or header fields that cannot describe a cartridge.
+    InvalidHeader(String),
+    UnsupportedMapper(u16),
+    // The mapper is implemented, but none of its boards match the submapper.
+    UnsupportedSubmapper(u16, u8),
+    // UNIF board name that no mapper implements.
+    UnsupportedBoard(String),
+    // Data that is shorter or longer than it should be, such as a truncated PRG ROM.
+    SizeMismatch { what : String, expected : usize, found : usize },
+    // Contents that do not parse: NSF files, disk images, archives, patches, board descriptions
+    // and the game database.
+    InvalidData(String),
+    // Battery save data that does not fit the cartridge.
+    InvalidSaveState(String),
+    // Requests the cartridge cannot carry out, e.g. inserting a disk side that does not exist.
+    InvalidInput(String),
+    // SDL and thread synchronisation errors, which only come with a message.
+    Frontend(String),
+}
+
+pub type Result<T> = std::result::Result<T, CoralError>;
+
+impl CoralError {
+    pub fn frontend<T : ToString>(error : T) -> CoralError {
+        CoralError::Frontend(error.to_string())
+    }
+}
+
+impl fmt::Display for CoralError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoralError::Io(error) => write!(f, "{}", error),
+            CoralError::UnsupportedMapper(mapper) => write!(f, "Mapper {} is not yet supported. My bad :(", mapper),
+            CoralError::UnsupportedSubmapper(mapper, submapper) => write!(f, "Submapper {} of mapper {} is not yet supported. My bad :(", submapper, mapper),
+            CoralError::UnsupportedBoard(board) => write!(f, "UNIF board {} is not yet supported. My bad :(", board),
+            CoralError::SizeMismatch { what, expected, found } => write!(f, "Failed to load {}: Expected {} bytes, found {}.", what, expected, found),
+            CoralError::InvalidHeader(message) | CoralError::InvalidData(message) | CoralError::InvalidSaveState(message)
+                | CoralError::InvalidInput(message) | CoralError::Frontend(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for CoralError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CoralError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for CoralError {
+    fn from(error : io::Error) -> CoralError {
+        CoralError::Io(error)
+    }
+}
+
+// For callers that still work with io::Error.
+impl From<CoralError> for io::Error {
+    fn from(error : CoralError) -> io::Error {
+        let kind = match &error {
+            CoralError::Io(_) => io::ErrorKind::Other,
+            CoralError::UnsupportedMapper(_) | CoralError::UnsupportedSubmapper(_, _) | CoralError::UnsupportedBoard(_) => io::ErrorKind::Unsupported,
+            CoralError::InvalidInput(_) => io::ErrorKind::InvalidInput,
+            CoralError::Frontend(_) => io::ErrorKind::Other,
+            _ => io::ErrorKind::InvalidData
+        };
+        match error {
+            CoralError::Io(error) => error,
+            error => io::Error::new(kind, error)
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+// Collects one audio level per CPU cycle and resamples it to SAMPLE_RATE by averaging.
+
+pub const SAMPLE_RATE : u32 = 44100;
+const CPU_RATE : f64 = 1789773.0;
+// One-pole high-pass coefficient (~37 Hz at 44.1 kHz) used to remove the DC offset.
+const HIGHPASS : f32 = 0.9948;
+// One second of audio. Beyond that the oldest samples are dropped, so a caller that never drains
+// the mixer does not grow it without bound.
+pub const MAX_SAMPLES : usize = SAMPLE_RATE as usize;
+
+#[derive(Clone, Debug)] 
+pub struct Mixer {
+    pub samples : VecDeque<f32>,
+    accumulator : f32,
+    count : u32,
+    phase : f64,
+    previous_input : f32,
+    previous_output : f32
+}
+
+impl Mixer {
+    pub fn reset(&mut self){
+        *self = new();
+    }
+    pub fn push(&mut self, level : f32){
+        self.accumulator += level;
+        self.count += 1;
+        self.phase += SAMPLE_RATE as f64 / CPU_RATE;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            let input = self.accumulator / self.count as f32;
+            let output = HIGHPASS * (self.previous_output + input - self.previous_input);
+            self.previous_input = input;
+            self.previous_output = output;
+            if self.samples.len() == MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(output);
+            self.accumulator = 0.0;
+            self.count = 0;
+        }
+    }
+    pub fn drain(&mut self, buffer : &mut Vec<f32>){
+        buffer.extend(self.samples.drain(..));
+    }
+}
+
+pub fn new() -> Mixer {
+    Mixer { samples: VecDeque::new(), accumulator: 0.0, count: 0, phase: 0.0, previous_input: 0.0, previous_output: 0.0 }
+}
@@ -4,3 +4,5 @@ pub mod utils;
 pub mod bus;
 pub mod ppu;
 pub mod controller;
+pub mod mixer;
+pub mod error;
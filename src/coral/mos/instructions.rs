@@ -143,16 +143,17 @@ pub fn irq<T : Bus>(bus : &mut T){
     let interupt_disabled = get_flag(bus, Flag::InterruptDisable);
     if !interupt_disabled {
         let (pc_msb, pc_lsb) = utils::split_bytes(get_pc(bus));
-        let ps = utils::p0(get_ps(bus), true);
+        let ps = utils::p4(utils::p5(get_ps(bus), true), false);
         write_to_stack(bus, pc_msb);
         write_to_stack(bus, pc_lsb);
         write_to_stack(bus, ps);
      
-        let irq_lsb = bus.read_byte(0xFFFA);
-        let irq_msb = bus.read_byte(0xFFFB);
+        let irq_lsb = bus.read_byte(0xFFFE);
+        let irq_msb = bus.read_byte(0xFFFF);
         let jump_address = join_bytes(irq_msb, irq_lsb);
         set_pc(bus, jump_address);
         set_flag(bus, Flag::InterruptDisable, true);
+        update_cycles(bus, 7);
     }
 }
 
@@ -366,6 +366,16 @@ fn handle_end_of_frame<T : Bus>(bus : &mut T){
     }
 }
 
+// The PPU fetches sprite patterns from cycle 257 onwards. With the usual setup (background at
+// $0000, sprites at $1000) that is where A12 rises, so the cartridge is notified at cycle 260.
+fn handle_scanline_counter<T : Bus>(bus : &mut T){
+    let cycle = get_cycle(bus);
+    let rendering = get_mask_flag(bus, MaskFlag::RenderBackground) || get_mask_flag(bus, MaskFlag::RenderSprites);
+    if cycle == 260 && rendering {
+        bus.notify_scanline();
+    }
+}
+
 pub fn tick<T : Bus>(bus : &mut T){
     let scanline = get_scanline(bus);
     let cycle = get_cycle(bus);
@@ -382,6 +392,9 @@ pub fn tick<T : Bus>(bus : &mut T){
     if scanline >= 241 && scanline < 260 {
         handle_end_of_frame(bus);
     }
+    if scanline < 240 {
+        handle_scanline_counter(bus);
+    }
 
     increase_cycle(bus);
 
@@ -3,6 +3,7 @@ pub trait Bus {
     fn write_byte(&mut self, address : u16, byte : u8);
     fn set_pixel(&mut self, position : (usize, usize), color : u8);
     fn trigger_nmi(&mut self);
+    fn notify_scanline(&mut self);
     fn fetch_ppu(&mut self) -> &mut PPU;
 }
 
@@ -1,55 +1,182 @@
-use std::io;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use super::shared;
-use super::shared::{State, err};
+use super::player;
+use super::shared::State;
+use super::main::Options;
 use coral::bus;
+use coral::cartridge;
+use coral::error::{CoralError, Result};
 
 struct Context {
     nes : bus::Bus,
     shared_data : Arc<shared::Data>,
-    state : State
+    state : State,
+    save_path : PathBuf,
+    // Disk side selected for the drive, whether or not it is inserted.
+    disk_side : usize
 }
 
-fn create_context(filepath : String, shared_data : Arc<shared::Data>) -> io::Result<Context> {
-    let nes = bus::load(filepath)?;
+fn create_context(options : Options, shared_data : Arc<shared::Data>) -> Result<Context> {
+    let save_path = Path::new(&options.filepath).with_extension("sav");
+    // Go by the contents, which also covers ROMs inside archives.
+    let format = cartridge::file_format(&options.filepath)?;
+    let disk_image = format == cartridge::Format::Disk;
+    let music = format == cartridge::Format::Music;
+    let patches : Vec<PathBuf> = if options.patches.is_empty() {
+        cartridge::find_patch(&options.filepath).into_iter().collect()
+    } else {
+        options.patches.iter().map(PathBuf::from).collect()
+    };
+    // Only cartridge ROMs are patched. NSF files are played as they are, and disk changes are
+    // already saved as a patch of their own.
+    let patched = options.board.is_some() || !(music || disk_image || options.bios.is_some());
+    if !patched && !options.patches.is_empty() {
+        return Err(CoralError::InvalidInput("--patch only applies to cartridge ROMs, not to NSF files or disk images.".to_string()));
+    }
+    if patched {
+        for patch in &patches {
+            println!("Applying {}.", patch.display());
+        }
+    }
+    let mut nes = match (options.board, options.bios) {
+        (Some(board), _) => bus::load_with_board_and_patches(&options.filepath, &patches, &fs::read_to_string(board)?)?,
+        (None, _) if music => bus::load_nsf(options.filepath)?,
+        (None, Some(bios)) => bus::load_fds(options.filepath, bios)?,
+        (None, None) if disk_image => return Err(CoralError::InvalidInput("Disk images need the FDS BIOS. Please specify it with --bios <file>.".to_string())),
+        (None, None) => bus::load_with_patches(&options.filepath, &patches)?
+    };
+    if let Some(info) = &nes.cart.nsf {
+        println!("Playing {} by {} ({}), {} tracks. N and P change tracks.", info.title, info.artist, info.copyright, info.tracks);
+    }
+    if let Some(board) = &nes.cart.board {
+        println!("UNIF board {} runs as mapper {}.", board, nes.cart.header.h_mapper);
+    }
+    if let Some(report) = &nes.cart.database {
+        println!("Found {} in the game database (CRC32 {:08X}).", report.name, report.crc);
+        for correction in &report.corrections {
+            println!("Corrected {}: {} in the header, {} in the database.", correction.field, correction.header, correction.database);
+        }
+    }
+    if let Some(value) = options.dip_switches {
+        nes.cart.set_dip_switches(value)?;
+        nes.power_on();
+    }
     let state = State::Running;
 
-    Ok(Context{nes, shared_data, state})
+    Ok(Context{nes, shared_data, state, save_path, disk_side: 0})
+}
+
+fn load_battery(ctx : &mut Context) -> Result<()> {
+    if ctx.save_path.exists() {
+        let data = fs::read(&ctx.save_path)?;
+        ctx.nes.cart.load_save_data(&data)?;
+    }
+    Ok(())
+}
+
+fn store_battery(ctx : &mut Context) -> Result<()> {
+    if let Some(data) = ctx.nes.cart.save_data() {
+        fs::write(&ctx.save_path, data)?;
+    }
+    Ok(())
+}
+
+fn eject_disk(ctx : &mut Context) -> Result<()>{
+    if ctx.nes.cart.disk_sides() == 0 {
+        return Ok(());
+    }
+    if ctx.nes.cart.disk_side().is_some() {
+        ctx.nes.cart.insert_disk(None)?;
+        println!("Ejected the disk.");
+    } else {
+        ctx.nes.cart.insert_disk(Some(ctx.disk_side))?;
+        println!("Inserted disk side {}.", ctx.disk_side + 1);
+    }
+    Ok(())
+}
+
+fn next_disk_side(ctx : &mut Context) -> Result<()>{
+    let sides = ctx.nes.cart.disk_sides();
+    if sides == 0 {
+        return Ok(());
+    }
+    ctx.disk_side = (ctx.disk_side + 1) % sides;
+    ctx.nes.cart.insert_disk(Some(ctx.disk_side))?;
+    println!("Inserted disk side {}.", ctx.disk_side + 1);
+    Ok(())
+}
+
+fn change_track(ctx : &mut Context, offset : isize) -> Result<()>{
+    let (track, tracks) = match (ctx.nes.cart.track(), &ctx.nes.cart.nsf) {
+        (Some(track), Some(info)) => (track, info.tracks),
+        _ => return Ok(())
+    };
+    let track = (track as isize + offset).rem_euclid(tracks as isize) as usize;
+    ctx.nes.cart.select_track(track)
 }
 
-fn handle_command(ctx : &mut Context, command : shared::Command){
+fn handle_command(ctx : &mut Context, command : shared::Command) -> Result<()>{
    match command {
         shared::Command::Stop => {ctx.state = State::Paused}
         shared::Command::Start => {ctx.state = State::Running}
+        shared::Command::Reset => {ctx.nes.soft_reset()}
+        shared::Command::EjectDisk => {eject_disk(ctx)?}
+        shared::Command::NextDiskSide => {next_disk_side(ctx)?}
+        shared::Command::NextTrack => {change_track(ctx, 1)?}
+        shared::Command::PreviousTrack => {change_track(ctx, -1)?}
         shared::Command::Exit => {ctx.state = State::Exit}
    } 
+   Ok(())
 }
 
-fn handle_commands(ctx : &mut Context) -> io::Result<()>{
-    let commands = ctx.shared_data.commands.read().map_err(err)?.clone();
-    ctx.shared_data.commands.write().map_err(err)?.clear();
+fn handle_commands(ctx : &mut Context) -> Result<()>{
+    let commands = ctx.shared_data.commands.read().map_err(CoralError::frontend)?.clone();
+    ctx.shared_data.commands.write().map_err(CoralError::frontend)?.clear();
 
     for command in commands {
-        handle_command(ctx, command);
+        handle_command(ctx, command)?;
     }
 
     Ok(())
 }
 
-fn update_controller(ctx : &mut Context) -> io::Result<()> {
-    let controller = *ctx.shared_data.controller.read().map_err(err)?;
+fn update_controller(ctx : &mut Context) -> Result<()> {
+    let controller = *ctx.shared_data.controller.read().map_err(CoralError::frontend)?;
     ctx.nes.set_controller_a(controller);
     Ok(())
 }
 
-fn save_screen(ctx : &mut Context) -> io::Result<()>{
-    let screen = &mut *ctx.shared_data.screen.write().map_err(err)?;
-    ctx.nes.copy_to_screen(screen);
+fn save_screen(ctx : &mut Context) -> Result<()>{
+    let screen = &mut *ctx.shared_data.screen.write().map_err(CoralError::frontend)?;
+    match (&ctx.nes.cart.nsf, ctx.nes.cart.track()) {
+        (Some(info), Some(track)) => {
+            let level = ctx.shared_data.audio.read().map_err(CoralError::frontend)?.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            player::draw(screen, info, track, level);
+        }
+        _ => ctx.nes.copy_to_screen(screen)
+    }
+    Ok(())
+}
+
+fn save_title(ctx : &mut Context) -> Result<()>{
+    if let (Some(info), Some(track)) = (&ctx.nes.cart.nsf, ctx.nes.cart.track()) {
+        let label = info.track_labels.get(track).map(|label| format!(": {}", label)).unwrap_or_default();
+        *ctx.shared_data.title.write().map_err(CoralError::frontend)? = format!("Coral - {} - Track {}/{}{}", info.title, track + 1, info.tracks, label);
+    }
+    Ok(())
+}
+
+fn save_audio(ctx : &mut Context) -> Result<()>{
+    let audio = &mut *ctx.shared_data.audio.write().map_err(CoralError::frontend)?;
+    ctx.nes.drain_audio(audio);
     Ok(())
 }
 
-pub fn main(filepath : String, shared_data : Arc<shared::Data>) -> io::Result<()>{
-    let mut ctx = create_context(filepath, shared_data)?;
+pub fn main(options : Options, shared_data : Arc<shared::Data>) -> Result<()>{
+    let mut ctx = create_context(options, shared_data)?;
+    load_battery(&mut ctx)?;
     let frame_duration = std::time::Duration::from_micros(16000);
 
     while ctx.state != State::Exit {
@@ -60,10 +187,13 @@ pub fn main(filepath : String, shared_data : Arc<shared::Data>) -> io::Result<()
             ctx.nes.frame();
             let ellapsed_time = time.elapsed();
             let sleep_duration = if ellapsed_time > frame_duration {std::time::Duration::from_millis(0)} else {frame_duration - ellapsed_time};
+            save_audio(&mut ctx)?;
             save_screen(&mut ctx)?;
+            save_title(&mut ctx)?;
             std::thread::sleep(sleep_duration);
         }
     }
 
+    store_battery(&mut ctx)?;
     Ok(())
 }
@@ -3,10 +3,22 @@ use super::emulator;
 use super::shared;
 use std::thread;
 
-pub fn main(filepath : String) -> std::io::Result<()>{
+pub struct Options {
+    pub filepath : String,
+    // Applied to the cartridge before power-on, for boards that have DIP switches.
+    pub dip_switches : Option<u8>,
+    // Board description for cartridges without a dedicated mapper.
+    pub board : Option<String>,
+    // BIOS for Famicom Disk System images.
+    pub bios : Option<String>,
+    // Patches given on the command line, which replace the ones found next to the ROM.
+    pub patches : Vec<String>,
+}
+
+pub fn main(options : Options) -> coral::error::Result<()>{
     let (s1, s2)= shared::new();
 
-    let e = thread::spawn(move || {emulator::main(filepath, s2)});
+    let e = thread::spawn(move || {emulator::main(options, s2)});
     renderer::main(s1).unwrap();
     e.join().unwrap()?;
     Ok(())
@@ -1,6 +1,7 @@
 pub mod renderer;
 pub mod emulator;
 mod shared;
+mod player;
 pub mod main;
 
 pub use main::*;
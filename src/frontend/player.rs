@@ -0,0 +1,73 @@
+use coral::cartridge::nsf::NsfInfo;
+
+// Screen shown instead of the PPU output while playing an NSF: the track number, one box per
+// track with the current one highlighted, and a level meter. Colors are NES palette indices.
+
+const BACKGROUND : u8 = 0x0F;
+const TEXT : u8 = 0x30;
+const TRACK : u8 = 0x2D;
+const CURRENT_TRACK : u8 = 0x21;
+const METER : u8 = 0x2A;
+const DIGIT_SCALE : usize = 8;
+const BOXES_PER_ROW : usize = 24;
+const BOX_ROWS : usize = 6;
+// Level that fills the meter.
+const FULL_LEVEL : f32 = 0.5;
+
+// 3x5 glyphs, one row per byte with the leftmost pixel in bit 2.
+fn glyph(character : char) -> [u8; 5] {
+    match character {
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 1, 1],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        '/' => [1, 1, 2, 4, 4],
+        _ => [0; 5]
+    }
+}
+
+fn fill(screen : &mut [u8; 256 * 240], x : usize, y : usize, width : usize, height : usize, color : u8){
+    for row in y..(y + height).min(240) {
+        for column in x..(x + width).min(256) {
+            screen[row * 256 + column] = color;
+        }
+    }
+}
+
+// Draws the text centered horizontally, with each glyph pixel scaled up.
+fn draw_text(screen : &mut [u8; 256 * 240], text : &str, y : usize, scale : usize){
+    let width = text.len() * 4 * scale - scale;
+    let left = 128usize.saturating_sub(width / 2);
+    for (index, character) in text.chars().enumerate() {
+        let x = left + index * 4 * scale;
+        for (row, bits) in glyph(character).iter().enumerate() {
+            for column in 0..3 {
+                if (bits >> (2 - column)) & 0x01 > 0 {
+                    fill(screen, x + column * scale, y + row * scale, scale, scale, TEXT);
+                }
+            }
+        }
+    }
+}
+
+pub fn draw(screen : &mut [u8; 256 * 240], info : &NsfInfo, track : usize, level : f32){
+    screen.fill(BACKGROUND);
+    draw_text(screen, &format!("{}/{}", track + 1, info.tracks), 40, DIGIT_SCALE);
+
+    let boxes = info.tracks.min(BOXES_PER_ROW * BOX_ROWS);
+    for index in 0..boxes {
+        let x = 8 + (index % BOXES_PER_ROW) * 10;
+        let y = 110 + (index / BOXES_PER_ROW) * 12;
+        let color = if index == track { CURRENT_TRACK } else { TRACK };
+        fill(screen, x, y, 8, 8, color);
+    }
+
+    let width = ((level / FULL_LEVEL).clamp(0.0, 1.0) * 224.0) as usize;
+    fill(screen, 16, 200, width, 12, METER);
+}
@@ -1,14 +1,19 @@
-use std::io;
 use std::sync::Arc;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::controller::Button;
 use sdl2::controller::Axis;
 use sdl2::render::TextureCreator;
 use sdl2::video::WindowContext;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use super::shared;
-use super::shared::{State, color_to_rgba, err};
+use super::shared::{State, color_to_rgba};
+use coral::error::{CoralError, Result};
+use coral::mixer;
+
+// Samples queued beyond this are dropped so audio never lags far behind the picture.
+const MAX_QUEUED_SAMPLES : u32 = mixer::SAMPLE_RATE / 10;
 
 // Types
 
@@ -16,27 +21,34 @@ struct Context<'a>{
     pub state : State,
     pub controller : u8,
     pub shared_data : Arc<shared::Data>,
-    pub screen_texture: sdl2::render::Texture<'a>
+    pub screen_texture: sdl2::render::Texture<'a>,
+    pub audio_queue : AudioQueue<f32>,
+    pub title : String
 }
 
-fn create_context<'a>(shared_data : Arc<shared::Data>, creator : &'a TextureCreator<WindowContext>) -> io::Result<Context<'a>> {
+fn create_context<'a>(shared_data : Arc<shared::Data>, creator : &'a TextureCreator<WindowContext>, audio : &sdl2::AudioSubsystem) -> Result<Context<'a>> {
     let state = State::Running;
     let controller = 0;
-    let screen_texture = creator.create_texture_streaming(PixelFormatEnum::RGBA8888, 256, 240).map_err(err)?;
+    let screen_texture = creator.create_texture_streaming(PixelFormatEnum::RGBA8888, 256, 240).map_err(CoralError::frontend)?;
+    let spec = AudioSpecDesired{freq: Some(mixer::SAMPLE_RATE as i32), channels: Some(1), samples: Some(1024)};
+    let audio_queue = audio.open_queue::<f32, _>(None, &spec).map_err(CoralError::frontend)?;
+    audio_queue.resume();
+
+    let title = String::from("Coral");
 
-    Ok(Context{state, controller, shared_data, screen_texture})
+    Ok(Context{state, controller, shared_data, screen_texture, audio_queue, title})
 }
 
 // Loop
 
-fn send_command(ctx : &mut Context, command : shared::Command) -> io::Result<()>{
+fn send_command(ctx : &mut Context, command : shared::Command) -> Result<()>{
     let commands_lock = &ctx.shared_data.commands;
-    commands_lock.write().map_err(err)?.push(command);
+    commands_lock.write().map_err(CoralError::frontend)?.push(command);
 
     Ok(())
 }
 
-fn toggle_pause(ctx : &mut Context) -> io::Result<()> {
+fn toggle_pause(ctx : &mut Context) -> Result<()> {
     match ctx.state {
        State::Paused =>  {ctx.state = State::Running; send_command(ctx, shared::Command::Start)?;}
        State::Running => {ctx.state = State::Paused;  send_command(ctx, shared::Command::Stop)?;}
@@ -46,10 +58,15 @@ fn toggle_pause(ctx : &mut Context) -> io::Result<()> {
     Ok(())
 }
 
-fn handle_keydown(ctx : &mut Context, keycode : Keycode) -> io::Result<()>{
+fn handle_keydown(ctx : &mut Context, keycode : Keycode) -> Result<()>{
     match keycode {
         Keycode::Q         => {handle_exit(ctx)?;}
         Keycode::Space     => {toggle_pause(ctx)?;}
+        Keycode::R         => {send_command(ctx, shared::Command::Reset)?;}
+        Keycode::E         => {send_command(ctx, shared::Command::EjectDisk)?;}
+        Keycode::S         => {send_command(ctx, shared::Command::NextDiskSide)?;}
+        Keycode::N         => {send_command(ctx, shared::Command::NextTrack)?;}
+        Keycode::P         => {send_command(ctx, shared::Command::PreviousTrack)?;}
         Keycode::Right     => {ctx.controller |= 0x01}
         Keycode::Left      => {ctx.controller |= 0x02}
         Keycode::Down      => {ctx.controller |= 0x04}
@@ -105,14 +122,14 @@ fn handle_controller_up(ctx : &mut Context, button : Button){
 }
 
 
-fn handle_exit(ctx : &mut Context) -> io::Result<()>{
+fn handle_exit(ctx : &mut Context) -> Result<()>{
     ctx.state = State::Exit;
     send_command(ctx, shared::Command::Exit)?;
     Ok(())
 }
 
 
-fn control(event_pump : &mut sdl2::EventPump, ctx : &mut Context) -> io::Result<()>{
+fn control(event_pump : &mut sdl2::EventPump, ctx : &mut Context) -> Result<()>{
     for event in event_pump.poll_iter() {
         match event {
             Event::Quit {..}  => { handle_exit(ctx)?; }
@@ -162,9 +179,9 @@ fn control(event_pump : &mut sdl2::EventPump, ctx : &mut Context) -> io::Result<
     Ok(())
 }
 
-fn update_screen(ctx: &mut Context) -> io::Result<()>{
+fn update_screen(ctx: &mut Context) -> Result<()>{
     let screen_lock = &ctx.shared_data.screen;
-    let screen = screen_lock.read().map_err(err)?.clone();
+    let screen = screen_lock.read().map_err(CoralError::frontend)?.clone();
     ctx.screen_texture.with_lock(None, |texture_data, _pitch| {
         for x in 0..(240*256) {
             let address = x*4;
@@ -175,50 +192,71 @@ fn update_screen(ctx: &mut Context) -> io::Result<()>{
             texture_data[address + 2] = g;
             texture_data[address + 3] = r;
         }
-    }).map_err(err)?;
+    }).map_err(CoralError::frontend)?;
+
+    Ok(())
+}
 
+fn update_audio(ctx : &mut Context) -> Result<()>{
+    let samples = std::mem::take(&mut *ctx.shared_data.audio.write().map_err(CoralError::frontend)?);
+    let queued_samples = ctx.audio_queue.size() / std::mem::size_of::<f32>() as u32;
+    if queued_samples < MAX_QUEUED_SAMPLES {
+        ctx.audio_queue.queue_audio(&samples).map_err(CoralError::frontend)?;
+    }
     Ok(())
 }
 
-fn update_controller(ctx : &mut Context) -> io::Result<()>{
-    *ctx.shared_data.controller.write().map_err(err)? = ctx.controller;
+fn update_controller(ctx : &mut Context) -> Result<()>{
+    *ctx.shared_data.controller.write().map_err(CoralError::frontend)? = ctx.controller;
+    Ok(())
+}
+
+fn update_title(canvas : &mut sdl2::render::Canvas<sdl2::video::Window>, ctx : &mut Context) -> Result<()>{
+    let title = ctx.shared_data.title.read().map_err(CoralError::frontend)?.clone();
+    if title != ctx.title {
+        canvas.window_mut().set_title(&title).map_err(CoralError::frontend)?;
+        ctx.title = title;
+    }
     Ok(())
 }
 
-fn render(canvas : &mut sdl2::render::Canvas<sdl2::video::Window>, ctx: &mut Context) -> io::Result<()>{
+fn render(canvas : &mut sdl2::render::Canvas<sdl2::video::Window>, ctx: &mut Context) -> Result<()>{
     canvas.clear();
-    canvas.copy(&ctx.screen_texture, None, None).map_err(err)?;
+    canvas.copy(&ctx.screen_texture, None, None).map_err(CoralError::frontend)?;
     canvas.present();
     Ok(())
 }
 
 
-pub fn main(shared_data : Arc<shared::Data>)-> io::Result<()> {
+pub fn main(shared_data : Arc<shared::Data>)-> Result<()> {
     // Initialize SDL
 
-    let sdl = sdl2::init().map_err(err)?;
-    let game_controller_subsystem = sdl.game_controller().map_err(err)?;
-    let video = sdl.video().map_err(err)?;
-    let window = video.window("Coral", 256 * 3, 240 * 3).position_centered().build().map_err(err)?;
-    let mut canvas = window.into_canvas().accelerated().build().map_err(err)?;
+    let sdl = sdl2::init().map_err(CoralError::frontend)?;
+    let game_controller_subsystem = sdl.game_controller().map_err(CoralError::frontend)?;
+    let video = sdl.video().map_err(CoralError::frontend)?;
+    let audio = sdl.audio().map_err(CoralError::frontend)?;
+    let window = video.window("Coral", 256 * 3, 240 * 3).position_centered().build().map_err(CoralError::frontend)?;
+    let mut canvas = window.into_canvas().accelerated().build().map_err(CoralError::frontend)?;
     let creator = canvas.texture_creator();
     let mut event_pump = sdl.event_pump().unwrap();
 
     // Initialize Joysticks
-    let num_joysticks = game_controller_subsystem.num_joysticks().map_err(err)?;
+    let num_joysticks = game_controller_subsystem.num_joysticks().map_err(CoralError::frontend)?;
     let _controller_a = if num_joysticks > 0 {
-        Some(game_controller_subsystem.open(0).map_err(err)?)
+        Some(game_controller_subsystem.open(0).map_err(CoralError::frontend)?)
     } else {None};
     // Initialize Context
 
-    let mut ctx = create_context(shared_data, &creator)?;
+    let mut ctx = create_context(shared_data, &creator, &audio)?;
 
     // Main loop
 
     while ctx.state != State::Exit {
         control(&mut event_pump, &mut ctx)?;
         update_screen(&mut ctx)?;
+        update_audio(&mut ctx)?;
         update_controller(&mut ctx)?;
+        update_title(&mut canvas, &mut ctx)?;
         render(&mut canvas, &mut ctx)?;
         std::thread::sleep(std::time::Duration::from_micros(16000));
     }
@@ -1,15 +1,15 @@
-use std::io;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-pub fn err<T : std::string::ToString>(e : T) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, e.to_string())
-}
-
 #[derive(Copy, Clone, PartialEq)]
 pub enum Command {
     Start,
     Stop,
+    Reset,
+    EjectDisk,
+    NextDiskSide,
+    NextTrack,
+    PreviousTrack,
     Exit
 }
 
@@ -24,6 +24,8 @@ pub struct Data {
     pub screen : RwLock<[u8; 256 * 240]>,
     pub controller : RwLock<u8>,
     pub commands : RwLock<Vec<Command>>,
+    pub audio : RwLock<Vec<f32>>,
+    pub title : RwLock<String>,
 }
 
 
@@ -31,8 +33,10 @@ pub fn new() -> (Arc<Data>, Arc<Data>){
    let screen = RwLock::new([0; 256 * 240]); 
    let controller = RwLock::new(0);
    let commands = RwLock::new(vec![]);
+   let audio = RwLock::new(vec![]);
+   let title = RwLock::new(String::from("Coral"));
 
-   let shared_data = Data{screen, controller, commands};
+   let shared_data = Data{screen, controller, commands, audio, title};
    let arc = Arc::new(shared_data);
    let a1 = arc.clone();
    let a2 = arc.clone();
@@ -2,7 +2,55 @@ extern crate coral;
 use std::env;
 mod frontend;
 
-pub fn main() -> std::io::Result<()>{
+fn parse_options(args : &[String]) -> frontend::Options {
+    let filepath = args[1].clone();
+    let mut options = frontend::Options{filepath, dip_switches: None, board: None, bios: None, patches: vec![]};
+
+    let mut remaining = args[2..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            "--dip" => {
+                let value = remaining.next().and_then(|value| value.parse::<u8>().ok());
+                if value.is_none() {
+                    println!("Error: --dip expects the DIP switch setting as a number between 0 and 255.");
+                    std::process::exit(-1);
+                }
+                options.dip_switches = value;
+            }
+            "--board" => {
+                let path = remaining.next();
+                if path.is_none() {
+                    println!("Error: --board expects the path to a board description.");
+                    std::process::exit(-1);
+                }
+                options.board = path.cloned();
+            }
+            "--bios" => {
+                let path = remaining.next();
+                if path.is_none() {
+                    println!("Error: --bios expects the path to the FDS BIOS.");
+                    std::process::exit(-1);
+                }
+                options.bios = path.cloned();
+            }
+            "--patch" => {
+                match remaining.next() {
+                    Some(path) => options.patches.push(path.clone()),
+                    None => {
+                        println!("Error: --patch expects the path to an IPS, UPS or BPS patch.");
+                        std::process::exit(-1);
+                    }
+                }
+            }
+            _ => {
+                println!("Warning: Ignoring unknown option {}.", arg);
+            }
+        }
+    }
+    options
+}
+
+pub fn main() {
     let args: Vec<String> = env::args().collect();
 
     if args.len() <= 1 {
@@ -10,8 +58,19 @@ pub fn main() -> std::io::Result<()>{
         std::process::exit(-1);
     }
 
-    let filepath = args[1].clone();
-    frontend::main(filepath)?;
+    if args[1] == "--list-mappers" {
+        for info in coral::cartridge::supported_mappers() {
+            match info.submapper {
+                Some(submapper) => println!("{:>4}.{:<2} {}", info.mapper, submapper, info.name),
+                None => println!("{:>4}    {}", info.mapper, info.name)
+            }
+        }
+        return;
+    }
 
-    Ok(())
+    let options = parse_options(&args);
+    if let Err(error) = frontend::main(options) {
+        println!("Error: {}", error);
+        std::process::exit(-1);
+    }
 }
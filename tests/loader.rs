@@ -0,0 +1,596 @@
+use coral::bus;
+use coral::cartridge;
+use coral::cartridge::database;
+use coral::cartridge::disk;
+use coral::cartridge::nsf;
+use coral::cartridge::patch;
+use coral::cartridge::archive;
+use coral::cartridge::unif;
+use coral::error::CoralError;
+use coral::mos::Bus;
+use coral::cartridge::types::{ConsoleType, Format, Mirroring, PlayChoice, TVSystem, VsHardware, VsPpu};
+use std::fs;
+use std::path::PathBuf;
+
+fn write_rom(name : &str, header : [u8; 16], body_size : usize) -> PathBuf {
+    let mut rom = header.to_vec();
+    rom.resize(16 + body_size, 0);
+    let path = std::env::temp_dir().join(format!("coral_loader_{}_{}.nes", name, std::process::id()));
+    fs::write(&path, rom).unwrap();
+    path
+}
+
+#[test]
+fn ines_header() {
+    let header = [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x41, 0x00, 0, 0x01, 0, 0, 0, 0, 0, 0];
+    let path = write_rom("ines", header, 0x8000 + 0x2000);
+    let cart = cartridge::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert!(!cart.header.h_nes2);
+    assert_eq!(cart.header.h_mapper, 4);
+    assert_eq!(cart.header.h_prg_size, 2);
+    assert_eq!(cart.header.h_chr_size, 1);
+    assert_eq!(cart.header.h_tv_system, TVSystem::PAL);
+    assert_eq!(cart.header.h_prg_ram_size, 0x2000);
+}
+
+#[test]
+fn ines_header_ignores_garbage_mapper_nibble() {
+    let mut header = [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0x00, 0x40, 0, 0, 0, 0, 0, 0, 0, 0];
+    header[12..].copy_from_slice(b"Dude");
+    let path = write_rom("ines_garbage", header, 0x4000 + 0x2000);
+    let cart = cartridge::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(cart.header.h_mapper, 0);
+}
+
+#[test]
+fn nes2_header() {
+    // Mapper 4.1 (MMC6), 48 KB of PRG in exponent notation, CHR RAM, Vs. System.
+    let header = [0x4E, 0x45, 0x53, 0x1A, (14 << 2) | 1, 0, 0x42, 0x09, 0x10, 0x0F, 0x77, 0x09, 0x03, 0x13, 0x01, 0x02];
+    let path = write_rom("nes2", header, 0xC000 + 0x100);
+    let cart = bus::load(&path).unwrap().cart;
+    fs::remove_file(path).unwrap();
+
+    assert!(cart.header.h_nes2);
+    assert_eq!(cart.header.h_mapper, 4);
+    assert_eq!(cart.header.h_submapper, 1);
+    assert_eq!(cart.header.h_prg_rom_bytes, 0xC000);
+    assert_eq!(cart.header.h_prg_size, 3);
+    assert!(cart.header.h_chr_ram);
+    assert_eq!(cart.header.h_prg_ram_size, 0x2000);
+    assert_eq!(cart.header.h_prg_nvram_size, 0x2000);
+    assert_eq!(cart.header.h_chr_ram_size, 0x8000);
+    assert_eq!(cart.header.h_chr_nvram_size, 0);
+    assert_eq!(cart.header.h_tv_system, TVSystem::Dendy);
+    assert_eq!(cart.header.h_console, ConsoleType::NVS);
+    assert_eq!(cart.header.h_vs_ppu, VsPpu::Rp2c04_0002);
+    assert_eq!(cart.header.h_vs_hardware, VsHardware::RbiBaseball);
+    assert_eq!(cart.header.h_misc_roms, 1);
+    assert_eq!(cart.misc_data.len(), 0x100);
+    assert_eq!(cart.header.h_expansion_device, 2);
+    assert_eq!(cart.header.chr_ram_size(0x2000), 0x8000);
+}
+
+#[test]
+fn nes2_mapper_above_255() {
+    let header = [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0x00, 0x08, 0x01, 0, 0, 0, 0, 0, 0, 0];
+    let path = write_rom("nes2_256", header, 0x4000 + 0x2000);
+    let error = bus::load(&path).err().unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert!(error.to_string().contains("Mapper 256"));
+}
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(database::crc32(b"123456789"), 0xCBF43926);
+}
+
+#[test]
+fn game_database_corrects_header() {
+    // Declared as NROM without a battery, but the database knows it is a TKROM board.
+    let header = [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut rom = vec![0u8; 0x8000 + 0x2000];
+    rom[..13].copy_from_slice(b"database test");
+    let mut file = header.to_vec();
+    file.extend_from_slice(&rom);
+    let path = std::env::temp_dir().join(format!("coral_loader_database_{}.nes", std::process::id()));
+    fs::write(&path, file).unwrap();
+
+    let crc = database::crc32(&rom);
+    let text = format!("{:08X} | name=Test | board=NES-TKROM | mapper=4 | mirroring=horizontal | battery=true | prg_nvram=8192", crc);
+    let mut entries = database::parse(&text).unwrap();
+    database::register(entries.remove(0)).unwrap();
+    let cart = cartridge::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(cart.header.h_mapper, 4);
+    assert!(cart.header.h_battery);
+    assert_eq!(cart.header.h_prg_ram_size, 0);
+    assert_eq!(cart.header.h_prg_nvram_size, 0x2000);
+    let report = cart.database.unwrap();
+    assert_eq!(report.crc, crc);
+    assert_eq!(report.board.as_deref(), Some("NES-TKROM"));
+    let fields : Vec<&str> = report.corrections.iter().map(|correction| correction.field).collect();
+    assert_eq!(fields, ["mapper", "mirroring", "battery", "RAM sizes"]);
+    assert_eq!(report.corrections[0].header, "0");
+    assert_eq!(report.corrections[0].database, "4");
+}
+
+#[test]
+fn builtin_game_database() {
+    let entries = database::parse(database::DATABASE).unwrap();
+    assert!(entries.len() > 10);
+    let mut crcs : Vec<u32> = entries.iter().map(|entry| entry.crc).collect();
+    crcs.sort();
+    crcs.dedup();
+    assert_eq!(crcs.len(), entries.len());
+}
+
+// Rewrites the last four bytes of `data` so that its CRC32 becomes `crc`. Running the CRC
+// register backwards from the target gives the value it must hold before those bytes.
+fn force_crc32(data : &mut [u8], crc : u32) {
+    let length = data.len();
+    data[length - 4..].fill(0);
+    let mut before = !database::crc32(&data[..length - 4]);
+    let mut register = !crc;
+    for _ in 0..32 {
+        register = if register & 0x8000_0000 > 0 { ((register ^ 0xEDB8_8320) << 1) | 1 } else { register << 1 };
+    }
+    before ^= register;
+    data[length - 4..].copy_from_slice(&before.to_le_bytes());
+}
+
+#[test]
+fn game_database_fixes_known_bad_header() {
+    // Famicom Jump II, dumped as plain mapper 16 without its battery-backed SRAM.
+    let header = [0x4E, 0x45, 0x53, 0x1A, 32, 0, 0x00, 0x10, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut rom = vec![0u8; 32 * 0x4000];
+    force_crc32(&mut rom, 0x3F15D20D);
+    let mut file = header.to_vec();
+    file.extend_from_slice(&rom);
+
+    let cart = cartridge::load_from_bytes(&file).unwrap();
+    assert_eq!(cart.header.h_mapper, 153);
+    assert!(cart.header.h_battery);
+    assert!(!cart.header.h_nes2);
+    assert!(cart.header.h_ram_sizes_known);
+    assert_eq!(cart.header.prg_ram_size(0), 0x2000);
+    let report = cart.database.unwrap();
+    assert_eq!(report.crc, 0x3F15D20D);
+    assert_eq!(report.name, "Famicom Jump II: Saikyou no 7 Nin (J)");
+    let fields : Vec<&str> = report.corrections.iter().map(|correction| correction.field).collect();
+    assert_eq!(fields, ["mapper", "battery", "RAM sizes"]);
+}
+
+#[test]
+fn game_database_errors() {
+    let error = database::parse("# comment\n\n1234ABCD | name=Game | mirroring=diagonal").unwrap_err();
+    assert!(error.to_string().contains("line 3: Unknown mirroring diagonal"));
+    assert!(database::parse("XYZ | name=Game").is_err());
+}
+
+#[test]
+fn fds_and_qd_images() {
+    let mut info = vec![0x01];
+    info.extend_from_slice(b"*NINTENDO-HVC*");
+    info.resize(56, 0);
+    let blocks = [info, vec![0x02, 0x00]];
+
+    let mut fds = blocks.concat();
+    fds.resize(65500 * 2, 0);
+    fds[65500..65500 + 58].copy_from_slice(&blocks.concat());
+    let mut qd = vec![];
+    for _ in 0..2 {
+        let start = qd.len();
+        for block in &blocks {
+            qd.extend_from_slice(block);
+            qd.extend_from_slice(&[0xAA, 0x55]);
+        }
+        qd.resize(start + 0x10000, 0);
+    }
+    let fds_image = disk::parse(&fds).unwrap();
+    assert_eq!(fds_image.sides.len(), 2);
+    assert_eq!(disk::parse(&qd).unwrap(), fds_image);
+    assert_eq!(disk::from_raw(&disk::to_raw(&fds_image.sides[1])), fds_image.sides[1]);
+
+    let disk_path = std::env::temp_dir().join(format!("coral_loader_fds_{}.fds", std::process::id()));
+    let bios_path = std::env::temp_dir().join(format!("coral_loader_fds_{}.rom", std::process::id()));
+    let mut with_header = vec![0x46, 0x44, 0x53, 0x1A, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    with_header.extend_from_slice(&fds);
+    fs::write(&disk_path, with_header).unwrap();
+    fs::write(&bios_path, [0; 0x1000]).unwrap();
+    let bios_error = cartridge::load_fds(&disk_path, &bios_path).err().unwrap();
+    let ines_error = cartridge::load(&disk_path).err().unwrap();
+    let format = cartridge::file_format(&disk_path).unwrap();
+    fs::write(&bios_path, [0; 0x2000]).unwrap();
+    let cart = cartridge::load_fds(&disk_path, &bios_path).unwrap();
+    fs::remove_file(disk_path).unwrap();
+    fs::remove_file(bios_path).unwrap();
+
+    assert!(bios_error.to_string().contains("Expected 8192 bytes"));
+    assert!(ines_error.to_string().contains("Famicom Disk System"));
+    assert_eq!(format, Format::Disk);
+    assert_eq!(cartridge::detect_format(&fds), Format::Disk);
+    assert_eq!(cartridge::detect_format(&qd), Format::Disk);
+    assert_eq!(cart.disk_sides(), 2);
+    assert_eq!(cart.disk_side(), Some(0));
+}
+
+fn nsfe_chunk(id : &[u8], data : &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+    chunk.extend_from_slice(id);
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+#[test]
+fn nsfe_chunks() {
+    let mut file = b"NSFE".to_vec();
+    file.extend(nsfe_chunk(b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x01, 0x24, 2, 1]));
+    file.extend(nsfe_chunk(b"DATA", &[0x60; 16]));
+    file.extend(nsfe_chunk(b"auth", b"Title\0Artist\0Copyright\0Ripper\0"));
+    file.extend(nsfe_chunk(b"tlbl", b"Intro\0Theme\0"));
+    file.extend(nsfe_chunk(b"time", &[0; 8]));
+    file.extend(nsfe_chunk(b"NEND", &[]));
+    let nsf = nsf::parse(&file).unwrap();
+
+    assert_eq!(nsf.load_address, 0x8000);
+    assert_eq!(nsf.play_address, 0x8003);
+    assert_eq!(nsf.info.region, TVSystem::PAL);
+    assert_eq!(nsf.info.expansion, nsf::FDS | nsf::SUNSOFT_5B);
+    assert_eq!(nsf.info.tracks, 2);
+    assert_eq!(nsf.info.first_track, 1);
+    assert_eq!(nsf.info.artist, "Artist");
+    assert_eq!(nsf.info.track_labels, ["Intro", "Theme"]);
+    assert_eq!(nsf.data.len(), 16);
+    assert!(nsf.banks.is_none());
+    assert_eq!(cartridge::detect_format(&file), Format::Music);
+    let cart = cartridge::load_from_bytes(&file).unwrap();
+    assert_eq!(cart.nsf.unwrap().track_labels, ["Intro", "Theme"]);
+
+    let mut unknown = b"NSFE".to_vec();
+    unknown.extend(nsfe_chunk(b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x00, 0x00]));
+    unknown.extend(nsfe_chunk(b"XTRA", &[0]));
+    assert!(nsf::parse(&unknown).unwrap_err().to_string().contains("Unsupported NSFe chunk XTRA"));
+}
+
+fn unif_chunk(id : &[u8], data : &[u8]) -> Vec<u8> {
+    let mut chunk = id.to_vec();
+    chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(data);
+    chunk
+}
+
+fn unif_file(board : &[u8]) -> Vec<u8> {
+    let mut file = b"UNIF".to_vec();
+    file.resize(32, 0);
+    file[4] = 7;
+    file.extend(unif_chunk(b"MAPR", board));
+    file.extend(unif_chunk(b"PRG1", &[0x22; 0x4000]));
+    file.extend(unif_chunk(b"PRG0", &[0x11; 0x4000]));
+    file.extend(unif_chunk(b"CHR0", &[0x33; 0x2000]));
+    file.extend(unif_chunk(b"MIRR", &[1]));
+    file.extend(unif_chunk(b"BATR", &[1]));
+    file.extend(unif_chunk(b"TVCI", &[1]));
+    file.extend(unif_chunk(b"NAME", b"Test\0"));
+    file
+}
+
+#[test]
+fn unif_chunks() {
+    let path = std::env::temp_dir().join(format!("coral_loader_unif_{}.unf", std::process::id()));
+    fs::write(&path, unif_file(b"NES-TLROM\0")).unwrap();
+    let cart = cartridge::load(&path).unwrap();
+    fs::write(&path, unif_file(b"UNL-MYSTERY\0")).unwrap();
+    let error = cartridge::load(&path).err().unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(cart.board.as_deref(), Some("NES-TLROM"));
+    assert_eq!(cart.header.h_mapper, 4);
+    assert_eq!(cart.header.h_prg_size, 2);
+    assert_eq!(cart.header.h_chr_size, 1);
+    assert_eq!(cart.header.h_mirroring, Mirroring::Horizontal);
+    assert!(cart.header.h_battery);
+    assert_eq!(cart.header.h_tv_system, TVSystem::PAL);
+    assert_eq!(cart.prg_data[0], 0x11);
+    assert_eq!(cart.prg_data[0x4000], 0x22);
+    assert!(error.to_string().contains("UNIF board UNL-MYSTERY"));
+
+    assert_eq!(unif::board_mapper("HVC-HKROM"), Some((4, 1)));
+    assert_eq!(unif::board_mapper("TQROM"), Some((119, 0)));
+    assert!(unif::parse(&unif_file(b"NES-NROM-256\0")[..40]).is_err());
+}
+
+fn patch_number(number : usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut number = number;
+    loop {
+        let low = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            bytes.push(0x80 | low);
+            return bytes;
+        }
+        bytes.push(low);
+        number -= 1;
+    }
+}
+
+fn patch_footer(patch : &mut Vec<u8>, source : &[u8], target : &[u8]) {
+    patch.extend_from_slice(&database::crc32(source).to_le_bytes());
+    patch.extend_from_slice(&database::crc32(target).to_le_bytes());
+    let patch_crc = database::crc32(patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+}
+
+#[test]
+fn ups_bps_and_ips_patches() {
+    let path = write_rom("patched", [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0x4000 + 0x2000);
+    let rom = fs::read(&path).unwrap();
+
+    // UPS: the first PRG byte becomes $EA.
+    let mut ups_target = rom.clone();
+    ups_target[16] = 0xEA;
+    let mut ups = b"UPS1".to_vec();
+    ups.extend(patch_number(rom.len()));
+    ups.extend(patch_number(rom.len()));
+    ups.extend(patch_number(16));
+    ups.extend_from_slice(&[0xEA, 0x00]);
+    patch_footer(&mut ups, &rom, &ups_target);
+
+    // BPS: the mapper nibble becomes 2, and the last byte is a copy of the one before.
+    let mut bps_target = rom.clone();
+    bps_target[6] = 0x20;
+    let last = rom.len() - 1;
+    bps_target[last - 1] = 0x55;
+    bps_target[last] = 0x55;
+    let mut bps = b"BPS1".to_vec();
+    bps.extend(patch_number(rom.len()));
+    bps.extend(patch_number(rom.len()));
+    bps.extend(patch_number(0));
+    bps.extend(patch_number(5 << 2));
+    bps.extend(patch_number(1));
+    bps.push(0x20);
+    bps.extend(patch_number(((last - 9) << 2) | 2));
+    bps.extend(patch_number(7 << 1));
+    bps.extend(patch_number(1));
+    bps.push(0x55);
+    bps.extend(patch_number(3));
+    bps.extend(patch_number((last - 1) << 1));
+    patch_footer(&mut bps, &rom, &bps_target);
+    assert_eq!(patch::apply_bps(&rom, &bps).unwrap(), bps_target);
+
+    let ups_path = path.with_extension("ups");
+    let bps_path = path.with_extension("bps");
+    fs::write(&ups_path, &ups).unwrap();
+    fs::write(&bps_path, &bps).unwrap();
+    let found = cartridge::find_patch(&path);
+    let detected = cartridge::load(&path).unwrap();
+    let both = cartridge::load_with_patches(&path, &[&ups_path, &bps_path]);
+    let board = cartridge::load_with_board_and_patches(&path, &[&ups_path], "").unwrap();
+    fs::remove_file(&bps_path).unwrap();
+    let detected_ups = cartridge::load(&path).unwrap();
+    let untouched = fs::read(&path).unwrap();
+    fs::remove_file(&ups_path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // Only the BPS patch is picked up when both are there.
+    assert_eq!(found, Some(bps_path));
+    assert_eq!(detected.header.h_mapper, 2);
+    assert_eq!(detected.prg_data[0], rom[16]);
+    assert_eq!(detected_ups.prg_data[0], 0xEA);
+    assert_eq!(board.prg_data[0], 0xEA);
+    // The BPS patch was made for the original ROM, not the one the UPS patch produced.
+    assert!(both.err().unwrap().to_string().contains("ROM checksum"));
+    assert_eq!(untouched, rom);
+
+    // A target size no ROM has is refused before anything is allocated.
+    let mut huge = b"UPS1".to_vec();
+    huge.extend(patch_number(rom.len()));
+    huge.extend(patch_number(1 << 40));
+    patch_footer(&mut huge, &rom, &rom);
+    assert!(patch::apply(&rom, &huge).unwrap_err().to_string().contains("more than the"));
+
+    let mut corrupt = ups.clone();
+    corrupt[8] ^= 0x01;
+    assert!(patch::apply(&rom, &corrupt).unwrap_err().to_string().contains("Patch checksum"));
+    assert!(patch::apply(&rom, b"NOT A PATCH").unwrap_err().to_string().contains("Unknown patch format"));
+
+    let ips = patch::create_ips(&rom, &ups_target).unwrap();
+    assert_eq!(patch::apply(&rom, &ips).unwrap(), ups_target);
+}
+
+// NROM image with a repeating pattern in PRG and CHR, and the same image compressed with gzip.
+fn archived_rom() -> Vec<u8> {
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend((0..0x4000).map(|index| (index % 16) as u8 * 3));
+    rom.extend((0..0x2000).map(|index| if index % 8 < 4 { 0xFF } else { 0x0F }));
+    rom
+}
+
+const GZIPPED_ROM : [u8; 122] = [
+    0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xED, 0xC7, 0x41, 0x06, 0x80, 0x40,
+    0x00, 0x40, 0xD1, 0x46, 0x44, 0xC4, 0x10, 0x91, 0x86, 0x88, 0x88, 0xE8, 0x18, 0x6D, 0xDB, 0x74,
+    0xFF, 0xB3, 0xD4, 0xEC, 0xE6, 0x06, 0x11, 0xEF, 0x6F, 0xBE, 0x77, 0x9D, 0xF7, 0x14, 0x42, 0x55,
+    0xAA, 0x9B, 0xB6, 0x8B, 0xFD, 0x30, 0xA6, 0x79, 0x59, 0xB7, 0xFD, 0x60, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0xE6, 0xEF, 0xFD, 0xE4, 0x62, 0xCE,
+    0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xDD, 0xFF,
+    0xFB, 0x17, 0x62, 0x6A, 0x52, 0xB0, 0x10, 0x60, 0x00, 0x00,
+];
+
+fn zip_entry(name : &[u8], method : u16, data : &[u8], compressed : &[u8], offset : usize) -> (Vec<u8>, Vec<u8>) {
+    let mut fields = vec![20, 0, 0, 0];
+    fields.extend_from_slice(&method.to_le_bytes());
+    fields.extend_from_slice(&[0; 4]);
+    fields.extend_from_slice(&database::crc32(data).to_le_bytes());
+    fields.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    fields.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    fields.extend_from_slice(&[0, 0]);
+
+    let mut local = b"PK\x03\x04".to_vec();
+    local.extend_from_slice(&fields);
+    local.extend_from_slice(name);
+    local.extend_from_slice(compressed);
+    let mut central = b"PK\x01\x02".to_vec();
+    central.extend_from_slice(&[20, 0]);
+    central.extend_from_slice(&fields);
+    central.extend_from_slice(&[0; 10]);
+    central.extend_from_slice(&(offset as u32).to_le_bytes());
+    central.extend_from_slice(name);
+    (local, central)
+}
+
+#[test]
+fn zip_and_gzip_archives() {
+    let rom = archived_rom();
+    assert_eq!(archive::gunzip(&GZIPPED_ROM).unwrap(), rom);
+
+    // A readme stored as is, and the ROM compressed with the same DEFLATE data as the gzip file.
+    let readme : &[u8] = b"Unpack me";
+    let (mut zip, mut directory) = zip_entry(b"readme.txt", 0, readme, readme, 0);
+    let (local, central) = zip_entry(b"game.nes", 8, &rom, &GZIPPED_ROM[10..114], zip.len());
+    zip.extend(local);
+    directory.extend(central);
+    let directory_offset = zip.len();
+    zip.extend_from_slice(&directory);
+    zip.extend_from_slice(b"PK\x05\x06\0\0\0\0\x02\0\x02\0");
+    zip.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&(directory_offset as u32).to_le_bytes());
+    zip.extend_from_slice(&[0, 0]);
+    assert_eq!(archive::extract(zip.clone()).unwrap(), rom);
+
+    let path = std::env::temp_dir().join(format!("coral_loader_archive_{}.nes.gz", std::process::id()));
+    fs::write(&path, GZIPPED_ROM).unwrap();
+    let from_file = cartridge::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let from_zip = cartridge::load_from_bytes(&zip).unwrap();
+    let from_reader = bus::load_from_reader(rom.as_slice()).unwrap();
+    assert_eq!(from_file.prg_data, rom[16..0x4010]);
+    assert_eq!(from_zip.chr_data, rom[0x4010..]);
+    assert_eq!(from_reader.cart.header.h_prg_size, 1);
+
+    // Fixed Huffman codes, then a stored block.
+    let fixed = [0x73, 0xCE, 0x2F, 0x4A, 0xCC, 0x51, 0x70, 0x46, 0x27, 0x15, 0x01];
+    assert_eq!(archive::inflate(&fixed).unwrap(), (b"Coral Coral Coral Coral!".to_vec(), fixed.len()));
+    let stored = [0x01, 0x03, 0x00, 0xFC, 0xFF, b'N', b'E', b'S'];
+    assert_eq!(archive::inflate(&stored).unwrap().0, b"NES");
+
+    let mut corrupt = GZIPPED_ROM;
+    corrupt[114] ^= 0x01;
+    assert!(archive::gunzip(&corrupt).unwrap_err().to_string().contains("CRC32"));
+    assert!(cartridge::load_from_bytes(&GZIPPED_ROM[..60]).is_err());
+    assert_eq!(cartridge::detect_format(&rom), Format::Cartridge);
+}
+
+#[test]
+fn inflate_output_is_bounded() {
+    // One fixed Huffman block of a zero followed by back references of 258 bytes at distance 1,
+    // enough for one more byte than allowed. Codes are written most significant bit first.
+    let mut bits = vec![1, 1, 0];
+    let mut code = |value : u32, length : u32| bits.extend((0..length).rev().map(|bit| (value >> bit) & 1));
+    code(0x30, 8);
+    for _ in 0..archive::MAX_OUTPUT_SIZE / 258 + 1 {
+        code(0xC5, 8);
+        code(0, 5);
+    }
+    code(0, 7);
+    let data : Vec<u8> = bits.chunks(8).map(|byte| byte.iter().enumerate().map(|(index, bit)| (*bit as u8) << index).sum()).collect();
+    let error = archive::inflate(&data).unwrap_err();
+    assert!(error.to_string().contains("larger than 33554432 bytes"));
+}
+
+#[test]
+fn trainer_and_playchoice_roms() {
+    // Trainer, one PRG bank whose reset vector points at the trainer, and no CHR ROM.
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend((0..512).map(|index| index as u8));
+    rom.resize(rom.len() + 0x4000, 0xEA);
+    let reset_vector = rom.len() - 4;
+    rom[reset_vector..reset_vector + 2].copy_from_slice(&[0x00, 0x70]);
+    let path = std::env::temp_dir().join(format!("coral_loader_trainer_{}.nes", std::process::id()));
+    fs::write(&path, &rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+
+    assert!(nes.cart.header.h_trainer);
+    assert_eq!(nes.cart.cpu_read(0x7000), 0x00);
+    assert_eq!(nes.read_byte(0x71FF), 0xFF);
+    assert_eq!(nes.cart.cpu_read(0xFFFD), 0x70);
+    nes.cart.cpu_write(0x7010, 0x42);
+    assert_eq!(nes.cart.cpu_read(0x7010), 0x42);
+    assert_eq!(nes.cart.trainer[0x10], 0x10);
+    nes.soft_reset();
+    assert_eq!(nes.cart.cpu_read(0x7010), 0x42);
+    nes.power_on();
+    assert_eq!(nes.cart.cpu_read(0x7010), 0x10);
+    assert!(nes.cart.playchoice.is_none());
+
+    // PlayChoice-10 dump with the INST-ROM and only the PROM data.
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0x02, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.resize(16 + 0x4000 + 0x2000, 0);
+    rom.resize(rom.len() + 0x2000, 0x1E);
+    rom.extend_from_slice(&[0x2A; 16]);
+    fs::write(&path, &rom).unwrap();
+    let cart = cartridge::load(&path).unwrap();
+    fs::write(&path, &rom[..rom.len() - 0x1000]).unwrap();
+    let truncated = cartridge::load(&path).err().unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(cart.header.h_console, ConsoleType::Playchoice10);
+    assert_eq!(cart.playchoice, Some(PlayChoice { inst_rom: vec![0x1E; 0x2000], prom_data: Some([0x2A; 16]), prom_counter_out: None }));
+    assert!(matches!(truncated, CoralError::SizeMismatch { expected: 0x2000, found: 0x1010, .. }));
+}
+
+#[test]
+fn structured_errors() {
+    // Mapper 1, then a mapper registered for submapper 2 only, loaded with submapper 1.
+    let unsupported = write_rom("unsupported", [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0x4000 + 0x2000);
+    let submapper = write_rom("submapper", [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0x80, 0xB8, 0x10, 0, 0, 0, 0, 0, 0, 0], 0x4000 + 0x2000);
+    let truncated = write_rom("truncated", [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0x4000);
+    let battery = write_rom("battery", [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0], 0x8000 + 0x2000);
+    cartridge::register(0xB8, Some(2), "Submapper 2 only", |_| Ok(()));
+    let errors = [&unsupported, &submapper, &truncated].map(|path| cartridge::load(path).err().unwrap());
+    let mut cart = cartridge::load(&battery).unwrap();
+    for path in [unsupported, submapper, truncated, battery] {
+        fs::remove_file(path).unwrap();
+    }
+
+    assert!(matches!(errors[0], CoralError::UnsupportedMapper(1)));
+    assert!(matches!(errors[1], CoralError::UnsupportedSubmapper(0xB8, 1)));
+    assert!(matches!(errors[2], CoralError::SizeMismatch { expected: 0x8000, found: 0x4000, .. }));
+    assert_eq!(errors[2].to_string(), "Failed to load the PRG ROM: Expected 32768 bytes, found 16384.");
+    assert!(matches!(cart.load_save_data(&[0; 100]), Err(CoralError::InvalidSaveState(_))));
+    assert!(cart.load_save_data(&[0; 0x2000]).is_ok());
+    assert!(matches!(cart.insert_disk(Some(0)), Err(CoralError::InvalidInput(_))));
+    assert_eq!(std::io::Error::from(CoralError::UnsupportedMapper(1)).kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn oversized_nes2_rom_sizes() {
+    // Exponent-multiplier sizes: 2^29 bytes of PRG ROM that the file does not have, then 2^30
+    // bytes, which is more 16 KB banks than the header fields can count.
+    let missing = [0x4E, 0x45, 0x53, 0x1A, 29 << 2, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0];
+    let too_many_banks = [0x4E, 0x45, 0x53, 0x1A, 30 << 2, 0, 0, 0x08, 0, 0x0F, 0, 0, 0, 0, 0, 0];
+    let missing = cartridge::load_from_bytes(&missing).err().unwrap();
+    let too_many_banks = cartridge::load_from_bytes(&too_many_banks).err().unwrap();
+
+    assert!(matches!(missing, CoralError::SizeMismatch { expected: 0x2000_0000, found: 0, .. }));
+    assert!(matches!(too_many_banks, CoralError::InvalidHeader(_)));
+}
+
+#[test]
+fn missing_prg_rom() {
+    // MMC3 with only CHR ROM, as iNES and as NES 2.0.
+    for flag7 in [0x00, 0x08] {
+        let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 0, 1, 0x40, flag7, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.resize(16 + 0x2000, 0);
+        let error = cartridge::load_from_bytes(&rom).err().unwrap();
+        assert!(matches!(error, CoralError::InvalidHeader(_)));
+        assert!(error.to_string().contains("no PRG ROM"));
+    }
+}
@@ -0,0 +1,1500 @@
+use coral::bus;
+use coral::cartridge;
+use coral::cartridge::mapper::types::{Mapper, MapperT, Reset};
+use coral::cartridge::types::{Mirroring, Nametable};
+use coral::mixer;
+use coral::mos::Bus;
+use coral::ppu;
+use std::fs;
+use std::path::PathBuf;
+
+// Writes an iNES image where every 8 KB PRG bank and 1 KB CHR bank is filled with its own index.
+fn build_rom(name : &str, mapper : u8, prg_16k : u8, chr_8k : u8) -> PathBuf {
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, prg_16k, chr_8k, (mapper & 0x0F) << 4, mapper & 0xF0, 0, 0, 0, 0, 0, 0, 0, 0];
+    for bank in 0..(prg_16k as usize * 2) {
+        rom.resize(rom.len() + 0x2000, bank as u8);
+    }
+    for bank in 0..(chr_8k as usize * 8) {
+        rom.resize(rom.len() + 0x0400, bank as u8);
+    }
+    let path = std::env::temp_dir().join(format!("coral_{}_{}.nes", name, std::process::id()));
+    fs::write(&path, rom).unwrap();
+    path
+}
+
+#[test]
+fn vrc6_prg_banking() {
+    let path = build_rom("vrc6_prg", 24, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x03);
+    nes.write_byte(0xC000, 0x05);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xA000), 7);
+    assert_eq!(nes.read_byte(0xC000), 5);
+    assert_eq!(nes.read_byte(0xE000), 15);
+}
+
+#[test]
+fn vrc6_cycle_irq() {
+    let path = build_rom("vrc6_irq", 26, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xF000, 0xFD);
+    nes.write_byte(0xF002, 0x06);
+    for _ in 0..2 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    // VRC6b swaps A0 and A1, so the acknowledge register appears at $F001.
+    nes.write_byte(0xF001, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn mixer_keeps_the_last_second() {
+    let mut mixer = mixer::new();
+    for index in 0..4_000_000 {
+        mixer.push(index as f32);
+    }
+    let mut buffer = vec![];
+    mixer.drain(&mut buffer);
+    assert_eq!(buffer.len(), mixer::MAX_SAMPLES);
+    assert!(mixer.samples.is_empty());
+}
+
+#[test]
+fn vrc7_key_on_produces_sound() {
+    let path = build_rom("vrc7_fm", 85, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    let registers = [(0x10, 0xAC), (0x30, 0x30), (0x20, 0x1C)];
+    for (register, value) in registers {
+        nes.write_byte(0x9010, register);
+        nes.write_byte(0x9030, value);
+    }
+    let mut peak : f32 = 0.0;
+    for _ in 0..36 * 400 {
+        nes.cart.cpu_tick();
+        peak = peak.max(nes.cart.audio_output().abs());
+    }
+    assert!(peak > 0.01);
+}
+
+#[test]
+fn n163_irq_and_internal_ram() {
+    let path = build_rom("n163", 19, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xF800, 0x80 | 0x10);
+    nes.write_byte(0x4800, 0xAB);
+    nes.write_byte(0x4800, 0xCD);
+    nes.write_byte(0xF800, 0x80 | 0x10);
+    assert_eq!(nes.read_byte(0x4800), 0xAB);
+    assert_eq!(nes.read_byte(0x4800), 0xCD);
+
+    nes.write_byte(0x5000, 0xFD);
+    nes.write_byte(0x5800, 0xFF);
+    nes.cart.cpu_tick();
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    nes.write_byte(0x5800, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn n163_ciram_pattern_banks() {
+    let path = build_rom("n163_ciram", 19, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Nametables start out on CIRAM pages 0, 1, 0, 1.
+    ppu::Bus::write_byte(&mut nes, 0x2405, 0x77);
+    ppu::Bus::write_byte(&mut nes, 0x2010, 0x55);
+    nes.write_byte(0x8000, 0xE1);
+    nes.write_byte(0xB800, 0xE0);
+    nes.write_byte(0xE800, 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0005), 0x77);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x1C10), 0x55);
+    ppu::Bus::write_byte(&mut nes, 0x0006, 0x66);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2406), 0x66);
+
+    // Bit 6 gives the low pattern table back to CHR ROM, where $E1 wraps to page 1.
+    nes.write_byte(0xE800, 0x40);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0005), 1);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x1C10), 0x55);
+    nes.write_byte(0xE800, 0xC0);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x1C10), 0);
+}
+
+#[test]
+fn fme7_prg_ram_and_irq() {
+    let path = build_rom("fme7", 69, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x08);
+    nes.write_byte(0xA000, 0x03);
+    assert_eq!(nes.read_byte(0x6000), 3);
+    nes.write_byte(0xA000, 0xC0);
+    nes.write_byte(0x6000, 0x5A);
+    assert_eq!(nes.read_byte(0x6000), 0x5A);
+
+    nes.write_byte(0x8000, 0x0E);
+    nes.write_byte(0xA000, 0x01);
+    nes.write_byte(0x8000, 0x0F);
+    nes.write_byte(0xA000, 0x00);
+    nes.write_byte(0x8000, 0x0D);
+    nes.write_byte(0xA000, 0x81);
+    nes.cart.cpu_tick();
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+}
+
+// Marks an image written by build_rom as NES 2.0 with the given submapper.
+fn set_submapper(path : &PathBuf, submapper : u8) {
+    let mut rom = fs::read(path).unwrap();
+    rom[7] |= 0x08;
+    rom[8] = submapper << 4;
+    fs::write(path, rom).unwrap();
+}
+
+#[test]
+fn mmc3_banking_and_scanline_irq() {
+    let path = build_rom("mmc3", 4, 8, 2);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x06);
+    nes.write_byte(0x8001, 0x03);
+    assert_eq!(nes.read_byte(0x8000), 3);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    nes.write_byte(0x8000, 0x46);
+    assert_eq!(nes.read_byte(0x8000), 14);
+    assert_eq!(nes.read_byte(0xC000), 3);
+
+    nes.write_byte(0xC000, 0x02);
+    nes.write_byte(0xC001, 0x00);
+    nes.write_byte(0xE001, 0x00);
+    for _ in 0..2 {
+        nes.cart.scanline();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.scanline();
+    assert!(nes.cart.irq());
+    nes.write_byte(0xE000, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn mmc6_ram_protection() {
+    let path = build_rom("mmc6", 4, 8, 2);
+    set_submapper(&path, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x20);
+    // Low half readable and writable, high half read-only.
+    nes.write_byte(0xA001, 0x30 | 0x80);
+    nes.write_byte(0x7000, 0x12);
+    nes.write_byte(0x7200, 0x34);
+    assert_eq!(nes.read_byte(0x7000), 0x12);
+    assert_eq!(nes.read_byte(0x7400), 0x12);
+    assert_eq!(nes.read_byte(0x7200), 0x00);
+}
+
+#[test]
+fn txsrom_chr_a17_mirroring() {
+    let path = build_rom("txsrom", 118, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // 2 KB CHR mode: R0 covers $2000/$2400 and R1 $2800/$2C00, with bit 7 picking the CIRAM page.
+    nes.write_byte(0x8000, 0x00);
+    nes.write_byte(0x8001, 0x80);
+    nes.write_byte(0x8000, 0x01);
+    nes.write_byte(0x8001, 0x00);
+    ppu::Bus::write_byte(&mut nes, 0x2000, 0x11);
+    ppu::Bus::write_byte(&mut nes, 0x2800, 0x22);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2400), 0x11);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2C00), 0x22);
+    nes.write_byte(0x8000, 0x00);
+    nes.write_byte(0x8001, 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x22);
+
+    // With the CHR halves swapped the nametables follow the 1 KB registers R2-R5 instead.
+    nes.write_byte(0x8000, 0x84);
+    nes.write_byte(0x8001, 0x80);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x22);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2800), 0x11);
+    // The MMC3 mirroring register has no effect.
+    nes.write_byte(0xA000, 0x01);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2800), 0x11);
+}
+
+#[test]
+fn mmc3_four_screen_nametables() {
+    let path = build_rom("mmc3_four_screen", 4, 8, 2);
+    let mut rom = fs::read(&path).unwrap();
+    rom[6] |= 0x08;
+    fs::write(&path, rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // CIRAM holds the first two nametables and 2 KB of RAM on the board the other two.
+    for (index, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+        ppu::Bus::write_byte(&mut nes, address, index as u8 + 1);
+    }
+    for (index, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+        assert_eq!(ppu::Bus::read_byte(&mut nes, address), index as u8 + 1);
+    }
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x3800), 3);
+    nes.write_byte(0xA000, 0x01);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2400), 2);
+}
+
+#[test]
+fn tqrom_chr_ram_select() {
+    let path = build_rom("tqrom", 119, 8, 2);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x02);
+    nes.write_byte(0x8001, 0x41);
+    nes.cart.ppu_write(0x1000, 0x5A);
+    assert_eq!(nes.cart.ppu_read(0x1000), 0x5A);
+    nes.write_byte(0x8001, 0x01);
+    assert_eq!(nes.cart.ppu_read(0x1000), 1);
+}
+
+#[test]
+fn namco108_chr_a16_split_and_mirroring() {
+    let path = build_rom("namco154", 154, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x00);
+    nes.write_byte(0x8001, 0x44);
+    nes.write_byte(0x8000, 0x02);
+    nes.write_byte(0x8001, 0x05);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x04);
+    assert_eq!(nes.cart.ppu_read(0x1000), 0x45);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenLower);
+    nes.write_byte(0xC000, 0x40);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenUpper);
+}
+
+// Writes one of the eight Namco 108 bank registers.
+fn namco108_write(nes : &mut bus::Bus, register : u8, value : u8) {
+    nes.write_byte(0x8000, register);
+    nes.write_byte(0x8001, value);
+}
+
+#[test]
+fn namco108_banking() {
+    let path = build_rom("namco108", 206, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Only 4 bits of PRG and 6 bits of CHR bank, with the last two PRG banks fixed.
+    namco108_write(&mut nes, 6, 0x13);
+    namco108_write(&mut nes, 7, 0x05);
+    assert_eq!(nes.read_byte(0x8000), 3);
+    assert_eq!(nes.read_byte(0xA000), 5);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.read_byte(0xE000), 15);
+    namco108_write(&mut nes, 0, 0x0B);
+    namco108_write(&mut nes, 2, 0x61);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x0A);
+    assert_eq!(nes.cart.ppu_read(0x0400), 0x0B);
+    assert_eq!(nes.cart.ppu_read(0x1000), 0x21);
+    // No mirroring control, so the header decides.
+    nes.write_byte(0xA000, 0x01);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn namco3446_2k_chr_banks() {
+    let path = build_rom("namco3446", 76, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // R2-R5 pick 2 KB banks, and R0/R1 do nothing.
+    namco108_write(&mut nes, 2, 0x03);
+    namco108_write(&mut nes, 5, 0x10);
+    namco108_write(&mut nes, 0, 0x08);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x06);
+    assert_eq!(nes.cart.ppu_read(0x0400), 0x07);
+    assert_eq!(nes.cart.ppu_read(0x1800), 0x20);
+    assert_eq!(nes.cart.ppu_read(0x1C00), 0x21);
+}
+
+#[test]
+fn namco3433_chr_a16_split() {
+    let path = build_rom("namco3433", 88, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // The low pattern table sees the first 64 KB of CHR and the high one the second.
+    namco108_write(&mut nes, 0, 0x22);
+    namco108_write(&mut nes, 2, 0x05);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x22);
+    assert_eq!(nes.cart.ppu_read(0x1000), 0x45);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn namco3425_nametable_control() {
+    let path = build_rom("namco3425", 95, 8, 4);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Bit 5 of R0 picks the CIRAM page of $2000/$2400, and bit 5 of R1 that of $2800/$2C00.
+    ppu::Bus::write_byte(&mut nes, 0x2800, 0x22);
+    namco108_write(&mut nes, 0, 0x20);
+    ppu::Bus::write_byte(&mut nes, 0x2000, 0x11);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2400), 0x11);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2C00), 0x22);
+    namco108_write(&mut nes, 1, 0x20);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2800), 0x11);
+    namco108_write(&mut nes, 0, 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x22);
+
+    // The same bit is not part of the CHR bank.
+    namco108_write(&mut nes, 2, 0x25);
+    assert_eq!(nes.cart.ppu_read(0x1000), 0x05);
+}
+
+// Drives the LZ93D50 EEPROM lines through register $D: bit 5 is SCL, bit 6 is SDA.
+fn i2c(nes : &mut bus::Bus, scl : bool, sda : bool) {
+    nes.write_byte(0x800D, ((scl as u8) << 5) | ((sda as u8) << 6) | 0x80);
+}
+
+fn i2c_send(nes : &mut bus::Bus, byte : u8) -> bool {
+    for bit in (0..8).rev() {
+        let sda = (byte >> bit) & 0x01 > 0;
+        i2c(nes, false, sda);
+        i2c(nes, true, sda);
+    }
+    i2c(nes, false, true);
+    i2c(nes, true, true);
+    let ack = nes.read_byte(0x6000) & 0x10 == 0;
+    i2c(nes, false, true);
+    ack
+}
+
+fn i2c_start(nes : &mut bus::Bus) {
+    i2c(nes, false, true);
+    i2c(nes, true, true);
+    i2c(nes, true, false);
+}
+
+#[test]
+fn lz93d50_eeprom_write_and_read() {
+    let path = build_rom("lz93d50", 16, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    i2c_start(&mut nes);
+    assert!(i2c_send(&mut nes, 0xA0));
+    assert!(i2c_send(&mut nes, 0x10));
+    assert!(i2c_send(&mut nes, 0x5C));
+    i2c(&mut nes, false, false);
+    i2c(&mut nes, true, false);
+    i2c(&mut nes, true, true);
+
+    i2c_start(&mut nes);
+    assert!(i2c_send(&mut nes, 0xA0));
+    assert!(i2c_send(&mut nes, 0x10));
+    i2c_start(&mut nes);
+    assert!(i2c_send(&mut nes, 0xA1));
+    let mut byte = 0;
+    for _ in 0..8 {
+        i2c(&mut nes, false, true);
+        i2c(&mut nes, true, true);
+        byte = (byte << 1) | ((nes.read_byte(0x6000) >> 4) & 0x01);
+    }
+    assert_eq!(byte, 0x5C);
+    assert_eq!(nes.cart.save_data().unwrap()[0x10], 0x5C);
+}
+
+#[test]
+fn x24c01_eeprom_write_and_read() {
+    let path = build_rom("x24c01", 159, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // No device select: the word address carries the R/W bit in bit 7, and bytes go LSB first.
+    i2c_start(&mut nes);
+    assert!(i2c_send(&mut nes, 0x10u8.reverse_bits()));
+    assert!(i2c_send(&mut nes, 0x5Cu8.reverse_bits()));
+    i2c(&mut nes, false, false);
+    i2c(&mut nes, true, false);
+    i2c(&mut nes, true, true);
+
+    i2c_start(&mut nes);
+    assert!(i2c_send(&mut nes, 0x90u8.reverse_bits()));
+    let mut byte = 0;
+    for bit in 0..8 {
+        i2c(&mut nes, false, true);
+        i2c(&mut nes, true, true);
+        byte |= ((nes.read_byte(0x6000) >> 4) & 0x01) << bit;
+    }
+    assert_eq!(byte, 0x5C);
+    assert_eq!(nes.cart.save_data().unwrap().len(), 0x80);
+    assert_eq!(nes.cart.save_data().unwrap()[0x10], 0x5C);
+}
+
+#[test]
+fn lz93d50_sram_and_outer_prg_bank() {
+    let path = build_rom("lz93d50_sram", 153, 32, 0);
+    let mut rom = fs::read(&path).unwrap();
+    rom[6] |= 0x02;
+    fs::write(&path, rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8008, 0x03);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xC000), 30);
+    // Bit 0 of any CHR register selects the upper 256 KB for both windows.
+    nes.write_byte(0x8000, 0x01);
+    assert_eq!(nes.read_byte(0x8000), 38);
+    assert_eq!(nes.read_byte(0xC000), 62);
+    nes.write_byte(0x8000, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 6);
+
+    // Bit 5 of register $D enables the SRAM; while disabled it reads 0 and ignores writes.
+    nes.write_byte(0x6000, 0x11);
+    assert_eq!(nes.read_byte(0x6000), 0);
+    nes.write_byte(0x800D, 0x20);
+    assert_eq!(nes.read_byte(0x6000), 0);
+    nes.write_byte(0x6000, 0x5A);
+    assert_eq!(nes.read_byte(0x6000), 0x5A);
+    nes.write_byte(0x800D, 0x00);
+    assert_eq!(nes.read_byte(0x6000), 0);
+    assert_eq!(nes.cart.save_data().unwrap().len(), 0x2000);
+    assert_eq!(nes.cart.save_data().unwrap()[0], 0x5A);
+}
+
+#[test]
+fn datach_barcode_and_irq() {
+    let path = build_rom("datach", 157, 8, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert!(nes.cart.scan_barcode("123").is_err());
+    nes.cart.scan_barcode("4902425123454").unwrap();
+    let mut bars = 0;
+    let mut previous = 0x08;
+    for _ in 0..200_000 {
+        nes.cart.cpu_tick();
+        let level = nes.read_byte(0x6000) & 0x08;
+        if level == 0 && previous != 0 {
+            bars += 1;
+        }
+        previous = level;
+    }
+    // EAN-13 has 30 bars: 2 per digit plus 3 guard patterns of 2 bars each.
+    assert_eq!(bars, 30);
+
+    nes.write_byte(0x800B, 0x02);
+    nes.write_byte(0x800C, 0x00);
+    nes.write_byte(0x800A, 0x01);
+    for _ in 0..2 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+}
+
+#[test]
+fn unrom512_self_flash() {
+    let path = build_rom("unrom512", 30, 8, 0);
+    let mut rom = fs::read(&path).unwrap();
+    rom[6] |= 0x02;
+    fs::write(&path, rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Programming can only clear bits, so the result is the old byte AND the new one.
+    let program = [(1, 0x9555, 0xAA), (0, 0xAAAA, 0x55), (1, 0x9555, 0xA0), (2, 0x8000, 0x0F)];
+    for (bank, address, byte) in program {
+        nes.write_byte(0xC000, bank);
+        nes.write_byte(address, byte);
+    }
+    nes.write_byte(0xC000, 2);
+    assert_eq!(nes.read_byte(0x8000), 4 & 0x0F);
+    assert_eq!(nes.cart.save_data().unwrap()[0x8000], 4 & 0x0F);
+
+    // Sector erase brings the 4 KB sector back to $FF.
+    let erase = [(1, 0x9555, 0xAA), (0, 0xAAAA, 0x55), (1, 0x9555, 0x80), (1, 0x9555, 0xAA), (0, 0xAAAA, 0x55), (2, 0x8000, 0x30)];
+    for (bank, address, byte) in erase {
+        nes.write_byte(0xC000, bank);
+        nes.write_byte(address, byte);
+    }
+    nes.write_byte(0xC000, 2);
+    assert_eq!(nes.read_byte(0x8FFF), 0xFF);
+    assert_eq!(nes.read_byte(0x9000), 4);
+}
+
+#[test]
+fn unrom512_four_screen_nametables() {
+    let path = build_rom("unrom512_four_screen", 30, 8, 0);
+    let mut rom = fs::read(&path).unwrap();
+    rom[6] |= 0x09;
+    fs::write(&path, rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // The four nametables live in the last 8 KB of CHR RAM, and $3000-$3EFF mirrors them.
+    ppu::Bus::write_byte(&mut nes, 0x2400, 0x11);
+    ppu::Bus::write_byte(&mut nes, 0x3C00, 0x22);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x3400), 0x11);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2C00), 0x22);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x00);
+    nes.write_byte(0xC000, 0x60);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0400), 0x11);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0C00), 0x22);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x1C00), 0x00);
+}
+
+#[test]
+fn gtrom_banks_nametables_and_leds() {
+    let path = build_rom("gtrom", 111, 8, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(nes.cart.leds(), 0x03);
+    nes.write_byte(0x5000, 0x02);
+    assert_eq!(nes.read_byte(0x8000), 8);
+    assert_eq!(nes.read_byte(0xE000), 11);
+    nes.write_byte(0x7000, 0xC1);
+    assert_eq!(nes.read_byte(0x8000), 4);
+    assert_eq!(nes.cart.leds(), 0x00);
+
+    // Two CHR RAM pages, picked by bit 4.
+    ppu::Bus::write_byte(&mut nes, 0x0010, 0xAB);
+    nes.write_byte(0x5000, 0x10);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0010), 0x00);
+    nes.write_byte(0x5000, 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x0010), 0xAB);
+
+    // Four-screen nametables in two pages, picked by bit 5.
+    ppu::Bus::write_byte(&mut nes, 0x2000, 0x33);
+    ppu::Bus::write_byte(&mut nes, 0x2C00, 0x44);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x3000), 0x33);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2400), 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x3C00), 0x44);
+    nes.write_byte(0x5000, 0x20);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x00);
+    ppu::Bus::write_byte(&mut nes, 0x2000, 0x55);
+    nes.write_byte(0x5000, 0x00);
+    assert_eq!(ppu::Bus::read_byte(&mut nes, 0x2000), 0x33);
+}
+
+#[test]
+fn action53_outer_and_inner_banks() {
+    let path = build_rom("action53", 28, 8, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Power-on: 32 KB mode on the last outer bank.
+    assert_eq!(nes.read_byte(0x8000), 12);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    // Outer bank 1, 64 KB UNROM-style game: $C000 fixed to the end of the outer bank.
+    nes.write_byte(0x5000, 0x81);
+    nes.write_byte(0x8000, 0x01);
+    nes.write_byte(0x5000, 0x80);
+    nes.write_byte(0x8000, 0x1E);
+    nes.write_byte(0x5000, 0x01);
+    nes.write_byte(0x8000, 0x02);
+    assert_eq!(nes.read_byte(0x8000), 4);
+    assert_eq!(nes.read_byte(0xC000), 6);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn h3001_cycle_irq() {
+    let path = build_rom("h3001", 65, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xC000, 0x03);
+    assert_eq!(nes.read_byte(0xC000), 3);
+    nes.write_byte(0x9005, 0x00);
+    nes.write_byte(0x9006, 0x03);
+    nes.write_byte(0x9004, 0x00);
+    nes.write_byte(0x9003, 0x80);
+    for _ in 0..2 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    nes.write_byte(0x9003, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn g101_banking_and_mirroring() {
+    let path = build_rom("g101", 32, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+
+    nes.write_byte(0x8000, 0x03);
+    nes.write_byte(0xA000, 0x05);
+    nes.write_byte(0xB003, 0x21);
+    assert_eq!(nes.read_byte(0x8000), 3);
+    assert_eq!(nes.read_byte(0xA000), 5);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.read_byte(0xE000), 15);
+    assert_eq!(nes.cart.ppu_read(0x0C00), 0x21);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+    // Bit 1 swaps $8000 and $C000, bit 0 is the mirroring.
+    nes.write_byte(0x9000, 0x03);
+    assert_eq!(nes.read_byte(0x8000), 14);
+    assert_eq!(nes.read_byte(0xC000), 3);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+
+    // Major League ignores both bits and is wired for one-screen mirroring.
+    set_submapper(&path, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    nes.write_byte(0x8000, 0x03);
+    nes.write_byte(0x9000, 0x03);
+    assert_eq!(nes.read_byte(0x8000), 3);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenLower);
+}
+
+#[test]
+fn tc0190_banking_and_mirroring() {
+    let path = build_rom("tc0190", 33, 8, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // The first PRG register also holds the mirroring in bit 6.
+    nes.write_byte(0x8000, 0x43);
+    nes.write_byte(0x8001, 0x05);
+    assert_eq!(nes.read_byte(0x8000), 3);
+    assert_eq!(nes.read_byte(0xA000), 5);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.write_byte(0x8000, 0x03);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+
+    // Two 2 KB banks, then four 1 KB banks.
+    nes.write_byte(0x8002, 0x05);
+    nes.write_byte(0x8003, 0x06);
+    nes.write_byte(0xA001, 0x21);
+    assert_eq!(nes.cart.ppu_read(0x0000), 10);
+    assert_eq!(nes.cart.ppu_read(0x0400), 11);
+    assert_eq!(nes.cart.ppu_read(0x0800), 12);
+    assert_eq!(nes.cart.ppu_read(0x1400), 0x21);
+}
+
+#[test]
+fn mapper78_mirroring_by_board() {
+    // Cosmo Carrier (JF-16) switches between one-screen pages.
+    let path = build_rom("m78", 78, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8000, 0x35);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.ppu_read(0x0000), 24);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenLower);
+    nes.write_byte(0x8000, 0x3D);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenUpper);
+
+    // Holy Diver switches between the two arrangements, picked by the four-screen bit in iNES
+    // dumps or by submapper 3.
+    let mut rom = fs::read(&path).unwrap();
+    rom[6] |= 0x08;
+    fs::write(&path, rom).unwrap();
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8000, 0x00);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.write_byte(0x8000, 0x08);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+
+    // Submapper 1 is Cosmo Carrier whatever the four-screen bit says.
+    set_submapper(&path, 1);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8000, 0x08);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenUpper);
+    set_submapper(&path, 3);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    nes.write_byte(0x8000, 0x08);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn tc0690_scanline_irq() {
+    let path = build_rom("tc0690", 48, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xC000, 0xFF ^ 0x01);
+    nes.write_byte(0xC001, 0x00);
+    nes.write_byte(0xC002, 0x00);
+    nes.cart.scanline();
+    assert!(!nes.cart.irq());
+    nes.cart.scanline();
+    assert!(nes.cart.irq());
+    nes.write_byte(0xC003, 0x00);
+    assert!(!nes.cart.irq());
+    nes.write_byte(0xE000, 0x40);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn ss88006_narrow_irq_counter() {
+    let path = build_rom("ss88006", 18, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Only the low nibble counts in 4-bit mode, so $12 fires after 3 cycles.
+    nes.write_byte(0xE000, 0x02);
+    nes.write_byte(0xE001, 0x01);
+    nes.write_byte(0xF000, 0x00);
+    nes.write_byte(0xF001, 0x09);
+    for _ in 0..2 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+
+    nes.write_byte(0x8000, 0x05);
+    nes.write_byte(0x8001, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 5);
+}
+
+#[test]
+fn x1005_internal_ram_latch() {
+    let path = build_rom("x1005", 80, 8, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x7F00, 0x11);
+    nes.write_byte(0x7EF8, 0xA3);
+    assert_eq!(nes.read_byte(0x7F00), 0x00);
+    nes.write_byte(0x7F00, 0x22);
+    assert_eq!(nes.read_byte(0x7F80), 0x22);
+    nes.write_byte(0x7EF8, 0x00);
+    assert_eq!(nes.read_byte(0x7F00), 0x00);
+    nes.write_byte(0x7EFC, 0x06);
+    assert_eq!(nes.read_byte(0xA000), 6);
+}
+
+#[test]
+fn sunsoft4_rom_nametables() {
+    let path = build_rom("sunsoft4", 68, 8, 32);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(nes.cart.nametable(0x2000), Nametable::Ciram(0));
+    // Vertical arrangement, $2000 from NT register 0 and $2800 from NT register 1.
+    nes.write_byte(0xC000, 0x05);
+    nes.write_byte(0xD000, 0x06);
+    nes.write_byte(0xE000, 0x11);
+    assert_eq!(nes.cart.nametable(0x2000), Nametable::Cartridge);
+    assert_eq!(nes.cart.nt_read(0x2000), 0x85);
+    assert_eq!(nes.cart.nt_read(0x2400), 0x85);
+    assert_eq!(nes.cart.nt_read(0x2800), 0x86);
+}
+
+#[test]
+fn rambo1_banking_and_cycle_irq() {
+    let path = build_rom("rambo1", 64, 8, 2);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000, 0x0F);
+    nes.write_byte(0x8001, 0x09);
+    assert_eq!(nes.read_byte(0xC000), 9);
+    nes.write_byte(0x8000, 0x28);
+    nes.write_byte(0x8001, 0x0B);
+    assert_eq!(nes.cart.ppu_read(0x0400), 11);
+
+    // Latch 2 in CPU cycle mode: the counter is clocked every 4 cycles and reaches zero on the
+    // fourth clock after a reload, then the IRQ is asserted one cycle later.
+    nes.write_byte(0xC000, 0x02);
+    nes.write_byte(0xC001, 0x01);
+    nes.write_byte(0xE001, 0x00);
+    for _ in 0..16 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    nes.write_byte(0xE000, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn rambo1_scanline_irq_delay() {
+    let path = build_rom("rambo1_scanline", 64, 8, 2);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Latch 0 in scanline mode: the counter reaches zero on the first scanline after a reload and
+    // the IRQ is asserted two CPU cycles later.
+    nes.write_byte(0xC000, 0x00);
+    nes.write_byte(0xC001, 0x00);
+    nes.write_byte(0xE001, 0x00);
+    nes.cart.scanline();
+    nes.cart.cpu_tick();
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    nes.write_byte(0xE000, 0x00);
+    assert!(!nes.cart.irq());
+
+    // Acknowledging during the delay drops the IRQ before it is ever asserted.
+    nes.write_byte(0xE001, 0x00);
+    nes.cart.scanline();
+    nes.cart.cpu_tick();
+    nes.write_byte(0xE000, 0x00);
+    nes.write_byte(0xE001, 0x00);
+    nes.cart.cpu_tick();
+    nes.cart.cpu_tick();
+    assert!(!nes.cart.irq());
+}
+
+// Loads a value into an MMC1 register through the serial port.
+fn mmc1_write(nes : &mut bus::Bus, address : u16, value : u8) {
+    for bit in 0..5 {
+        nes.write_byte(address, (value >> bit) & 0x01);
+    }
+}
+
+#[test]
+fn nwc_init_sequence_and_dip_switches() {
+    let path = build_rom("nwc", 105, 16, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(nes.cart.dip_switches(), Some(0x04));
+    nes.cart.set_dip_switches(0x0F).unwrap();
+    assert_eq!(nes.cart.dip_switches(), Some(0x0F));
+
+    // Locked to the first 32 KB until CHR register 0 bit 4 goes low, then high.
+    mmc1_write(&mut nes, 0xA000, 0x02);
+    assert_eq!(nes.read_byte(0x8000), 0);
+    mmc1_write(&mut nes, 0xA000, 0x12);
+    assert_eq!(nes.read_byte(0x8000), 4);
+    assert_eq!(nes.read_byte(0xC000), 6);
+
+    // Second chip with MMC1 banking, $C000 fixed to its last bank.
+    mmc1_write(&mut nes, 0xA000, 0x18);
+    mmc1_write(&mut nes, 0xE000, 0x02);
+    assert_eq!(nes.read_byte(0x8000), 20);
+    assert_eq!(nes.read_byte(0xC000), 30);
+}
+
+#[test]
+fn dip_switches_require_board_support() {
+    let path = build_rom("nodip", 2, 8, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(nes.cart.dip_switches(), None);
+    assert!(nes.cart.set_dip_switches(0x01).is_err());
+}
+
+#[test]
+fn multicart_soft_reset_returns_to_menu() {
+    let path = build_rom("m225", 225, 16, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x5800, 0x1A);
+    nes.write_byte(0x8000 | 0x1000 | (3 << 6), 0x00);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xC000), 6);
+
+    nes.soft_reset();
+    assert_eq!(nes.read_byte(0x8000), 0);
+    assert_eq!(nes.read_byte(0xC000), 2);
+    assert_eq!(nes.read_byte(0x5800), 0x0A);
+
+    nes.power_on();
+    assert_eq!(nes.read_byte(0x5800), 0x00);
+    nes.write_byte(0x5800, 0x1A);
+    nes.reset();
+    assert_eq!(nes.read_byte(0x5800), 0x00);
+}
+
+#[test]
+fn contra_function_16_prg_modes() {
+    let path = build_rom("m15", 15, 16, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // Mode 0: 32 KB, with CHR RAM write-protected.
+    nes.write_byte(0x8000, 0x03);
+    assert_eq!(nes.read_byte(0x8000), 4);
+    assert_eq!(nes.read_byte(0xC000), 6);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+    nes.cart.ppu_write(0x0000, 0xAA);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x00);
+
+    // Mode 1: UNROM-like, with bank | 7 fixed at $C000.
+    nes.write_byte(0x8001, 0x42);
+    assert_eq!(nes.read_byte(0x8000), 4);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.cart.ppu_write(0x0000, 0xAA);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0xAA);
+
+    // Mode 2: one 8 KB bank everywhere, bit 7 picking the half.
+    nes.write_byte(0x8002, 0x83);
+    assert_eq!(nes.read_byte(0x8000), 7);
+    assert_eq!(nes.read_byte(0xE000), 7);
+    nes.write_byte(0x6000, 0x5A);
+    assert_eq!(nes.read_byte(0x6000), 0x5A);
+}
+
+#[test]
+fn gk192_multicart() {
+    let path = build_rom("m58", 58, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0x8000 | 0x80 | 0x40 | (3 << 3) | 0x05, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 10);
+    assert_eq!(nes.cart.ppu_read(0x0000), 24);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.write_byte(0x8006, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 12);
+    assert_eq!(nes.read_byte(0xE000), 15);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn multicart_20_in_1() {
+    let path = build_rom("m61", 61, 16, 16);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // 16 KB mode takes its low bank bit from A5.
+    nes.write_byte(0x8000 | (5 << 8) | 0x80 | 0x20 | 0x10 | 0x03, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 14);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.ppu_read(0x0000), 40);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.write_byte(0x8002, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 8);
+    assert_eq!(nes.read_byte(0xE000), 11);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn multicarts_200_to_204() {
+    let path = build_rom("m200", 200, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x800D, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 10);
+    assert_eq!(nes.cart.ppu_read(0x0000), 40);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    fs::remove_file(path).unwrap();
+
+    let path = build_rom("m201", 201, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8002, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 8);
+    assert_eq!(nes.read_byte(0xE000), 11);
+    assert_eq!(nes.cart.ppu_read(0x0000), 16);
+    fs::remove_file(path).unwrap();
+
+    // 202 switches to 32 KB when A0 and A3 are both set.
+    let path = build_rom("m202", 202, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8006, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xC000), 6);
+    assert_eq!(nes.cart.ppu_read(0x0000), 24);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+    nes.write_byte(0x8009, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 8);
+    assert_eq!(nes.read_byte(0xC000), 10);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    fs::remove_file(path).unwrap();
+
+    // 203 latches the data rather than the address.
+    let path = build_rom("m203", 203, 8, 4);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8000, (5 << 2) | 0x02);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 10);
+    assert_eq!(nes.cart.ppu_read(0x0000), 16);
+    fs::remove_file(path).unwrap();
+
+    // 204 uses 32 KB for its last outer bank only.
+    let path = build_rom("m204", 204, 8, 8);
+    let mut nes = bus::load(&path).unwrap();
+    nes.write_byte(0x8013, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xC000), 6);
+    assert_eq!(nes.cart.ppu_read(0x0000), 24);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.write_byte(0x8006, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 12);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.ppu_read(0x0000), 48);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn multicart_1200_in_1() {
+    let path = build_rom("m227", 227, 64, 0);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // NROM mode protects CHR RAM.
+    nes.write_byte(0x8000 | 0x80 | (5 << 2) | 0x02, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 10);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Vertical);
+    nes.cart.ppu_write(0x0000, 0xAA);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0x00);
+
+    // UNROM modes fix the last or the first bank of the 128 KB block at $C000.
+    nes.write_byte(0x8000 | 0x200 | (5 << 2), 0x00);
+    assert_eq!(nes.read_byte(0x8000), 10);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    nes.cart.ppu_write(0x0000, 0xAA);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0xAA);
+    nes.write_byte(0x8000 | (5 << 2), 0x00);
+    assert_eq!(nes.read_byte(0xC000), 0);
+
+    // A8 is the high bank bit.
+    nes.write_byte(0x8000 | 0x100 | 0x80 | (1 << 2) | 0x01, 0x00);
+    assert_eq!(nes.read_byte(0x8000), 64);
+    assert_eq!(nes.read_byte(0xE000), 67);
+}
+
+#[test]
+fn reset_based_multicart_selects_next_game() {
+    let path = build_rom("m60", 60, 4, 4);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(nes.read_byte(0xC000), 0);
+    nes.soft_reset();
+    assert_eq!(nes.read_byte(0xC000), 2);
+    nes.soft_reset();
+    nes.soft_reset();
+    nes.soft_reset();
+    assert_eq!(nes.read_byte(0xC000), 0);
+}
+
+#[test]
+fn jy_company_banking_multiplier_and_irq() {
+    let path = build_rom("jy", 90, 32, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xD000, 0x02);
+    nes.write_byte(0x8000, 0x05);
+    nes.write_byte(0x8001, 0x06);
+    assert_eq!(nes.read_byte(0x8000), 5);
+    assert_eq!(nes.read_byte(0xA000), 6);
+    assert_eq!(nes.read_byte(0xE000), 63);
+    // Mode 3 reverses the bank bits.
+    nes.write_byte(0xD000, 0x03);
+    nes.write_byte(0x8000, 0x40);
+    assert_eq!(nes.read_byte(0x8000), 1);
+
+    nes.write_byte(0x5800, 12);
+    nes.write_byte(0x5801, 34);
+    assert_eq!(nes.read_byte(0x5800), 0x98);
+    assert_eq!(nes.read_byte(0x5801), 0x01);
+
+    // Count up on CPU cycles with the 3-bit prescaler.
+    nes.write_byte(0xC001, 0x44);
+    nes.write_byte(0xC005, 0xFE);
+    nes.write_byte(0xC003, 0x00);
+    for _ in 0..15 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    nes.write_byte(0xC002, 0x00);
+    assert!(!nes.cart.irq());
+}
+
+#[test]
+fn jy_company_rom_nametables() {
+    let path = build_rom("jy211", 211, 8, 32);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    nes.write_byte(0xB000, 0x01);
+    nes.write_byte(0xB001, 0x83);
+    assert_eq!(nes.cart.nametable(0x2000), Nametable::Ciram(1));
+    assert_eq!(nes.cart.nametable(0x2400), Nametable::Cartridge);
+    assert_eq!(nes.cart.nt_read(0x2400), 0x83);
+    // Bit 6 of $D000 takes every nametable from CHR ROM.
+    nes.write_byte(0xD000, 0x40);
+    assert_eq!(nes.cart.nametable(0x2000), Nametable::Cartridge);
+}
+
+#[test]
+fn jy_company_optional_rom_nametables() {
+    // Mapper 209 only uses the nametable registers once $D000 bit 5 is set, and mapper 90 never.
+    for (mapper, rom_nametables) in [(209, true), (90, false)] {
+        let path = build_rom("jy209", mapper, 8, 32);
+        let mut nes = bus::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        nes.write_byte(0xB000, 0x01);
+        nes.write_byte(0xB001, 0x83);
+        assert!(matches!(nes.cart.nametable(0x2400), Nametable::Ciram(_)));
+        nes.write_byte(0xD000, 0x20);
+        assert_eq!(nes.cart.nametable(0x2400) == Nametable::Cartridge, rom_nametables);
+        if rom_nametables {
+            assert_eq!(nes.cart.nametable(0x2000), Nametable::Ciram(1));
+            assert_eq!(nes.cart.nt_read(0x2400), 0x83);
+        }
+    }
+}
+
+const GENERIC_BOARD : &str = r#"
+name = "Test board"  # UNROM-like with CHR banking
+
+[register]
+mask = 0x8000
+match = 0x8000
+bus_conflicts = false
+
+[prg]
+bank_size = 16
+banks = ["select", "last"]
+mask = 0x07
+
+[chr]
+shift = 4
+mask = 0b11
+
+[mirroring]
+mode = "select"
+bit = 7
+values = ["one_screen_lower", "one_screen_upper"]
+"#;
+
+#[test]
+fn generic_discrete_board() {
+    let path = build_rom("generic", 255, 8, 4);
+    let mut nes = bus::load_with_board(&path, GENERIC_BOARD).unwrap();
+
+    nes.write_byte(0x8000, 0x93);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.read_byte(0xC000), 14);
+    assert_eq!(nes.cart.ppu_read(0x0000), 8);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenUpper);
+
+    // With bus conflicts the ROM byte at $E000 (15) masks the written value.
+    let description = GENERIC_BOARD.replace("bus_conflicts = false", "bus_conflicts = true");
+    let mut nes = bus::load_with_board(&path, &description).unwrap();
+    fs::remove_file(path).unwrap();
+    nes.write_byte(0xE000, 0x93);
+    assert_eq!(nes.read_byte(0x8000), 6);
+    assert_eq!(nes.cart.ppu_read(0x0000), 0);
+    assert_eq!(nes.cart.mirroring(), Mirroring::OneScreenLower);
+}
+
+#[test]
+fn generic_board_description_errors() {
+    let path = build_rom("generic_errors", 255, 2, 1);
+    let unknown_key = GENERIC_BOARD.replace("shift = 4", "shfit = 4");
+    let error = bus::load_with_board(&path, &unknown_key).err().unwrap();
+    assert!(error.to_string().contains("line 15: Unknown key chr.shfit"));
+    let bad_slots = GENERIC_BOARD.replace(r#"["select", "last"]"#, r#"["select"]"#);
+    assert!(bus::load_with_board(&path, &bad_slots).is_err());
+    let bad_shift = GENERIC_BOARD.replace("shift = 4", "shift = 16");
+    let error = bus::load_with_board(&path, &bad_shift).err().unwrap();
+    assert!(error.to_string().contains("line 15: chr.shift must be between 0 and 15"));
+    let negative_ram = GENERIC_BOARD.replace("mask = 0x07", "mask = 0x07\nram = -1");
+    let error = bus::load_with_board(&path, &negative_ram).err().unwrap();
+    assert!(error.to_string().contains("line 13: prg.ram must be between 0 and 32"));
+    let wide_mask = GENERIC_BOARD.replace("mask = 0x8000", "mask = 0x18000");
+    let error = bus::load_with_board(&path, &wide_mask).err().unwrap();
+    assert!(error.to_string().contains("line 5: register.mask must be between 0 and 65535"));
+    let bad_bit = GENERIC_BOARD.replace("bit = 7", "bit = 64");
+    let error = bus::load_with_board(&path, &bad_bit).err().unwrap();
+    assert!(error.to_string().contains("line 20: mirroring.bit must be between 0 and 15"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn generic_board_mirrors_small_roms() {
+    // NROM-128: 16 KB of PRG in the default 32 KB window, and in a single selected bank.
+    let path = build_rom("generic_nrom", 255, 1, 1);
+    let mut nes = bus::load_with_board(&path, "").unwrap();
+    assert_eq!(nes.read_byte(0x8000), 0);
+    assert_eq!(nes.read_byte(0xC000), 0);
+    assert_eq!(nes.read_byte(0xFFFF), 1);
+    let mut nes = bus::load_with_board(&path, "[prg]\nbanks = [\"select\"]\n").unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(nes.read_byte(0xE000), 1);
+}
+
+#[test]
+fn generic_board_mask_defaults_to_all_bits() {
+    // CNROM-like: no mask, so the whole latched value picks the CHR bank.
+    let path = build_rom("generic_mask", 255, 2, 4);
+    let mut nes = bus::load_with_board(&path, "[chr]\nbank_size = 8\n").unwrap();
+    fs::remove_file(path).unwrap();
+    nes.write_byte(0x8000, 0x03);
+    assert_eq!(nes.cart.ppu_read(0x0000), 24);
+}
+
+#[derive(Clone)]
+struct ConstantMapper;
+
+impl MapperT for ConstantMapper {
+    fn cpu_read(&mut self, _address : u16) -> u8 { 0x42 }
+    fn cpu_write(&mut self, _address : u16, _byte : u8) {}
+    fn ppu_read(&mut self, _address : u16) -> u8 { 0 }
+    fn ppu_write(&mut self, _address : u16, _byte : u8) {}
+    fn clone_self(&self) -> Box<dyn MapperT> { Box::new(self.clone()) }
+    fn reset(&mut self, _kind : Reset) {}
+}
+
+#[test]
+fn registered_mappers_are_used_and_listed() {
+    cartridge::register(250, None, "Constant board", |cartridge| {
+        cartridge.mapper = Mapper(Box::new(ConstantMapper));
+        Ok(())
+    });
+    let path = build_rom("registered", 250, 2, 1);
+    let mut nes = bus::load(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(nes.read_byte(0x8000), 0x42);
+
+    let mappers = cartridge::supported_mappers();
+    assert!(mappers.iter().any(|info| info.mapper == 250 && info.name == "Constant board"));
+    assert!(mappers.iter().any(|info| info.mapper == 4 && info.submapper == Some(1) && info.name == "MMC6"));
+}
+
+// Writes a single-sided .fds image holding one 4-byte file, and a BIOS whose bytes are their own
+// offset. Returns the disk and BIOS paths.
+fn build_fds(name : &str) -> (PathBuf, PathBuf) {
+    let mut side = vec![0x01];
+    side.extend_from_slice(b"*NINTENDO-HVC*");
+    side.resize(56, 0);
+    side.extend_from_slice(&[0x02, 0x01]);
+    side.extend_from_slice(&[0x03, 0x00, 0x00]);
+    side.extend_from_slice(b"TESTFILE");
+    side.extend_from_slice(&[0x00, 0x60, 0x04, 0x00, 0x00]);
+    side.extend_from_slice(&[0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+    side.resize(65500, 0);
+    let mut image = vec![0x46, 0x44, 0x53, 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    image.extend_from_slice(&side);
+    let bios : Vec<u8> = (0..0x2000).map(|offset| offset as u8).collect();
+
+    let disk_path = std::env::temp_dir().join(format!("coral_{}_{}.fds", name, std::process::id()));
+    let bios_path = std::env::temp_dir().join(format!("coral_{}_{}.rom", name, std::process::id()));
+    fs::write(&disk_path, image).unwrap();
+    fs::write(&bios_path, bios).unwrap();
+    (disk_path, bios_path)
+}
+
+// Clocks the drive until it raises the disk IRQ, then reads the byte it transferred.
+fn next_disk_byte(nes : &mut bus::Bus) -> u8 {
+    for _ in 0..1_000_000 {
+        nes.cart.cpu_tick();
+        if nes.cart.irq() {
+            return nes.read_byte(0x4031);
+        }
+    }
+    panic!("The drive never transferred a byte");
+}
+
+// Reads the next block the way the BIOS does: restart the search for the end of the gap, read
+// the block and skip its CRC.
+fn read_disk_block(nes : &mut bus::Bus, length : usize) -> Vec<u8> {
+    nes.write_byte(0x4025, 0x85);
+    for _ in 0..300 {
+        nes.cart.cpu_tick();
+    }
+    nes.write_byte(0x4025, 0xC5);
+    let block = (0..length).map(|_| next_disk_byte(nes)).collect();
+    next_disk_byte(nes);
+    next_disk_byte(nes);
+    block
+}
+
+#[test]
+fn fds_ram_adapter_timer_and_drive() {
+    let (disk_path, bios_path) = build_fds("fds_drive");
+    let mut nes = bus::load_fds(&disk_path, &bios_path).unwrap();
+    fs::remove_file(disk_path).unwrap();
+    fs::remove_file(bios_path).unwrap();
+
+    assert_eq!(nes.read_byte(0xFFFC), 0xFC);
+    nes.write_byte(0xD000, 0x42);
+    assert_eq!(nes.read_byte(0xD000), 0x42);
+
+    nes.write_byte(0x4023, 0x01);
+    nes.write_byte(0x4020, 0x10);
+    nes.write_byte(0x4021, 0x00);
+    nes.write_byte(0x4022, 0x02);
+    for _ in 0..16 {
+        nes.cart.cpu_tick();
+    }
+    assert!(!nes.cart.irq());
+    nes.cart.cpu_tick();
+    assert!(nes.cart.irq());
+    assert_eq!(nes.read_byte(0x4030) & 0x01, 0x01);
+    assert!(!nes.cart.irq());
+
+    // Motor on, read mode, look for the end of the gap and interrupt on every byte.
+    nes.write_byte(0x4025, 0xC5);
+    assert_eq!(next_disk_byte(&mut nes), 0x01);
+    assert_eq!(next_disk_byte(&mut nes), b'*');
+    assert_eq!(next_disk_byte(&mut nes), b'N');
+    assert_eq!(nes.read_byte(0x4032) & 0x03, 0x00);
+    assert_eq!(nes.cart.mirroring(), Mirroring::Horizontal);
+
+    nes.cart.insert_disk(None).unwrap();
+    nes.cart.cpu_tick();
+    assert_eq!(nes.read_byte(0x4032) & 0x01, 0x01);
+    assert!(nes.cart.insert_disk(Some(1)).is_err());
+    nes.cart.insert_disk(Some(0)).unwrap();
+    nes.cart.cpu_tick();
+    // A new disk reads as ejected for a while, so the BIOS notices the change.
+    assert_eq!(nes.read_byte(0x4032) & 0x01, 0x01);
+    for _ in 0..2_000_000 {
+        nes.cart.cpu_tick();
+    }
+    assert_eq!(nes.read_byte(0x4032) & 0x01, 0x00);
+}
+
+#[test]
+fn fds_saves_disk_changes_as_a_patch() {
+    let (disk_path, bios_path) = build_fds("fds_save");
+    let mut nes = bus::load_fds(&disk_path, &bios_path).unwrap();
+    let mut reloaded = bus::load_fds(&disk_path, &bios_path).unwrap();
+    fs::remove_file(disk_path).unwrap();
+    fs::remove_file(bios_path).unwrap();
+    assert!(nes.cart.save_data().is_none());
+
+    // Change the first byte of the file, which sits at offset 56 + 2 + 16 + 1 of the side.
+    let mut patch = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x00, 75, 0x00, 0x01, 0x12]);
+    patch.extend_from_slice(b"EOF");
+    nes.cart.load_save_data(&patch).unwrap();
+    assert_eq!(nes.cart.save_data(), Some(patch.clone()));
+
+    reloaded.cart.load_save_data(&nes.cart.save_data().unwrap()).unwrap();
+    reloaded.write_byte(0x4023, 0x01);
+    let blocks : Vec<Vec<u8>> = [56, 2, 16, 5].iter().map(|length| read_disk_block(&mut reloaded, *length)).collect();
+    assert_eq!(blocks[0][0], 0x01);
+    assert_eq!(blocks[3], [0x04, 0x12, 0xAD, 0xBE, 0xEF]);
+}
+
+#[test]
+fn fds_wavetable_audio() {
+    let (disk_path, bios_path) = build_fds("fds_audio");
+    let mut nes = bus::load_fds(&disk_path, &bios_path).unwrap();
+    fs::remove_file(disk_path).unwrap();
+    fs::remove_file(bios_path).unwrap();
+
+    // Sound registers are ignored until enabled through $4023.
+    nes.write_byte(0x4080, 0xA0);
+    assert_eq!(nes.read_byte(0x4090), 0);
+
+    nes.write_byte(0x4023, 0x02);
+    nes.write_byte(0x4089, 0x80);
+    for step in 0..64 {
+        nes.write_byte(0x4040 + step, if step < 32 { 63 } else { 0 });
+    }
+    assert_eq!(nes.read_byte(0x4040), 63);
+    nes.write_byte(0x4089, 0x00);
+    nes.write_byte(0x4080, 0xA0);
+    assert_eq!(nes.read_byte(0x4090), 32);
+    nes.write_byte(0x4082, 0xFF);
+    nes.write_byte(0x4083, 0x0F);
+
+    let mut peak : f32 = 0.0;
+    for _ in 0..4000 {
+        nes.cart.cpu_tick();
+        peak = peak.max(nes.cart.audio_output());
+    }
+    assert!(peak > 0.3);
+}
+
+// NSF whose INIT stores the track at $0300 and starts a VRC6 pulse, and whose PLAY counts its
+// calls at $0301. The second 4 KB bank starts with $5A.
+fn build_nsf(name : &str) -> PathBuf {
+    let mut nsf = b"NESM\x1A".to_vec();
+    nsf.extend_from_slice(&[0x01, 3, 2, 0x00, 0x80, 0x00, 0x80, 0x10, 0x80]);
+    let mut title = b"Test Tune".to_vec();
+    title.resize(32, 0);
+    nsf.extend_from_slice(&title);
+    nsf.resize(0x6E, 0);
+    nsf.extend_from_slice(&16639u16.to_le_bytes());
+    nsf.extend_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    nsf.extend_from_slice(&19997u16.to_le_bytes());
+    nsf.extend_from_slice(&[0x00, 0x01, 0, 0, 0, 0]);
+    let mut data = vec![0x8D, 0x00, 0x03, 0xA9, 0x8F, 0x8D, 0x00, 0x90, 0xA9, 0x80, 0x8D, 0x02, 0x90, 0x60];
+    data.resize(0x10, 0);
+    data.extend_from_slice(&[0xEE, 0x01, 0x03, 0x60]);
+    data.resize(0x1000, 0);
+    data.push(0x5A);
+    data.resize(0x8000, 0);
+    nsf.extend_from_slice(&data);
+    let path = std::env::temp_dir().join(format!("coral_{}_{}.nsf", name, std::process::id()));
+    fs::write(&path, nsf).unwrap();
+    path
+}
+
+#[test]
+fn nsf_player_calls_init_and_play() {
+    let path = build_nsf("nsf_player");
+    let mut nes = bus::load_nsf(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    let info = nes.cart.nsf.clone().unwrap();
+    assert_eq!(info.title, "Test Tune");
+    assert_eq!(info.tracks, 3);
+    assert_eq!(nes.cart.track(), Some(1));
+
+    for _ in 0..10 {
+        nes.frame();
+    }
+    assert_eq!(nes.data.cpu_ram[0x300], 1);
+    let plays = nes.data.cpu_ram[0x301];
+    assert!((9..=10).contains(&plays));
+    assert!(nes.cart.audio_output() > 0.0);
+
+    assert_eq!(nes.read_byte(0x9000), 0x5A);
+    nes.write_byte(0x5FF9, 0x00);
+    assert_eq!(nes.read_byte(0x9000), 0x8D);
+
+    assert!(nes.cart.select_track(3).is_err());
+    nes.cart.select_track(2).unwrap();
+    for _ in 0..3 {
+        nes.frame();
+    }
+    assert_eq!(nes.data.cpu_ram[0x300], 2);
+    assert!(nes.data.cpu_ram[0x301] <= 3);
+    // Starting over switched the banks back.
+    assert_eq!(nes.read_byte(0x9000), 0x5A);
+}